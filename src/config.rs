@@ -2,11 +2,14 @@
 ///
 /// To avoid silently using unexpected defaults, all values must be defined only in the YAML file.
 /// For detailed information on what every setting does, refer to `.config.yaml`.
+use std::collections::HashMap;
 use std::net::Ipv4Addr;
 
 use eyre::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::conflicts::ArticleLayout;
+
 pub const DEFAULT_FILE_NAME: &str = "config.yaml";
 pub const STDERR_LOG_FILE: &str = "-";
 
@@ -51,6 +54,27 @@ pub struct GitHub {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Controller {
     pub post_comments: bool,
+    pub original_languages: Vec<String>,
+    pub include_drafts: bool,
+    pub skip_same_author: bool,
+    pub known_languages: Vec<String>,
+    pub extensions: Vec<String>,
+    pub case_insensitive_paths: bool,
+    pub ignore_labels: Vec<String>,
+    pub line_level_overlaps: bool,
+    pub line_hints: bool,
+    pub mixed_language_advisories: bool,
+    pub max_inline_files: usize,
+    pub include_paths: Vec<String>,
+    pub exclude_paths: Vec<String>,
+    pub default_branch_baseline: bool,
+    pub asset_conflicts: bool,
+    pub localized_comments: bool,
+    pub stale_pull_days: u32,
+    pub article_layouts: HashMap<String, ArticleLayout>,
+    pub max_files: usize,
+    pub comment_footer: bool,
+    pub feedback_url: String,
 }
 
 // Unfortunate copypaste: https://serde.rs/remote-derive.html
@@ -95,6 +119,30 @@ mod tests {
             },
             controller: Controller {
                 post_comments: true,
+                original_languages: vec!["en".to_string()],
+                include_drafts: false,
+                skip_same_author: false,
+                known_languages: Vec::new(),
+                extensions: vec![".md".to_string()],
+                case_insensitive_paths: false,
+                ignore_labels: Vec::new(),
+                line_level_overlaps: false,
+                line_hints: false,
+                mixed_language_advisories: false,
+                max_inline_files: 10,
+                include_paths: Vec::new(),
+                exclude_paths: Vec::new(),
+                default_branch_baseline: false,
+                asset_conflicts: false,
+                localized_comments: false,
+                stale_pull_days: 0,
+                article_layouts: HashMap::from([(
+                    "ppy/osu-wiki".to_string(),
+                    ArticleLayout::FileName,
+                )]),
+                max_files: 3000,
+                comment_footer: false,
+                feedback_url: "https://github.com/Walavouchey/observatory/issues".to_string(),
             },
         };
         assert_eq!(settings, template);
@@ -1,16 +1,23 @@
 /// `controller` contains core logic of the app. Refer to [`Controller`] for more details.
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use eyre::Result;
 
 use crate::config;
-use crate::github::{GitHub, GitHubInterface};
-use crate::helpers::comments::CommentHeader;
+use crate::github::{self, GitHubInterface};
+use crate::helpers::comments::{self, CommentHeader};
 use crate::helpers::conflicts::{self, ConflictType};
-use crate::helpers::ToMarkdown;
 use crate::structs::IssueComment;
 use crate::{memory, structs};
 
+/// Files of the default branch fetched while adding a pull, see [`Controller::apply_baseline`].
+#[derive(Debug, Default)]
+struct BaseFiles {
+    contents: HashMap<String, String>,
+    /// Paths which failed to be fetched.
+    unavailable: HashSet<String>,
+}
+
 /// Controller is a representation of a GitHub App, which contains a per-repository cache of
 /// pull requests and corresponding `.diff` files.
 ///
@@ -62,12 +69,21 @@ impl<T: GitHubInterface> Controller<T> {
         self.github.update_cached_installation(installation);
     }
 
+    /// Update the list of repositories of a cached installation after handling an `installation_repositories` event.
+    /// This doesn't fetch or forget pull requests -- see [`Controller::add_repository`] and [`Controller::remove_repository`].
+    pub fn update_installation_repositories(
+        &self,
+        evt: &structs::InstallationRepositoriesEvent,
+    ) -> structs::Installation {
+        self.github.apply_installation_repositories_event(evt)
+    }
+
     /// Build the in-memory pull request cache on start-up. This will consume a lot of GitHub API quota,
     /// but fighting a stale database cache is left as an exercise for another day.
     pub async fn init(&mut self) -> Result<()> {
         self.app = Some(self.github.app().await?);
-        let installations = self.github.discover_installations().await?;
-        for i in installations {
+        let discovery = self.github.discover_installations().await?;
+        for i in discovery.added {
             for r in i.repositories {
                 self.add_repository(&r).await?;
             }
@@ -92,9 +108,34 @@ impl<T: GitHubInterface> Controller<T> {
         Ok(())
     }
 
+    /// Stop watching a repository for a while (see [`GitHubInterface::mute_repo`]), forgetting its pull requests.
+    pub fn mute_repository(&self, r: &structs::Repository) {
+        self.github.mute_repo(&r.full_name);
+        self.remove_repository(r);
+    }
+
+    /// Undo [`Controller::mute_repository`], fetching pull requests of the repository again.
+    /// Conflicts which appeared in the meantime are reported like for new pull requests.
+    pub async fn unmute_repository(&self, r: &structs::Repository) -> Result<()> {
+        self.github.unmute_repo(&r.full_name);
+        for p in self.github.pulls(&r.full_name).await? {
+            self.add_pull(&r.full_name, p, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the repositories of an installation again after one of them was renamed or transferred,
+    /// see [`GitHubInterface::refresh_installation`].
+    pub async fn refresh_installation(
+        &self,
+        installation_id: i64,
+    ) -> Result<structs::Installation> {
+        Ok(self.github.refresh_installation(installation_id).await?)
+    }
+
     /// Remove an installation from cache and forget about its pull requests.
-    pub fn remove_installation(&self, installation: structs::Installation) {
-        self.github.remove_installation(&installation);
+    pub async fn remove_installation(&self, installation: structs::Installation) {
+        self.github.remove_installation(&installation).await;
         for r in installation.repositories {
             self.remove_repository(&r);
         }
@@ -116,7 +157,7 @@ impl<T: GitHubInterface> Controller<T> {
     }
 
     /// Handle pull request changes. This includes fetching a `.diff` file from another GitHub domain,
-    /// which may have its own rate limits.
+    /// which may have its own rate limits. The diff is only fetched again if the pull has new commits.
     ///
     /// If `trigger_updates` is set, check if the update conflicts with existing pull requests,
     /// and make its author aware (or other PRs' owners, in rare cases). For details, see [`helpers::conflicts::Storage`].
@@ -126,10 +167,39 @@ impl<T: GitHubInterface> Controller<T> {
         mut new_pull: structs::PullRequest,
         trigger_updates: bool,
     ) -> Result<()> {
-        let diff = self
-            .github
-            .read_pull_diff(full_repo_name, new_pull.number)
-            .await?;
+        if self.github.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, skipping pull #{}",
+                full_repo_name,
+                new_pull.number
+            );
+            return Ok(());
+        }
+        let diff = match self.memory.unchanged_diff(full_repo_name, &new_pull) {
+            Some(diff) => {
+                log::debug!(
+                    "Pull #{} in {} is still at {}, reusing its diff",
+                    new_pull.number,
+                    full_repo_name,
+                    new_pull.head.sha
+                );
+                diff
+            }
+            None => {
+                if new_pull.is_from_fork() {
+                    log::debug!(
+                        "Pull #{} in {} comes from {}",
+                        new_pull.number,
+                        full_repo_name,
+                        new_pull.head_repo_name().unwrap_or("a deleted fork")
+                    );
+                }
+                // The diff lives in the repository the pull is made to, never in the fork.
+                self.github
+                    .read_pull_diff(full_repo_name, new_pull.number)
+                    .await?
+            }
+        };
         new_pull.diff = Some(diff);
         self.memory.insert_pull(full_repo_name, new_pull.clone());
 
@@ -143,9 +213,55 @@ impl<T: GitHubInterface> Controller<T> {
             // Compare the new pull with existing for conflicts.
             // Known conflicts are skipped (same kind + same file set), otherwise memory is updated.
 
+            let compare_config = self.compare_config(full_repo_name);
             let mut pending_updates: HashMap<i32, Vec<conflicts::Conflict>> = HashMap::new();
+            match conflicts::check_pull(&new_pull, &compare_config) {
+                Ok(Some(conflict)) => {
+                    if let Some(updated_conflict) = self.conflicts.upsert(full_repo_name, &conflict)
+                    {
+                        pending_updates
+                            .entry(updated_conflict.trigger)
+                            .or_default()
+                            .push(updated_conflict);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!(
+                    "Failed to check pull #{} in {}, skipping: {:?}",
+                    new_pull.number,
+                    full_repo_name,
+                    e
+                ),
+            }
+            let mut base_files = BaseFiles::default();
             for other_pull in pulls {
-                let conflicts = conflicts::compare_pulls(&new_pull, &other_pull);
+                let conflicts =
+                    match conflicts::compare_pulls(&new_pull, &other_pull, &compare_config) {
+                        Ok(conflicts) => conflicts,
+                        Err(e) => {
+                            log::warn!(
+                                "Failed to compare pulls #{} and #{} in {}, skipping: {:?}",
+                                new_pull.number,
+                                other_pull.number,
+                                full_repo_name,
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                let conflicts = if compare_config.default_branch_baseline {
+                    self.apply_baseline(
+                        full_repo_name,
+                        &new_pull,
+                        &other_pull,
+                        conflicts,
+                        &mut base_files,
+                        &compare_config,
+                    )
+                    .await
+                } else {
+                    conflicts
+                };
                 for conflict in conflicts {
                     if let Some(updated_conflict) = self.conflicts.upsert(full_repo_name, &conflict)
                     {
@@ -163,6 +279,75 @@ impl<T: GitHubInterface> Controller<T> {
         Ok(())
     }
 
+    /// Fetch overlapping files from the default branch, and drop those which one of the pulls doesn't really change
+    /// (see [`conflicts::filter_by_baseline`]). Fetched files are kept in `base_files` for comparisons with other pulls,
+    /// and so are the ones which can't be fetched (e.g. added by the pulls), so that they aren't requested again.
+    /// Those are kept in the conflict.
+    async fn apply_baseline(
+        &self,
+        full_repo_name: &str,
+        new_pull: &structs::PullRequest,
+        other_pull: &structs::PullRequest,
+        conflicts: Vec<conflicts::Conflict>,
+        base_files: &mut BaseFiles,
+        compare_config: &conflicts::CompareConfig,
+    ) -> Vec<conflicts::Conflict> {
+        let default_branch = match new_pull
+            .base
+            .repo
+            .as_ref()
+            .and_then(|r| r.default_branch.as_ref())
+        {
+            Some(default_branch) => default_branch,
+            None => return conflicts,
+        };
+        let mut out = Vec::new();
+        for conflict in conflicts {
+            if conflict.kind == ConflictType::Overlap {
+                for path in conflict.file_set.iter() {
+                    let is_added = conflict.file_changes.iter().any(|c| {
+                        &c.path == path
+                            && (c.trigger == conflicts::ChangeKind::Added
+                                || c.original == conflicts::ChangeKind::Added)
+                    });
+                    if is_added
+                        || base_files.contents.contains_key(path)
+                        || base_files.unavailable.contains(path)
+                    {
+                        continue;
+                    }
+                    match self
+                        .github
+                        .read_file(full_repo_name, path, default_branch)
+                        .await
+                    {
+                        Ok(contents) => {
+                            base_files.contents.insert(path.clone(), contents);
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "Failed to fetch {} from {} in {}, comparing pulls without it: {:?}",
+                                path,
+                                default_branch,
+                                full_repo_name,
+                                e
+                            );
+                            base_files.unavailable.insert(path.clone());
+                        }
+                    }
+                }
+            }
+            out.extend(conflicts::filter_by_baseline(
+                conflict,
+                new_pull,
+                other_pull,
+                &base_files.contents,
+                compare_config,
+            ));
+        }
+        out
+    }
+
     /// Notify pull request authors about conflicts by sending a comment for every
     /// `(conflict source, conflict type)` combination.
     ///
@@ -176,14 +361,10 @@ impl<T: GitHubInterface> Controller<T> {
         full_repo_name: &str,
     ) -> Result<()> {
         for (pull_to_notify, updates) in pending.into_iter() {
-            let existing_comments = self
-                .github
-                .list_comments(full_repo_name, pull_to_notify)
-                .await?
-                .into_iter()
-                .filter(|c| self.has_control_over(&c.user));
-            let mut pull_references: HashMap<(i32, ConflictType), IssueComment> = HashMap::new();
-            for c in existing_comments {
+            let render_config = self.render_config(full_repo_name, pull_to_notify);
+            let existing_comments = self.own_comments(full_repo_name, pull_to_notify).await?;
+            let mut pull_references: HashMap<(i32, ConflictType), &IssueComment> = HashMap::new();
+            for c in &existing_comments {
                 if let Some(header) = CommentHeader::from_comment(&c.body) {
                     pull_references.insert((header.pull_number, header.conflict_type), c);
                 }
@@ -195,7 +376,11 @@ impl<T: GitHubInterface> Controller<T> {
                     if self.config.post_comments {
                         if let Err(e) = self
                             .github
-                            .update_comment(full_repo_name, existing_comment.id, u.to_markdown())
+                            .update_comment(
+                                full_repo_name,
+                                existing_comment.id,
+                                u.to_markdown_with(&render_config),
+                            )
                             .await
                         {
                             log::error!(
@@ -203,39 +388,45 @@ impl<T: GitHubInterface> Controller<T> {
                                 existing_comment.id,
                                 u.original,
                                 u.kind,
-                                GitHub::pull_url(full_repo_name, pull_to_notify),
+                                self.github.urls().pull_url(full_repo_name, pull_to_notify),
                                 e
                             );
                         }
                     } else {
-                        log::debug!(
-                            "Would update comment #{} about pull #{} of kind {:?} in {}",
+                        log::info!(
+                            "Dry run: would update comment #{} about pull #{} of kind {:?} in {}:\n{}",
                             existing_comment.id,
                             u.original,
                             u.kind,
-                            GitHub::pull_url(full_repo_name, pull_to_notify),
+                            self.github.urls().pull_url(full_repo_name, pull_to_notify),
+                            u.to_markdown_with(&render_config)
                         );
                     }
                 } else if self.config.post_comments {
                     if let Err(e) = self
-                        .github
-                        .post_comment(full_repo_name, pull_to_notify, u.to_markdown())
+                        .post_comment_once(
+                            full_repo_name,
+                            pull_to_notify,
+                            &existing_comments,
+                            u.to_markdown_with(&render_config),
+                        )
                         .await
                     {
                         log::error!(
                             "Failed to post a NEW comment about pull #{} of kind {:?} in {}: {:?}",
                             u.original,
                             u.kind,
-                            GitHub::pull_url(full_repo_name, pull_to_notify),
+                            self.github.urls().pull_url(full_repo_name, pull_to_notify),
                             e
                         );
                     }
                 } else {
-                    log::debug!(
-                        "Would post a NEW comment about #{} of kind {:?} in {}",
+                    log::info!(
+                        "Dry run: would post a NEW comment about #{} of kind {:?} in {}:\n{}",
                         u.original,
                         u.kind,
-                        GitHub::pull_url(full_repo_name, pull_to_notify),
+                        self.github.urls().pull_url(full_repo_name, pull_to_notify),
+                        u.to_markdown_with(&render_config)
                     );
                 }
             }
@@ -243,11 +434,112 @@ impl<T: GitHubInterface> Controller<T> {
         Ok(())
     }
 
-    /// A helper for checking if the comment is made by the bot itself.
+    /// Post a comment, unless the app has already left one with the same text or about the same conflict
+    /// (see [`comments::find_duplicate`]) among `existing_comments`. These should be fetched right before posting
+    /// (once per pull, see [`Controller::send_updates`]), so that several instances of the app handling the same event
+    /// don't post duplicates. Returns whether the comment was posted.
+    async fn post_comment_once(
+        &self,
+        full_repo_name: &str,
+        issue_number: i32,
+        existing_comments: &[IssueComment],
+        body: String,
+    ) -> Result<bool> {
+        if let Some(duplicate) = comments::find_duplicate(existing_comments, &body) {
+            log::info!(
+                "Comment #{} in {} is the same as the new one, skipping",
+                duplicate.id,
+                self.github.urls().pull_url(full_repo_name, issue_number)
+            );
+            return Ok(false);
+        }
+        let Some(comment) = self
+            .github
+            .post_comment(full_repo_name, issue_number, body)
+            .await?
+        else {
+            return Ok(false);
+        };
+        log::debug!("Posted comment #{}: {}", comment.id, comment.html_url);
+        Ok(true)
+    }
+
+    /// Comments left by the app on an issue or a pull request. Together with [`GitHubInterface::delete_comment`] and
+    /// [`GitHubInterface::update_comment`], this can be used to clean up comments about conflicts which no longer exist.
+    pub async fn own_comments(
+        &self,
+        full_repo_name: &str,
+        issue_number: i32,
+    ) -> Result<Vec<IssueComment>> {
+        Ok(self
+            .github
+            .list_comments(full_repo_name, issue_number)
+            .await?
+            .into_iter()
+            .filter(|c| self.has_control_over(&c.user))
+            .collect())
+    }
+
+    /// Settings for comparing pulls in a repository, taken from the controller config.
+    fn compare_config(&self, full_repo_name: &str) -> conflicts::CompareConfig {
+        conflicts::CompareConfig {
+            original_languages: self.config.original_languages.clone(),
+            include_drafts: self.config.include_drafts,
+            skip_same_author: self.config.skip_same_author,
+            known_languages: self.config.known_languages.clone(),
+            extensions: self.config.extensions.clone(),
+            case_insensitive_paths: self.config.case_insensitive_paths,
+            ignore_labels: self.config.ignore_labels.clone(),
+            line_level_overlaps: self.config.line_level_overlaps,
+            line_hints: self.config.line_hints,
+            mixed_language_advisories: self.config.mixed_language_advisories,
+            include_paths: self.config.include_paths.clone(),
+            exclude_paths: self.config.exclude_paths.clone(),
+            default_branch_baseline: self.config.default_branch_baseline,
+            asset_conflicts: self.config.asset_conflicts,
+            stale_after: (self.config.stale_pull_days > 0)
+                .then(|| chrono::Duration::days(self.config.stale_pull_days.into())),
+            article_layout: self
+                .config
+                .article_layouts
+                .get(full_repo_name)
+                .copied()
+                .unwrap_or_default(),
+            max_files: self.config.max_files,
+        }
+    }
+
+    /// Settings for rendering comments on a pull, taken from the controller config. If comments are localized,
+    /// they are written in the language of translations changed by the pull (see [`conflicts::translation_language`]).
+    fn render_config(&self, full_repo_name: &str, pull_number: i32) -> conflicts::RenderConfig {
+        let language = if self.config.localized_comments {
+            self.memory
+                .pulls(full_repo_name)
+                .and_then(|pulls| pulls.get(&pull_number).cloned())
+                .and_then(|pull| {
+                    conflicts::translation_language(&pull, &self.compare_config(full_repo_name))
+                })
+        } else {
+            None
+        };
+        conflicts::RenderConfig {
+            max_inline_files: self.config.max_inline_files,
+            language,
+            footer: self
+                .config
+                .comment_footer
+                .then(|| comments::footer(&self.config.feedback_url)),
+        }
+    }
+
+    /// A helper for checking if the comment is made by the bot itself: its author is either the configured bot login
+    /// (see [`GitHubInterface::bot_login`]), or the app's own bot account.
     ///
     /// Curiously, there is no way of telling this from the comment's JSON.
     fn has_control_over(&self, user: &structs::Actor) -> bool {
-        if let Some(app) = &self.app {
+        if let Some(bot_login) = self.github.bot_login() {
+            github::is_bot_login(&user.login, &bot_login)
+        } else if let Some(app) = &self.app {
             user.login == format!("{}[bot]", &app.slug)
         } else {
             false
@@ -1,7 +1,8 @@
 use super::*;
 
 use crate::helpers::conflicts::Conflict;
-use crate::test::{self, pull_link};
+use crate::helpers::ToMarkdown;
+use crate::test::{self, pull_link, PULL_TITLE};
 
 async fn make_controller(init: bool) -> Controller<test::DummyGitHubClient> {
     let mut c = Controller::<test::DummyGitHubClient>::new(
@@ -9,6 +10,27 @@ async fn make_controller(init: bool) -> Controller<test::DummyGitHubClient> {
         "private-key".to_string(),
         crate::config::Controller {
             post_comments: true,
+            original_languages: vec!["en".to_string()],
+            include_drafts: false,
+            skip_same_author: false,
+            known_languages: Vec::new(),
+            extensions: vec![".md".to_string()],
+            case_insensitive_paths: false,
+            ignore_labels: Vec::new(),
+            line_level_overlaps: false,
+            line_hints: false,
+            mixed_language_advisories: false,
+            max_inline_files: 10,
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            default_branch_baseline: false,
+            asset_conflicts: false,
+            localized_comments: false,
+            stale_pull_days: 0,
+            article_layouts: HashMap::new(),
+            max_files: conflicts::DEFAULT_MAX_FILES,
+            comment_footer: false,
+            feedback_url: String::new(),
         },
     );
     if init {
@@ -17,6 +39,22 @@ async fn make_controller(init: bool) -> Controller<test::DummyGitHubClient> {
     c
 }
 
+#[tokio::test]
+async fn test_has_control_over_custom_login() {
+    let c = make_controller(true).await;
+    c.github.test_set_bot_login("observatory");
+    let actor = |login: &str| structs::Actor {
+        id: 1,
+        login: login.to_string(),
+    };
+
+    assert!(c.has_control_over(&actor("observatory[bot]")));
+    assert!(c.has_control_over(&actor("Observatory")));
+    // The configured login replaces the one derived from the app.
+    assert!(!c.has_control_over(&actor("test-app[bot]")));
+    assert!(!c.has_control_over(&actor("observatory-dev[bot]")));
+}
+
 #[tokio::test]
 async fn test_has_control_over() {
     let c = make_controller(true).await;
@@ -67,6 +105,7 @@ async fn test_simple_overlap_originals() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()]
         )]
     );
@@ -90,6 +129,7 @@ async fn test_simple_overlap_translations() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/ru.md".to_string()]
         )]
     );
@@ -127,6 +167,7 @@ async fn test_simple_early_incomplete_translation() {
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()]
         )]
     );
@@ -151,6 +192,7 @@ async fn test_simple_late_incomplete_translation() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()]
         )]
     );
@@ -174,6 +216,7 @@ async fn test_multiple_overlapping_changes() {
             trigger,
             original,
             pull_link("test/repo", original),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()],
         )
     };
@@ -211,6 +254,7 @@ async fn test_multiple_incomplete_translations() {
             trigger,
             original,
             pull_link("test/repo", original),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()],
         )
     };
@@ -248,6 +292,7 @@ async fn test_incomplete_translation_multiple_conflicts() {
             trigger,
             original,
             pull_link("test/repo", original),
+            PULL_TITLE.to_string(),
             vec![format!("wiki/Article/en.md")],
         )
     };
@@ -294,6 +339,7 @@ async fn test_overlap_no_extra_files_on_update() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/ru.md".to_string()]
         )]
     );
@@ -318,7 +364,7 @@ async fn test_overlap_file_set_update_in_trigger_recognized() {
         1,
         &["wiki/Article/ru.md", "wiki/Other_article/en.md"],
     );
-    c.add_pull("test/repo", pulls[0].clone(), false)
+    c.add_pull("test/repo", c.github.fetch_pull("test/repo", 1), false)
         .await
         .unwrap();
 
@@ -329,6 +375,7 @@ async fn test_overlap_file_set_update_in_trigger_recognized() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/ru.md".to_string(),
                 "wiki/Other_article/en.md".to_string()
@@ -378,6 +425,7 @@ async fn test_overlap_double_update_recognized() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/ru.md".to_string(),
                 "wiki/Other_article/en.md".to_string()
@@ -419,6 +467,7 @@ async fn test_early_incomplete_translation_update_no_unrelated_files() {
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()]
         )]
     );
@@ -460,6 +509,7 @@ async fn test_incomplete_translation_original_update_recognized() {
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/en.md".to_string(),
                 "wiki/Other_article/en.md".to_string()
@@ -509,6 +559,7 @@ async fn test_incomplete_translation_double_update() {
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/en.md".to_string(),
                 "wiki/Other_article/en.md".to_string()
@@ -550,6 +601,7 @@ async fn test_late_incomplete_translation_update_no_extra_files() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()]
         )]
     );
@@ -591,6 +643,7 @@ async fn test_incomplete_translation_update_recognized() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/en.md".to_string(),
                 "wiki/Other_article/en.md".to_string()
@@ -624,6 +677,7 @@ async fn test_outdated_translation_produces_single_conflict() {
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string(),]
         )]
     );
@@ -664,18 +718,21 @@ async fn test_three_conflicts_at_once() {
                 3,
                 2,
                 pull_link("test/repo", 2),
+                PULL_TITLE.to_string(),
                 vec!["wiki/Other_article/ru.md".to_string(),]
             ),
             Conflict::incomplete_translation(
                 3,
                 1,
                 pull_link("test/repo", 1),
+                PULL_TITLE.to_string(),
                 vec!["wiki/Article/en.md".to_string(),]
             ),
             Conflict::incomplete_translation(
                 3,
                 4,
                 pull_link("test/repo", 4),
+                PULL_TITLE.to_string(),
                 vec!["wiki/Different_article/en.md".to_string(),]
             ),
         ]
@@ -1010,3 +1067,326 @@ async fn test_closed_pull_related_conflicts_removed() {
         assert!(c.conflicts.by_trigger("test/repo", p.number).is_empty());
     }
 }
+
+#[tokio::test]
+async fn test_dry_run_posts_nothing() {
+    let mut c = make_controller(true).await;
+    c.config.post_comments = false;
+    let p1 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    let p2 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+
+    for p in [&p1, &p2] {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+
+    // Conflicts are still detected, they just aren't announced.
+    assert_eq!(c.conflicts.by_trigger("test/repo", p2.number).len(), 1);
+    let comments = c
+        .github
+        .list_comments("test/repo", p2.number)
+        .await
+        .unwrap();
+    assert!(comments.is_empty());
+}
+
+#[tokio::test]
+async fn test_own_comments_deleted() {
+    let c = make_controller(true).await;
+    let p1 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    let p2 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    for p in [&p1, &p2] {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+
+    let own = c.own_comments("test/repo", p2.number).await.unwrap();
+    assert_eq!(own.len(), 1);
+    c.github
+        .delete_comment("test/repo", own[0].id)
+        .await
+        .unwrap();
+
+    assert!(c
+        .own_comments("test/repo", p2.number)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(c
+        .github
+        .delete_comment("test/repo", own[0].id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_mixed_language_change_comment() {
+    let mut c = make_controller(true).await;
+    c.config.mixed_language_advisories = true;
+    let p = c
+        .github
+        .test_add_pull("test/repo", &["wiki/Article/en.md", "wiki/Article/ru.md"]);
+
+    c.add_pull(
+        "test/repo",
+        c.github.fetch_pull("test/repo", p.number),
+        true,
+    )
+    .await
+    .unwrap();
+
+    let comments = c.github.list_comments("test/repo", p.number).await.unwrap();
+    assert_eq!(comments.len(), 1);
+    let header = CommentHeader::from_comment(&comments[0].body).unwrap();
+    assert_eq!(header.pull_number, p.number);
+    assert_eq!(header.conflict_type, ConflictType::MixedLanguageChange);
+}
+
+#[tokio::test]
+async fn test_default_branch_baseline() {
+    let mut c = make_controller(true).await;
+    c.config.default_branch_baseline = true;
+    let article = "wiki/Article/en.md";
+    // Matches the result of every pull made by the dummy client.
+    c.github.test_add_file(
+        "test/repo",
+        article,
+        "# Article\n\n## Test article\n\n<!-- test -->\nDo whatever you want.\n\nThat's it, that's the article.\n",
+    );
+    let p1 = c.github.test_add_pull("test/repo", &[article]);
+    let p2 = c
+        .github
+        .test_add_pull("test/repo", &[article, "wiki/Other/en.md"]);
+    let p3 = c.github.test_add_pull("test/repo", &["wiki/Other/en.md"]);
+
+    for p in [p1, p2, p3] {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+
+    // The article is already up to date, but the other file couldn't be fetched and still counts.
+    assert!(c
+        .github
+        .list_comments("test/repo", 2)
+        .await
+        .unwrap()
+        .is_empty());
+    let comments = c.github.list_comments("test/repo", 3).await.unwrap();
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].body.contains("wiki/Other/en.md"));
+}
+
+#[tokio::test]
+async fn test_post_comment_once() {
+    let c = make_controller(true).await;
+    let header = CommentHeader {
+        pull_number: 1,
+        conflict_type: ConflictType::Overlap,
+    };
+    let comment_body = |text: &str| {
+        format!(
+            "{}\n{}\n{}",
+            header.to_markdown(),
+            header.tracking_marker(2).to_markdown(),
+            text
+        )
+    };
+    let body = comment_body("Files changed.");
+
+    assert!(c
+        .post_comment_once(
+            "test/repo",
+            2,
+            &c.own_comments("test/repo", 2).await.unwrap(),
+            body.clone()
+        )
+        .await
+        .unwrap());
+    assert!(!c
+        .post_comment_once(
+            "test/repo",
+            2,
+            &c.own_comments("test/repo", 2).await.unwrap(),
+            body.clone()
+        )
+        .await
+        .unwrap());
+    // Another instance may have posted a slightly different version of the same comment.
+    let updated_body = comment_body("More files changed.");
+    assert!(!c
+        .post_comment_once(
+            "test/repo",
+            2,
+            &c.own_comments("test/repo", 2).await.unwrap(),
+            updated_body
+        )
+        .await
+        .unwrap());
+    assert!(c
+        .post_comment_once(
+            "test/repo",
+            3,
+            &c.own_comments("test/repo", 3).await.unwrap(),
+            body
+        )
+        .await
+        .unwrap());
+
+    assert_eq!(
+        c.github.list_comments("test/repo", 2).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_localized_comments() {
+    let mut c = make_controller(true).await;
+    c.config.localized_comments = true;
+    let original = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    let translation = c.github.test_add_pull("test/repo", &["wiki/Article/fr.md"]);
+
+    for p in [original, translation.clone()] {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+
+    let comments = c
+        .github
+        .list_comments("test/repo", translation.number)
+        .await
+        .unwrap();
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0]
+        .body
+        .contains(crate::helpers::comments::FRENCH_TEMPLATES.incomplete_translation));
+}
+
+#[tokio::test]
+async fn test_comment_footer() {
+    let mut c = make_controller(true).await;
+    c.config.comment_footer = true;
+    c.config.feedback_url = "https://example.com/issues".to_string();
+    let footer = crate::helpers::comments::footer("https://example.com/issues");
+    let pulls: Vec<_> = (0..3)
+        .map(|_| c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]))
+        .collect();
+    for p in &pulls {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+
+    // The last pull overlaps with both earlier ones, and each comment has its own footer.
+    let comments = c
+        .github
+        .list_comments("test/repo", pulls[2].number)
+        .await
+        .unwrap();
+    assert_eq!(comments.len(), 2);
+    for comment in comments {
+        assert_eq!(comment.body.matches(&footer).count(), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_unchanged_pull_diff_reused() {
+    let c = make_controller(true).await;
+    // The dummy client doesn't know this pull, so its diff can't be downloaded.
+    let pull = test::make_pull(100, &["wiki/Article/en.md"]);
+    c.memory.insert_pull("test/repo", pull.clone());
+
+    let mut unchanged = pull.clone();
+    unchanged.diff = None;
+    unchanged.updated_at = chrono::Utc::now();
+    c.add_pull("test/repo", unchanged, false).await.unwrap();
+    assert!(c.memory.pulls("test/repo").unwrap()[&100].diff.is_some());
+
+    let mut new_commit = pull.clone();
+    new_commit.diff = None;
+    new_commit.head.sha = "5483f282a0a".to_string();
+    assert!(c.add_pull("test/repo", new_commit, false).await.is_err());
+}
+
+#[tokio::test]
+async fn test_muted_repository() {
+    let c = make_controller(true).await;
+    let repo = test::make_ref("master", "test/repo").repo.unwrap();
+    c.mute_repository(&repo);
+
+    // Conflicting pulls opened while muted are neither tracked nor commented on...
+    let p1 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    let p2 = c.github.test_add_pull("test/repo", &["wiki/Article/en.md"]);
+    for p in [&p1, &p2] {
+        c.add_pull(
+            "test/repo",
+            c.github.fetch_pull("test/repo", p.number),
+            true,
+        )
+        .await
+        .unwrap();
+    }
+    c.add_repository(&repo).await.unwrap();
+    assert!(c.memory.pulls("test/repo").is_none());
+
+    // ...but are picked up again, and their conflict reported, once unmuted.
+    c.unmute_repository(&repo).await.unwrap();
+    let pulls = c.memory.pulls("test/repo").unwrap();
+    assert!(pulls.contains_key(&p1.number) && pulls.contains_key(&p2.number));
+    assert!(c
+        .github
+        .list_comments("test/repo", p1.number)
+        .await
+        .unwrap()
+        .is_empty());
+    let comments = c
+        .github
+        .list_comments("test/repo", p2.number)
+        .await
+        .unwrap();
+    assert_eq!(comments.len(), 1);
+    assert!(comments[0].body.contains(&format!("#{}", p1.number)));
+}
+
+#[tokio::test]
+async fn test_refresh_installation() {
+    let c = make_controller(true).await;
+    let repo = test::make_ref("master", "test/repo").repo.unwrap();
+    c.update_cached_installation(structs::Installation {
+        id: 1,
+        account: structs::Actor {
+            id: 1,
+            login: "test".to_string(),
+        },
+        app_id: 123,
+        repositories: vec![repo],
+        suspended_at: None,
+    });
+
+    let refreshed = c.refresh_installation(1).await.unwrap();
+    assert_eq!(refreshed.repositories[0].full_name, "test/repo");
+    assert!(c.refresh_installation(2).await.is_err());
+}
@@ -0,0 +1,91 @@
+/// `error` contains the error type returned by the GitHub client, see [`ObservatoryError`].
+use std::fmt;
+
+/// Shorthand for results of [`crate::github::GitHubInterface`] methods.
+pub type Result<T, E = ObservatoryError> = std::result::Result<T, E>;
+
+/// Errors which callers may want to handle differently (e.g. wait for the rate limit to reset, or skip a broken pull).
+/// Everything else ends up in [`ObservatoryError::Other`].
+///
+/// Converts into [`eyre::Report`] with `?`, so it can be used in functions returning `eyre::Result` as usual.
+#[derive(Debug)]
+pub enum ObservatoryError {
+    /// None of the known installations has access to the repository.
+    NoTokenForRepo(String),
+
+    /// The rate limit has been exhausted, and requests won't succeed until it resets.
+    RateLimited {
+        reset: chrono::DateTime<chrono::Utc>,
+    },
+
+    /// The request couldn't be sent or its response couldn't be read (DNS, connection, timeouts).
+    Http(reqwest::Error),
+
+    /// The diff of a pull request was downloaded, but it isn't valid unified diff text.
+    DiffParse {
+        full_repo_name: String,
+        pull_number: i32,
+        reason: String,
+    },
+
+    Other(eyre::Report),
+}
+
+impl fmt::Display for ObservatoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObservatoryError::NoTokenForRepo(full_repo_name) => {
+                write!(f, "No GitHub token for {} found", full_repo_name)
+            }
+            ObservatoryError::RateLimited { reset } => {
+                write!(f, "Rate limit exceeded, resets at {}", reset)
+            }
+            ObservatoryError::Http(e) => write!(f, "HTTP request failed: {}", e),
+            ObservatoryError::DiffParse {
+                full_repo_name,
+                pull_number,
+                reason,
+            } => write!(
+                f,
+                "Failed to parse diff of pull #{} in {}: {}",
+                pull_number, full_repo_name, reason
+            ),
+            ObservatoryError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ObservatoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObservatoryError::Http(e) => Some(e),
+            ObservatoryError::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ObservatoryError {
+    fn from(e: reqwest::Error) -> Self {
+        ObservatoryError::Http(e)
+    }
+}
+
+/// Internally, the client passes errors around as [`eyre::Report`]. Typed errors wrapped into a report along the way
+/// are recovered here, so that they aren't hidden behind [`ObservatoryError::Other`].
+impl From<eyre::Report> for ObservatoryError {
+    fn from(report: eyre::Report) -> Self {
+        let report = match report.downcast::<ObservatoryError>() {
+            Ok(e) => return e,
+            Err(report) => report,
+        };
+        match report.downcast::<reqwest::Error>() {
+            Ok(e) => ObservatoryError::Http(e),
+            Err(report) => ObservatoryError::Other(report),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "error_test.rs"]
+pub(crate) mod tests;
@@ -0,0 +1,16 @@
+use super::*;
+
+#[test]
+fn typed_errors_survive_eyre() {
+    let report: eyre::Report = ObservatoryError::NoTokenForRepo("ppy/osu-wiki".to_string()).into();
+    assert!(matches!(
+        ObservatoryError::from(report),
+        ObservatoryError::NoTokenForRepo(repo) if repo == "ppy/osu-wiki"
+    ));
+
+    let report = eyre::eyre!("something else");
+    assert!(matches!(
+        ObservatoryError::from(report),
+        ObservatoryError::Other(_)
+    ));
+}
@@ -3,23 +3,78 @@
 use std::str::FromStr;
 use std::time::Duration;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
+use chrono::TimeZone;
 
 use serde::Deserialize;
 use serde::Serialize;
 
 use eyre::Result;
+
+use crate::error::{self, ObservatoryError};
 use unidiff;
 
+use crate::helpers::conflicts;
 use crate::structs;
 
 const GITHUB_API_ROOT: &str = "https://api.github.com";
 const GITHUB_ROOT: &str = "https://github.com";
 
-const RETRYABLE_ERRORS: [u16; 4] = [429, 500, 502, 503];
+/// Default number of attempts made for a single API request, see [`Client::with_max_attempts`].
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 3;
+
+/// Default limit for a whole request (connection, sending, and reading the response), see [`Client::with_timeouts`].
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default limit for establishing a connection, see [`Client::with_timeouts`].
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default limit for requests in flight at the same time, shared by all operations of a client (and its clones),
+/// see [`Client::with_max_concurrent_requests`].
+pub const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// How many `.diff` files are downloaded at the same time by [`Client::load_diffs`].
+pub const DIFF_CONCURRENCY: usize = 4;
+
+/// How many comments are posted at the same time by [`Client::post_comments`]. GitHub asks to create content
+/// one request at a time, so this is kept low to stay clear of secondary rate limits.
+pub const COMMENT_CONCURRENCY: usize = 2;
+
+/// How many installations are set up at the same time by [`GitHubInterface::discover_installations`].
+pub const DISCOVERY_CONCURRENCY: usize = 4;
+
+/// Time limit for setting up a single installation (getting its token and listing its repositories) during discovery.
+pub const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many pages of pulls are kept for conditional requests (see [`Client::pulls_page`]). Once there are more,
+/// the least recently used ones are forgotten.
+const PULLS_CACHE_PAGES: usize = 64;
+
+/// Status codes which are only retried if GitHub tells us when to come back (the `Retry-After` header).
+const RATE_LIMIT_ERRORS: [u16; 2] = [403, 429];
+
+/// Name of check runs created by the app, shown in the list of checks of a pull request.
+pub const CHECK_RUN_NAME: &str = "observatory";
+
+/// Cached tokens are regenerated this long before they actually expire, so that one picked up at the last moment
+/// doesn't run out in the middle of a slow request.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// How long to back off after hitting a secondary rate limit without a `Retry-After` header (as GitHub recommends).
+/// Doubles for every further hit during the cooldown, up to [`MAX_SECONDARY_RATE_LIMIT_BACKOFF`].
+const SECONDARY_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_SECONDARY_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// After backing off, requests are sent one by one at this interval for a while, see [`ThrottleState`].
+const THROTTLED_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// GitHub answers `202 Accepted` with no diff while it's still generating the diff of a large pull request.
+/// The diff is requested up to this many times, waiting [`DIFF_GENERATION_DELAY`] in between.
+const DIFF_GENERATION_ATTEMPTS: i32 = 5;
+const DIFF_GENERATION_DELAY: Duration = Duration::from_secs(2);
 
 const MIN_TIMEOUT: Duration = Duration::from_secs(1);
 const MAX_TIMEOUT: Duration = Duration::from_secs(30);
@@ -28,10 +83,10 @@ const BACKOFF_MP: f32 = 1.2;
 /// Helper for exponential backoff retries. Usage:
 ///
 /// ```ignore
-/// // Allow up to 3 retries and sleep for 1, 1.2, and 1.44s between them.
+/// // Allow up to 3 retries and sleep for about 1, 1.2, and 1.44s between them (plus jitter).
 /// let mut t = ProgressiveTimeout::new(3);
 /// while let None = fetch_data() {
-///     t.sleep();
+///     t.sleep().await;
 ///     if let Err(e) = t.tick() {
 ///         panic!("failed to fetch data: {e:?}")
 ///     }
@@ -80,103 +135,674 @@ impl ProgressiveTimeout {
         Ok(())
     }
 
-    pub fn sleep(&mut self) {
-        std::thread::sleep(self.current_timeout);
-        self.total_time_slept += self.current_timeout;
+    pub async fn sleep(&mut self) {
+        self.sleep_for(with_jitter(self.current_timeout)).await;
+    }
+
+    /// Sleep for an arbitrary duration (e.g. the one requested by the server), keeping track of the total time slept.
+    pub async fn sleep_for(&mut self, timeout: Duration) {
+        tokio::time::sleep(timeout).await;
+        self.total_time_slept += timeout;
+    }
+}
+
+/// Add up to 25% of random-ish jitter to a timeout, so that concurrent retries don't hit the API at the same time.
+fn with_jitter(timeout: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    timeout.mul_f32(1.0 + (nanos % 250) as f32 / 1000.0)
+}
+
+/// Parse the `Retry-After` header. GitHub only uses the delay-seconds form of it.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+/// Server errors are always worth another try, while rate limit errors only make sense to retry
+/// when the server says how long to wait. Anything else (e.g. 404) will fail the same way again.
+fn is_retryable(status: reqwest::StatusCode, retry_after: Option<Duration>) -> bool {
+    status.is_server_error()
+        || (RATE_LIMIT_ERRORS.contains(&status.as_u16()) && retry_after.is_some())
+}
+
+/// GitHub's secondary rate limits (too many concurrent requests, too much CPU time, etc.) are reported with the same
+/// status codes as the primary one, but with requests still remaining and a specific message in the body.
+fn is_secondary_rate_limit(
+    status: reqwest::StatusCode,
+    headers: &HashMap<String, String>,
+    body: &str,
+) -> bool {
+    RATE_LIMIT_ERRORS.contains(&status.as_u16())
+        && headers.get("x-ratelimit-remaining").map(String::as_str) != Some("0")
+        && body.to_lowercase().contains("secondary rate limit")
+}
+
+/// What the client does after hitting a secondary rate limit, see [`Client::throttle_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ThrottleState {
+    /// No requests are sent until then.
+    pub paused_until: Option<std::time::Instant>,
+    /// After the pause, requests are sent one at a time (see [`THROTTLED_REQUEST_INTERVAL`]) until then.
+    pub cooldown_until: Option<std::time::Instant>,
+    /// Length of the last pause.
+    pub backoff: Duration,
+    /// Secondary rate limit hits since the client was last not throttled.
+    pub hits: u32,
+}
+
+impl ThrottleState {
+    pub fn is_throttled(&self) -> bool {
+        self.cooldown_until
+            .is_some_and(|until| until > std::time::Instant::now())
+    }
+}
+
+/// Adaptive backoff for secondary rate limits, shared by all clones of a client.
+#[derive(Debug)]
+struct Throttle {
+    base_backoff: Duration,
+    state: Mutex<ThrottleState>,
+    /// The earliest moment the next request may be sent during a cooldown.
+    next_slot: Mutex<Option<std::time::Instant>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::with_backoff(SECONDARY_RATE_LIMIT_BACKOFF)
+    }
+}
+
+impl Throttle {
+    fn with_backoff(base_backoff: Duration) -> Self {
+        Self {
+            base_backoff,
+            state: Mutex::new(ThrottleState::default()),
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    fn state(&self) -> ThrottleState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Wait until a request may be sent: after the pause, and at least [`THROTTLED_REQUEST_INTERVAL`] after the previous
+    /// one during the cooldown. Doesn't wait at all if the client isn't throttled.
+    async fn wait(&self) {
+        let state = self.state();
+        if !state.is_throttled() {
+            return;
+        }
+        let now = std::time::Instant::now();
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = [Some(now), state.paused_until, *next_slot]
+                .into_iter()
+                .flatten()
+                .max()
+                .unwrap();
+            *next_slot = Some(slot + THROTTLED_REQUEST_INTERVAL);
+            slot
+        };
+        if slot > now {
+            log::info!(
+                "Throttled after {} secondary rate limit hit(s), waiting for {:?}...",
+                state.hits,
+                slot - now
+            );
+            tokio::time::sleep(slot - now).await;
+        }
+    }
+
+    /// Record a secondary rate limit hit, and return how long to pause.
+    fn hit(&self, retry_after: Option<Duration>) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let backoff = match retry_after {
+            Some(retry_after) => retry_after,
+            None if state.is_throttled() => {
+                std::cmp::min(state.backoff * 2, MAX_SECONDARY_RATE_LIMIT_BACKOFF)
+            }
+            None => self.base_backoff,
+        };
+        let now = std::time::Instant::now();
+        *state = ThrottleState {
+            paused_until: Some(now + backoff),
+            // Keep sending requests carefully for as long as the pause itself.
+            cooldown_until: Some(now + backoff * 2),
+            backoff,
+            hits: if state.is_throttled() {
+                state.hits + 1
+            } else {
+                1
+            },
+        };
+        *self.next_slot.lock().unwrap() = None;
+        backoff
+    }
+}
+
+/// Check if a login (e.g. of a comment author) belongs to the bot. Logins are case-insensitive,
+/// and a plain name like `observatory` also matches the `observatory[bot]` account of a GitHub App.
+pub fn is_bot_login(login: &str, bot_login: &str) -> bool {
+    login.eq_ignore_ascii_case(bot_login)
+        || login
+            .strip_suffix("[bot]")
+            .is_some_and(|name| name.eq_ignore_ascii_case(bot_login))
+}
+
+/// Check the `X-Hub-Signature-256` header of a webhook delivery against the HMAC-SHA256 digest of its raw body.
+///
+/// The header is expected to look like `sha256=757107ea...`. Comparison is done in constant time.
+pub fn verify_signature(secret: &str, payload: &[u8], signature_header: &str) -> bool {
+    let signature = match signature_header
+        .strip_prefix("sha256=")
+        .and_then(decode_hex)
+    {
+        Some(s) => s,
+        None => return false,
+    };
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    ring::hmac::verify(&key, payload, &signature).is_ok()
+}
+
+/// A webhook delivery, parsed according to its `X-GitHub-Event` header (see [`WebhookEvent::parse`]).
+#[derive(Debug)]
+pub enum WebhookEvent {
+    Ping(structs::PingEvent),
+    PullRequest(Box<structs::PullRequestEvent>),
+    Installation(structs::InstallationEvent),
+    InstallationRepositories(structs::InstallationRepositoriesEvent),
+    IssueComment(Box<structs::IssueCommentEvent>),
+    PullRequestReview(Box<structs::PullRequestReviewEvent>),
+    /// An event the app doesn't handle, with its type. The body isn't parsed.
+    Other(String),
+}
+
+impl WebhookEvent {
+    /// Parse the body of a webhook delivery with the given `X-GitHub-Event` header value.
+    pub fn parse(event_type: &str, body: &str) -> serde_json::Result<Self> {
+        Ok(match event_type {
+            "ping" => Self::Ping(serde_json::from_str(body)?),
+            "pull_request" => Self::PullRequest(Box::new(serde_json::from_str(body)?)),
+            "installation" => Self::Installation(serde_json::from_str(body)?),
+            "installation_repositories" => {
+                Self::InstallationRepositories(serde_json::from_str(body)?)
+            }
+            "issue_comment" => Self::IssueComment(Box::new(serde_json::from_str(body)?)),
+            "pull_request_review" => Self::PullRequestReview(Box::new(serde_json::from_str(body)?)),
+            _ => Self::Other(event_type.to_string()),
+        })
+    }
+}
+
+/// Convert a hex string into bytes, where every two characters describe a byte (high 4 bits | low 4 bits).
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => u8::from_str_radix(std::str::from_utf8(&[*hi, *lo]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Percent-encode a single segment of a URL path, so that file names with spaces, `#` or `?` stay in the path.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// URL builder for GitHub API endpoints and web pages.
+///
+/// Defaults to github.com; GitHub Enterprise Server installations need custom roots, see [`GitHub::new`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHub {
+    api_root: String,
+    root: String,
+}
+
+impl Default for GitHub {
+    fn default() -> Self {
+        Self::new(GITHUB_API_ROOT, GITHUB_ROOT)
     }
 }
 
-pub struct GitHub {}
 impl GitHub {
-    pub fn pulls(full_repo_name: &str) -> String {
-        format!("{GITHUB_API_ROOT}/repos/{full_repo_name}/pulls")
+    /// Create a URL builder with custom roots, for example `https://github.example.com/api/v3`
+    /// and `https://github.example.com` for a GitHub Enterprise Server instance.
+    pub fn new(api_root: &str, root: &str) -> Self {
+        Self {
+            api_root: api_root.trim_end_matches('/').to_string(),
+            root: root.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn pulls(&self, full_repo_name: &str) -> String {
+        format!("{}/repos/{full_repo_name}/pulls", self.api_root)
+    }
+    pub fn pull(&self, full_repo_name: &str, pull_number: i32) -> String {
+        format!(
+            "{}/repos/{full_repo_name}/pulls/{pull_number}",
+            self.api_root
+        )
+    }
+    pub fn pull_files(&self, full_repo_name: &str, pull_number: i32) -> String {
+        format!(
+            "{}/repos/{full_repo_name}/pulls/{pull_number}/files",
+            self.api_root
+        )
+    }
+    pub fn app(&self) -> String {
+        format!("{}/app", self.api_root)
     }
-    pub fn app() -> String {
-        format!("{GITHUB_API_ROOT}/app")
+    pub fn app_installations(&self) -> String {
+        format!("{}/app/installations", self.api_root)
     }
-    pub fn app_installations() -> String {
-        format!("{GITHUB_API_ROOT}/app/installations")
+    pub fn installation_tokens(&self, installation_id: i64) -> String {
+        format!(
+            "{}/app/installations/{installation_id}/access_tokens",
+            self.api_root
+        )
+    }
+    pub fn installation_repos(&self) -> String {
+        format!("{}/installation/repositories", self.api_root)
+    }
+    pub fn comments(&self, full_repo_name: &str, issue_number: i32) -> String {
+        format!(
+            "{}/repos/{full_repo_name}/issues/{issue_number}/comments",
+            self.api_root
+        )
     }
-    pub fn installation_tokens(installation_id: i64) -> String {
-        format!("{GITHUB_API_ROOT}/app/installations/{installation_id}/access_tokens")
+    pub fn reactions(&self, full_repo_name: &str, issue_number: i32) -> String {
+        format!(
+            "{}/repos/{full_repo_name}/issues/{issue_number}/reactions",
+            self.api_root
+        )
+    }
+    pub fn check_runs(&self, full_repo_name: &str) -> String {
+        format!("{}/repos/{full_repo_name}/check-runs", self.api_root)
+    }
+    pub fn contents(&self, full_repo_name: &str, path: &str) -> String {
+        let path: Vec<_> = path.split('/').map(encode_path_segment).collect();
+        format!(
+            "{}/repos/{full_repo_name}/contents/{}",
+            self.api_root,
+            path.join("/")
+        )
     }
-    pub fn installation_repos() -> String {
-        format!("{GITHUB_API_ROOT}/installation/repositories")
+    pub fn rate_limit(&self) -> String {
+        format!("{}/rate_limit", self.api_root)
     }
-    pub fn comments(full_repo_name: &str, issue_number: i32) -> String {
-        format!("{GITHUB_API_ROOT}/repos/{full_repo_name}/issues/{issue_number}/comments")
+    pub fn graphql(&self) -> String {
+        // Enterprise Server serves GraphQL next to the REST API (`/api/graphql`), not under it.
+        match self.api_root.strip_suffix("/v3") {
+            Some(api) => format!("{api}/graphql"),
+            None => format!("{}/graphql", self.api_root),
+        }
     }
-    pub fn issue_comment(full_repo_name: &str, comment_id: i64) -> String {
-        format!("{GITHUB_API_ROOT}/repos/{full_repo_name}/issues/comments/{comment_id}")
+    pub fn issue_comment(&self, full_repo_name: &str, comment_id: i64) -> String {
+        format!(
+            "{}/repos/{full_repo_name}/issues/comments/{comment_id}",
+            self.api_root
+        )
     }
 
-    // GitHub.com links
+    // Web links (github.com, or the web root of an Enterprise instance)
 
-    pub fn pull_url(full_repo_name: &str, pull_number: i32) -> String {
-        format!("{GITHUB_ROOT}/{full_repo_name}/pull/{pull_number}")
+    pub fn pull_url(&self, full_repo_name: &str, pull_number: i32) -> String {
+        format!("{}/{full_repo_name}/pull/{pull_number}", self.root)
     }
-    pub fn diff_url(full_repo_name: &str, pull_number: i32) -> String {
-        // Diff links are handled by github.com, not the API subdomain.
-        format!("{GITHUB_ROOT}/{full_repo_name}/pull/{pull_number}.diff")
+    pub fn diff_url(&self, full_repo_name: &str, pull_number: i32) -> String {
+        // Diff links are handled by the web root, not the API one.
+        format!("{}/{full_repo_name}/pull/{pull_number}.diff", self.root)
+    }
+    pub fn patch_url(&self, full_repo_name: &str, pull_number: i32) -> String {
+        format!("{}/{full_repo_name}/pull/{pull_number}.patch", self.root)
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum TokenType {
     JWT,
     Installation(i64),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub t: String,
     pub ttype: TokenType,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Full names of the only repositories an installation token can access, if GitHub limited it to them
+    /// (e.g. for installations on selected repositories). Otherwise, the token covers the whole installation.
+    #[serde(default)]
+    pub repositories: Option<Vec<String>>,
 }
 
 impl Token {
     pub fn expired(&self) -> bool {
         chrono::Utc::now() >= self.expires_at
     }
+
+    /// Check if the token can be used for requests to the repository, see [`Token::repositories`].
+    pub fn covers(&self, full_repo_name: &str) -> bool {
+        self.repositories
+            .as_ref()
+            .is_none_or(|repos| repos.iter().any(|r| r == full_repo_name))
+    }
+
+    /// Check if the token expires in less than `margin` from now (or already has).
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match chrono::Duration::from_std(margin) {
+            Ok(margin) => chrono::Utc::now() + margin >= self.expires_at,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Storage for tokens generated by [`Client`], which can be shared between several instances of the app
+/// (e.g. through Redis), so that each of them doesn't request its own installation tokens.
+///
+/// The client checks expiration itself, so stores may return expired tokens. Errors are logged and treated
+/// as cache misses, so a store being unavailable only results in extra token requests.
+#[async_trait]
+pub trait TokenStore: std::fmt::Debug + Send + Sync {
+    async fn get(&self, ttype: &TokenType) -> Result<Option<Token>>;
+    async fn put(&self, token: Token) -> Result<()>;
+    async fn remove(&self, ttype: &TokenType) -> Result<()>;
+}
+
+/// The default [`TokenStore`], which keeps tokens in memory of the current process.
+#[derive(Debug, Default)]
+pub struct InMemoryTokenStore {
+    tokens: Mutex<HashMap<TokenType, Token>>,
+}
+
+#[async_trait]
+impl TokenStore for InMemoryTokenStore {
+    async fn get(&self, ttype: &TokenType) -> Result<Option<Token>> {
+        Ok(self.tokens.lock().unwrap().get(ttype).cloned())
+    }
+
+    async fn put(&self, token: Token) -> Result<()> {
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(token.ttype.clone(), token);
+        Ok(())
+    }
+
+    async fn remove(&self, ttype: &TokenType) -> Result<()> {
+        self.tokens.lock().unwrap().remove(ttype);
+        Ok(())
+    }
+}
+
+/// Outcome of [`GitHubInterface::discover_installations`].
+#[derive(Debug, Clone, Default)]
+pub struct Discovery {
+    /// Installations which are set up, along with their repositories.
+    pub added: Vec<structs::Installation>,
+    /// Installations which are known, but can't be used until they are unsuspended.
+    pub suspended: Vec<structs::Installation>,
+    /// Installations which failed to set up (or took too long, see [`DISCOVERY_TIMEOUT`]), with the reason.
+    pub failed: Vec<(structs::Installation, String)>,
+}
+
+/// Snapshot of what the client knows, see [`Client::status`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClientStatus {
+    pub installations: usize,
+    /// Repositories accessible to all known installations.
+    pub repositories: usize,
+    /// Cached app JWTs which haven't expired yet (0 or 1).
+    pub live_jwt_tokens: usize,
+    /// Cached installation tokens which haven't expired yet.
+    pub live_installation_tokens: usize,
+}
+
+/// A finished API request (including retries), see [`Metrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestMetrics {
+    /// Logical operation the request is a part of, e.g. `pulls`, `post_comment`, `read_pull_diff`,
+    /// or `installation_token`. Operations spanning several pages make one request per page.
+    pub operation: &'static str,
+    /// Status of the last response, or `None` if no response was received (e.g. the connection failed).
+    pub status: Option<u16>,
+    /// Time spent on the request, including retries and sleeping between them.
+    pub latency: Duration,
+    pub retries: i32,
+}
+
+impl RequestMetrics {
+    /// Status class like `2xx` or `4xx`, or `error` if there was no response.
+    pub fn status_class(&self) -> String {
+        match self.status {
+            Some(status) => format!("{}xx", status / 100),
+            None => "error".to_string(),
+        }
+    }
+}
+
+/// Instrumentation hook for API requests made by [`Client`] (e.g. to export them as Prometheus metrics).
+pub trait Metrics: std::fmt::Debug + Send + Sync {
+    fn record(&self, request: &RequestMetrics);
+}
+
+/// The default [`Metrics`], which ignores everything.
+#[derive(Debug, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {
+    fn record(&self, _request: &RequestMetrics) {}
 }
 
 #[async_trait]
 pub trait GitHubInterface {
     fn new(app_id: String, key: String) -> Self;
-    async fn installations(&self) -> Result<Vec<structs::Installation>>;
+    fn urls(&self) -> &GitHub;
+    /// Login the app comments as, if it's set explicitly (see [`Client::with_bot_login`]).
+    /// Otherwise, GitHub Apps comment as `<app slug>[bot]`.
+    fn bot_login(&self) -> Option<String>;
+    async fn installations(&self) -> error::Result<Vec<structs::Installation>>;
     fn cached_installations(&self) -> Vec<structs::Installation>;
     fn update_cached_installation(&self, installation: structs::Installation);
-    async fn discover_installations(&self) -> Result<Vec<structs::Installation>>;
-    async fn app(&self) -> Result<structs::App>;
+    fn apply_installation_repositories_event(
+        &self,
+        evt: &structs::InstallationRepositoriesEvent,
+    ) -> structs::Installation;
+    /// Fetch all installations of the app and set them up (see [`GitHubInterface::add_installation`]).
+    /// Installations which fail to set up are skipped, see [`Discovery`], but failing to list them is an error.
+    async fn discover_installations(&self) -> error::Result<Discovery>;
+    async fn app(&self) -> error::Result<structs::App>;
     async fn add_installation(
         &self,
         mut installation: structs::Installation,
-    ) -> Result<structs::Installation>;
-    fn remove_installation(&self, installation: &structs::Installation);
-    async fn pulls(&self, full_repo_name: &str) -> Result<Vec<structs::PullRequest>>;
+    ) -> error::Result<structs::Installation>;
+    async fn remove_installation(&self, installation: &structs::Installation);
+    /// Fetch the repositories of a known installation again, replacing the cached list (e.g. after a repository
+    /// was renamed or transferred), without rediscovering every other installation.
+    async fn refresh_installation(
+        &self,
+        installation_id: i64,
+    ) -> error::Result<structs::Installation>;
+    /// Stop scanning and commenting on a repository without uninstalling the app (e.g. during a large migration).
+    /// Until it is unmuted, [`GitHubInterface::pulls`] and [`GitHubInterface::list_comments`] find nothing there,
+    /// diffs read there are empty, and comments in it are neither posted, edited, nor deleted. See [`crate::controller::Controller::unmute_repository`] for picking up its pulls again.
+    fn mute_repo(&self, full_repo_name: &str);
+    fn unmute_repo(&self, full_repo_name: &str);
+    fn is_repo_muted(&self, full_repo_name: &str) -> bool;
+    async fn pulls(&self, full_repo_name: &str) -> error::Result<Vec<structs::PullRequest>>;
+    /// Fetch a single pull request (without its diff).
+    async fn get_pull(
+        &self,
+        full_repo_name: &str,
+        pull_number: i32,
+    ) -> error::Result<structs::PullRequest>;
+    /// Create a new comment. To avoid spamming the same pull, prefer editing an existing comment made by the app
+    /// (see [`GitHubInterface::list_comments`] and [`GitHubInterface::update_comment`]).
+    /// Returns the created comment, whose ID can be kept to update it later, or `None` if the repository is muted
+    /// (see [`GitHubInterface::mute_repo`]).
     async fn post_comment(
         &self,
         full_repo_name: &str,
         issue_number: i32,
         body: String,
-    ) -> Result<()>;
+    ) -> error::Result<Option<structs::IssueCommentResponse>>;
+    /// Replace the body of an existing issue comment.
     async fn update_comment(
         &self,
         full_repo_name: &str,
         comment_id: i64,
         body: String,
-    ) -> Result<()>;
+    ) -> error::Result<()>;
+    /// Delete an issue comment (e.g. one about a conflict which no longer exists).
+    async fn delete_comment(&self, full_repo_name: &str, comment_id: i64) -> error::Result<()>;
+    /// List all comments of an issue or a pull request. The app's own comments can be told apart by author login.
     async fn list_comments(
         &self,
         full_repo_name: &str,
         issue_number: i32,
-    ) -> Result<Vec<structs::IssueComment>>;
+    ) -> error::Result<Vec<structs::IssueComment>>;
     async fn read_pull_diff(
         &self,
         full_repo_name: &str,
         pull_number: i32,
-    ) -> Result<unidiff::PatchSet>;
+    ) -> error::Result<unidiff::PatchSet>;
+    /// Fetch contents of a file at the given branch, tag, or commit.
+    async fn read_file(
+        &self,
+        full_repo_name: &str,
+        path: &str,
+        ref_name: &str,
+    ) -> error::Result<String>;
+}
+
+/// A page of pulls along with what's needed to fetch it again conditionally.
+#[derive(Debug, Clone)]
+struct PullsPage {
+    etag: String,
+    pulls: Vec<structs::PullRequest>,
+    next: Option<String>,
+}
+
+/// Pages of pulls by URL, holding at most `capacity` of them (see [`PULLS_CACHE_PAGES`]).
+#[derive(Debug)]
+struct PullsCache {
+    capacity: usize,
+    pages: HashMap<String, PullsPage>,
+    /// URLs of cached pages, least recently used first.
+    order: std::collections::VecDeque<String>,
+}
+
+impl PullsCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            pages: HashMap::new(),
+            order: Default::default(),
+        }
+    }
+
+    fn get(&mut self, url: &str) -> Option<PullsPage> {
+        let page = self.pages.get(url)?.clone();
+        self.touch(url);
+        Some(page)
+    }
+
+    fn insert(&mut self, url: String, page: PullsPage) {
+        if self.pages.insert(url.clone(), page).is_some() {
+            self.touch(&url);
+            return;
+        }
+        self.order.push_back(url);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.pages.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, url: &str) {
+        if let Some(i) = self.order.iter().position(|u| u == url) {
+            let url = self.order.remove(i).unwrap();
+            self.order.push_back(url);
+        }
+    }
+}
+
+/// Open pulls of a repository, fetched one page at a time as they are consumed (see [`Client::pulls_stream`]),
+/// so that only one page is kept in memory. Pages aren't kept for conditional requests either (unlike those
+/// fetched by [`GitHubInterface::pulls`], see [`Client::pulls_page`]). Usage:
+///
+/// ```ignore
+/// let mut pulls = client.pulls_stream("ppy/osu-wiki");
+/// while let Some(pull) = pulls.next().await {
+///     process(pull?);
+/// }
+/// ```
+pub struct PullsStream<'a> {
+    client: &'a Client,
+    full_repo_name: String,
+    /// Picked when the first page is requested.
+    token: Option<String>,
+    /// URL of the page to fetch once the current one runs out, or `None` after the last page.
+    next_url: Option<String>,
+    page: std::collections::VecDeque<structs::PullRequest>,
+    /// IDs of pulls yielded so far, see [`dedup_pulls`].
+    seen: std::collections::HashSet<i64>,
+    /// Whether fetched pages are cached, see [`Client::pulls_page`].
+    cache_pages: bool,
+}
+
+impl<'a> PullsStream<'a> {
+    /// Get the next pull, fetching a new page if needed. After an error, the stream ends.
+    pub async fn next(&mut self) -> Option<error::Result<structs::PullRequest>> {
+        loop {
+            while let Some(pull) = self.page.pop_front() {
+                if self.seen.insert(pull.id) {
+                    return Some(Ok(pull));
+                }
+                log::debug!("Skipping duplicate pull #{} from pagination", pull.number);
+            }
+            let url = self.next_url.take()?;
+            if let Err(e) = self.fetch_page(&url).await {
+                return Some(Err(e.into()));
+            }
+        }
+    }
+
+    async fn fetch_page(&mut self, url: &str) -> Result<()> {
+        let token = match &self.token {
+            Some(token) => token.clone(),
+            None => {
+                let token = self.client.pick_token(&self.full_repo_name).await?;
+                self.token = Some(token.clone());
+                token
+            }
+        };
+        let url = reqwest::Url::parse(url)?;
+        let page = self
+            .client
+            .pulls_page(
+                RequestContext::repo("pulls", &self.full_repo_name),
+                &url,
+                &token,
+                self.cache_pages,
+            )
+            .await?;
+        self.page.extend(page.pulls);
+        self.next_url = page.next;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -184,9 +810,37 @@ pub struct Client {
     app_id: String,
     key: String,
     http_client: reqwest::Client,
+    max_attempts: i32,
+    urls: GitHub,
+    last_rate_limit: Arc<Mutex<Option<structs::RateLimit>>>,
 
-    tokens: Arc<Mutex<HashMap<TokenType, Token>>>,
+    /// Generated JWT and installation tokens, see [`Client::with_token_store`].
+    tokens: Arc<dyn TokenStore>,
     pub installations: Arc<Mutex<HashMap<i64, structs::Installation>>>,
+    /// Full repository name -> ID of the installation which has access to it, kept in sync with `installations`.
+    repo_installations: Arc<Mutex<HashMap<String, i64>>>,
+    /// Last page of pulls received for recently fetched page URLs, see [`Client::pulls_page`].
+    pulls_cache: Arc<Mutex<PullsCache>>,
+    /// Whether diffs are assembled from the pull request files API instead of downloading the `.diff`.
+    use_files_api: bool,
+    /// Personal access token used for every request instead of GitHub App authentication, see [`Client::with_token`].
+    personal_token: Option<String>,
+    /// Receives statistics of every request, see [`Client::with_metrics`].
+    metrics: Arc<dyn Metrics>,
+    /// Backoff after secondary rate limits, see [`Client::throttle_state`].
+    throttle: Arc<Throttle>,
+    /// See [`Client::with_bot_login`].
+    bot_login: Option<String>,
+    /// See [`DIFF_GENERATION_DELAY`].
+    diff_generation_delay: Duration,
+    /// See [`DISCOVERY_CONCURRENCY`] and [`DISCOVERY_TIMEOUT`].
+    discovery_concurrency: usize,
+    discovery_timeout: Duration,
+    /// See [`Client::with_max_concurrent_requests`].
+    request_limit: Arc<tokio::sync::Semaphore>,
+    /// Full names of repositories which aren't scanned or commented on, see [`GitHubInterface::mute_repo`].
+    /// Clones of the client share the list.
+    muted_repos: Arc<Mutex<HashSet<String>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -201,114 +855,1570 @@ pub struct Claims {
     pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-impl Claims {
-    pub fn new(app_id: &str) -> Self {
-        let now = chrono::Utc::now();
-        let created_at = now - chrono::Duration::minutes(1);
-        let expires_at = now + chrono::Duration::minutes(7);
-        Self {
-            iat: created_at.timestamp().try_into().unwrap(),
-            exp: expires_at.timestamp().try_into().unwrap(),
-            iss: app_id.to_owned(),
-            created_at,
-            expires_at,
+impl Claims {
+    pub fn new(app_id: &str) -> Self {
+        let now = chrono::Utc::now();
+        let created_at = now - chrono::Duration::minutes(1);
+        let expires_at = now + chrono::Duration::minutes(7);
+        Self {
+            iat: created_at.timestamp().try_into().unwrap(),
+            exp: expires_at.timestamp().try_into().unwrap(),
+            iss: app_id.to_owned(),
+            created_at,
+            expires_at,
+        }
+    }
+}
+
+/// Assemble a unified diff out of the per-file patches returned by the pull request files API,
+/// so that it can be parsed the same way as a downloaded `.diff`.
+fn files_to_diff(files: &[structs::PullRequestFile]) -> String {
+    let mut out = String::new();
+    for file in files {
+        let source = match file.status.as_str() {
+            "added" => "/dev/null".to_string(),
+            _ => format!(
+                "a/{}",
+                file.previous_filename.as_deref().unwrap_or(&file.filename)
+            ),
+        };
+        let target = match file.status.as_str() {
+            "removed" => "/dev/null".to_string(),
+            _ => format!("b/{}", file.filename),
+        };
+        out.push_str(&format!("--- {source}\n+++ {target}\n"));
+        if let Some(patch) = &file.patch {
+            out.push_str(patch);
+            if !patch.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Open pulls of a repository (oldest first, like [`Client::pulls_stream`]) with the paths of their changed files,
+/// see [`Client::pulls_with_files_graphql`]. Up to 100 files are listed per pull.
+const PULLS_WITH_FILES_QUERY: &str = r#"query($owner: String!, $name: String!, $cursor: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(states: OPEN, first: 50, after: $cursor, orderBy: {field: CREATED_AT, direction: ASC}) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        databaseId number state title url createdAt updatedAt isDraft mergedAt
+        author { login ... on User { databaseId } ... on Bot { databaseId } }
+        baseRefName baseRefOid headRefName headRefOid
+        baseRepository { databaseId name nameWithOwner }
+        headRepository { databaseId name nameWithOwner }
+        labels(first: 100) { nodes { name color } }
+        files(first: 100) { pageInfo { hasNextPage endCursor } nodes { path changeType } }
+      }
+    }
+  }
+}"#;
+
+/// Convert a pull returned by GraphQL into its REST form. Changed files are turned into a diff without hunks (see
+/// [`files_to_diff`]), which is enough to compare pulls file by file. GraphQL doesn't have numeric IDs of labels,
+/// so they are all 0.
+fn pull_from_graphql(pull: structs::GraphQLPullRequest) -> Result<structs::PullRequest> {
+    let repository = |repo: structs::GraphQLRepository| structs::Repository {
+        id: repo.database_id,
+        name: repo.name,
+        full_name: repo.name_with_owner,
+        fork: None,
+        owner: None,
+        default_branch: None,
+    };
+    let diff = match &pull.files {
+        Some(files) => {
+            let files: Vec<_> = files
+                .nodes
+                .iter()
+                .map(|file| structs::PullRequestFile {
+                    filename: file.path.clone(),
+                    status: match file.change_type.as_str() {
+                        "ADDED" => "added",
+                        "DELETED" => "removed",
+                        _ => "modified",
+                    }
+                    .to_string(),
+                    previous_filename: None,
+                    patch: None,
+                })
+                .collect();
+            Some(unidiff::PatchSet::from_str(&files_to_diff(&files))?)
+        }
+        None => None,
+    };
+    Ok(structs::PullRequest {
+        id: pull.database_id,
+        number: pull.number,
+        state: match pull.state.as_str() {
+            "OPEN" => "open",
+            _ => "closed",
+        }
+        .to_string(),
+        title: pull.title,
+        user: match pull.author {
+            Some(author) => structs::Actor {
+                id: author.database_id.unwrap_or_default(),
+                login: author.login,
+            },
+            None => structs::Actor {
+                id: 0,
+                login: "ghost".to_string(),
+            },
+        },
+        html_url: pull.url,
+        created_at: pull.created_at,
+        updated_at: pull.updated_at,
+        draft: Some(pull.is_draft),
+        merged_at: pull.merged_at,
+        base: structs::PullRequestRef {
+            ref_name: pull.base_ref_name,
+            sha: pull.base_ref_oid,
+            repo: pull.base_repository.map(repository),
+        },
+        head: structs::PullRequestRef {
+            ref_name: pull.head_ref_name,
+            sha: pull.head_ref_oid,
+            repo: pull.head_repository.map(repository),
+        },
+        labels: pull
+            .labels
+            .map(|labels| labels.nodes)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|label| structs::Label {
+                id: 0,
+                name: label.name,
+                color: label.color,
+            })
+            .collect(),
+        diff,
+    })
+}
+
+/// Incremental parser for large diffs, which are fed to it in chunks as they are downloaded.
+/// Only the file currently being read is kept as text, so that the whole diff isn't held in memory twice
+/// (as text and parsed) at the same time.
+///
+/// The diff is split into files at `diff --git` lines, each of which is parsed on its own into the same
+/// [`unidiff::PatchSet`], so the result is the same as parsing the whole text at once. The only difference is that
+/// binary files, which `unidiff` drops, are kept as files without hunks (see [`binary_file_header`]).
+#[derive(Default)]
+pub struct StreamingDiffParser {
+    patch_set: unidiff::PatchSet,
+    /// Text of the file being read, up to the last complete line.
+    section: String,
+    /// Bytes after the last line break received so far.
+    partial_line: Vec<u8>,
+}
+
+impl StreamingDiffParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the next chunk of the diff. Chunks may end anywhere, even in the middle of a character.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), unidiff::Error> {
+        self.partial_line.extend_from_slice(chunk);
+        let Some(end) = self.partial_line.iter().rposition(|b| *b == b'\n') else {
+            return Ok(());
+        };
+        let lines: Vec<u8> = self.partial_line.drain(..=end).collect();
+        for line in String::from_utf8_lossy(&lines).split_inclusive('\n') {
+            if line.starts_with("diff --git ") {
+                self.flush()?;
+            }
+            self.section.push_str(line);
+        }
+        Ok(())
+    }
+
+    /// Parse the rest of the diff and return all of it.
+    pub fn finish(mut self) -> Result<unidiff::PatchSet, unidiff::Error> {
+        let rest = String::from_utf8_lossy(&self.partial_line).into_owned();
+        self.section.push_str(&rest);
+        self.flush()?;
+        Ok(self.patch_set)
+    }
+
+    fn flush(&mut self) -> Result<(), unidiff::Error> {
+        if !self.section.is_empty() {
+            match binary_file_header(&self.section) {
+                Some(header) => self.patch_set.parse(header)?,
+                None => self.patch_set.parse(&self.section)?,
+            }
+            self.section.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Parse a whole diff at once, see [`StreamingDiffParser`].
+pub fn parse_diff(text: &str) -> Result<unidiff::PatchSet, unidiff::Error> {
+    let mut parser = StreamingDiffParser::new();
+    parser.feed(text.as_bytes())?;
+    parser.finish()
+}
+
+/// File headers (`--- a/...` and `+++ b/...`) for a section of a diff about a binary file, which only has a
+/// `Binary files a/... and b/... differ` line instead. Parsing them gives a file without hunks, same as binary files
+/// returned by the files API (see [`files_to_diff`]).
+fn binary_file_header(section: &str) -> Option<String> {
+    if section.lines().any(|line| line.starts_with("--- ")) {
+        return None;
+    }
+    let paths = section
+        .lines()
+        .find_map(|line| line.strip_prefix("Binary files ")?.strip_suffix(" differ"))?;
+    // Paths may contain spaces, but the target starts with `b/` unless the file was deleted.
+    let at = paths
+        .find(" and b/")
+        .or_else(|| paths.find(" and /dev/null"))?;
+    let (source, target) = (&paths[..at], &paths[at + " and ".len()..]);
+    Some(format!("--- {source}\n+++ {target}\n"))
+}
+
+/// Report a diff which couldn't be parsed, see [`ObservatoryError::DiffParse`].
+fn diff_parse_error(full_repo_name: &str, pull_number: i32, e: unidiff::Error) -> ObservatoryError {
+    let err = ObservatoryError::DiffParse {
+        full_repo_name: full_repo_name.to_string(),
+        pull_number,
+        reason: e.to_string(),
+    };
+    log::error!("{}", err);
+    err
+}
+
+/// Drop pulls which were already seen on a previous page, keeping the first occurrence.
+/// GitHub may return the same pull twice if the list changes while it's being paginated.
+fn dedup_pulls(pulls: Vec<structs::PullRequest>) -> Vec<structs::PullRequest> {
+    let mut seen = std::collections::HashSet::new();
+    pulls
+        .into_iter()
+        .filter(|pull| {
+            let first = seen.insert(pull.id);
+            if !first {
+                log::debug!("Skipping duplicate pull #{} from pagination", pull.number);
+            }
+            first
+        })
+        .collect()
+}
+
+/// Extract the next page URL from the `Link` header, which looks like this:
+/// ```ignore
+/// <https://api.github.com/repositories/1/pulls?page=2>; rel="next", <https://api.github.com/repositories/1/pulls?page=5>; rel="last"
+/// ```
+fn next_page_url(link_header: &str) -> Option<String> {
+    link_header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|p| p.trim().replace(' ', "") == r#"rel="next""#);
+        if is_next {
+            Some(
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse rate limit information sent along with every API response. Returns `None` if any of the headers is missing.
+fn rate_limit_from_headers(headers: &HashMap<String, String>) -> Option<structs::RateLimit> {
+    let get = |name: &str| headers.get(name).and_then(|v| v.parse::<i64>().ok());
+    Some(structs::RateLimit {
+        limit: get("x-ratelimit-limit")?,
+        remaining: get("x-ratelimit-remaining")?,
+        used: get("x-ratelimit-used")?,
+        reset: chrono::Utc
+            .timestamp_opt(get("x-ratelimit-reset")?, 0)
+            .single()?,
+    })
+}
+
+/// If a request failed because the rate limit ran out, find out when it resets.
+fn exhausted_rate_limit_reset(
+    status: reqwest::StatusCode,
+    headers: &HashMap<String, String>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    if !RATE_LIMIT_ERRORS.contains(&status.as_u16())
+        || headers.get("x-ratelimit-remaining").map(String::as_str) != Some("0")
+    {
+        return None;
+    }
+    let reset = headers.get("x-ratelimit-reset")?.parse::<i64>().ok()?;
+    chrono::Utc.timestamp_opt(reset, 0).single()
+}
+
+/// Describe where a request failed. Transport errors (DNS, connection, timeouts) don't necessarily carry a URL,
+/// in which case the URL of the previous attempt is used, if any.
+fn error_url(e: &reqwest::Error, last_url: Option<&reqwest::Url>) -> String {
+    match e.url().or(last_url) {
+        Some(url) => url.to_string(),
+        None => "unknown URL".to_string(),
+    }
+}
+
+const INTERESTING_HEADERS: [&str; 9] = [
+    "etag",
+    "link",
+    "retry-after",
+    "x-ratelimit-limit",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+    "x-ratelimit-used",
+    "x-ratelimit-resource",
+    "x-github-request-id",
+];
+
+/// A pull request in the `.patch` format: one email-formatted message per commit, see [`Client::read_pull_patch`].
+#[derive(Debug, Clone)]
+pub struct PullPatch {
+    /// The whole file, including commit messages and metadata.
+    pub text: String,
+    /// File changes of every commit, if they could be parsed. A file changed by several commits is listed
+    /// once per commit.
+    pub diff: Option<unidiff::PatchSet>,
+}
+
+/// A successful (non-error) response. The body is read into a string, unless it's streamed (see [`Client::stream`]).
+struct RawResponse<B = String> {
+    status: reqwest::StatusCode,
+    body: B,
+    /// Only those listed in [`INTERESTING_HEADERS`].
+    headers: HashMap<String, String>,
+    /// Slot of [`Client::with_max_concurrent_requests`], kept until a streamed body is read.
+    permit: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+/// What a request is for, included in its log messages and errors so that failures can be traced back to a
+/// repository and installation. Helpers accept a bare operation name as well (e.g. `"app"`).
+#[derive(Debug, Clone, Default)]
+struct RequestContext {
+    /// See [`RequestMetrics::operation`].
+    operation: &'static str,
+    full_repo_name: Option<String>,
+    /// Filled in from [`Client::installation_for_repo`] if not set, see [`Client::send`].
+    installation_id: Option<i64>,
+}
+
+impl RequestContext {
+    fn repo(operation: &'static str, full_repo_name: &str) -> Self {
+        Self {
+            operation,
+            full_repo_name: Some(full_repo_name.to_string()),
+            installation_id: None,
+        }
+    }
+
+    fn installation(operation: &'static str, installation_id: i64) -> Self {
+        Self {
+            operation,
+            full_repo_name: None,
+            installation_id: Some(installation_id),
+        }
+    }
+}
+
+impl From<&'static str> for RequestContext {
+    fn from(operation: &'static str) -> Self {
+        Self {
+            operation,
+            ..Default::default()
+        }
+    }
+}
+
+/// E.g. `get_pull in ppy/osu-wiki (installation 123)`.
+impl std::fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(full_repo_name) = &self.full_repo_name {
+            write!(f, " in {}", full_repo_name)?;
+        }
+        if let Some(installation_id) = self.installation_id {
+            write!(f, " (installation {})", installation_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// What happened while sending a request, for [`Metrics`]. Filled in even if the request fails.
+#[derive(Debug, Default)]
+struct RequestStats {
+    attempts: i32,
+    /// Status of the last response, if any was received.
+    status: Option<reqwest::StatusCode>,
+}
+
+enum ResponseBody {
+    Text(String),
+    Stream(reqwest::Response),
+}
+
+impl ResponseBody {
+    fn into_text(self) -> String {
+        match self {
+            ResponseBody::Text(body) => body,
+            ResponseBody::Stream(_) => {
+                unreachable!("the body of a response is only left unread if requested")
+            }
+        }
+    }
+
+    fn into_stream(self) -> reqwest::Response {
+        match self {
+            ResponseBody::Stream(response) => response,
+            ResponseBody::Text(_) => {
+                unreachable!("the body of a successful response is left unread if requested")
+            }
+        }
+    }
+}
+
+/// Send a request, retrying it if needed (see [`is_retryable`]). The body of a successful response is left unread
+/// if `stream_body` is set; bodies of error responses are always read, since they are needed to tell what went wrong.
+/// Log messages and errors mention `context`, and GitHub's ID of the request (`x-github-request-id`) if there was
+/// a response.
+async fn __request(
+    context: &RequestContext,
+    rb: reqwest::RequestBuilder,
+    max_attempts: i32,
+    throttle: &Throttle,
+    stats: &mut RequestStats,
+    stream_body: bool,
+) -> Result<RawResponse<ResponseBody>> {
+    // Headers set by the caller (e.g. a preview `Accept` type) take precedence over the defaults.
+    let mut headers = Client::default_headers();
+    if let Some(request) = rb.try_clone().and_then(|r| r.build().ok()) {
+        for key in request.headers().keys() {
+            headers.remove(key);
+        }
+    }
+    let prepared_request = rb.headers(headers);
+    let mut url: Option<reqwest::Url> = None;
+
+    let mut timer = ProgressiveTimeout::new(max_attempts);
+    while timer.tick().is_ok() {
+        stats.attempts = timer.current_retry();
+        throttle.wait().await;
+        match prepared_request.try_clone().unwrap().send().await {
+            Ok(response) => {
+                // Yes, you have to deconstruct the response by itself if you step from the trodden path
+                // (access URL and body, and do status checks at the same time).
+                // https://github.com/seanmonstar/reqwest/issues/1542
+                let headers: HashMap<String, String> = response
+                    .headers()
+                    .iter()
+                    .map(|(k, v)| {
+                        (
+                            k.to_string().to_lowercase(),
+                            v.to_str().unwrap_or("<none>").to_owned(),
+                        )
+                    })
+                    .filter(|(k, _)| INTERESTING_HEADERS.contains(&k.as_str()))
+                    .collect();
+                let status = response.status();
+                stats.status = Some(status);
+                url = Some(response.url().clone());
+                let logging_string = format!(
+                    "{}: HTTP {} {} ({}/{})",
+                    context,
+                    status,
+                    url.as_ref().unwrap(),
+                    timer.current_retry(),
+                    timer.max_retries(),
+                );
+                if stream_body && status.is_success() {
+                    log::debug!("{}. Headers: {:?}", logging_string, headers);
+                    return Ok(RawResponse {
+                        status,
+                        body: ResponseBody::Stream(response),
+                        headers,
+                        permit: None,
+                    });
+                }
+                let body = response.text().await;
+                if status.is_client_error() || status.is_server_error() || body.is_err() {
+                    let retry_after = headers
+                        .get("retry-after")
+                        .and_then(|v| parse_retry_after(v));
+                    let secondary_backoff = body
+                        .as_deref()
+                        .is_ok_and(|body| is_secondary_rate_limit(status, &headers, body))
+                        .then(|| throttle.hit(retry_after));
+                    let request_id = headers
+                        .get("x-github-request-id")
+                        .map(String::as_str)
+                        .unwrap_or("unknown");
+                    if let Some(backoff) = secondary_backoff {
+                        log::warn!(
+                            "Secondary rate limit hit (request ID: {}), pausing requests for {:?}",
+                            request_id,
+                            backoff
+                        );
+                    }
+                    let can_be_retried = (is_retryable(status, retry_after)
+                        || secondary_backoff.is_some())
+                        && timer.current_retry() < timer.max_retries();
+                    let log_level = if can_be_retried {
+                        log::Level::Warn
+                    } else {
+                        log::Level::Error
+                    };
+                    log::log!(
+                        log_level,
+                        "{} (request ID: {}). Headers: {:?} + body: {:?}",
+                        logging_string,
+                        request_id,
+                        headers,
+                        body
+                    );
+
+                    if can_be_retried {
+                        // The pause after a secondary rate limit is awaited before the next attempt.
+                        if secondary_backoff.is_some() {
+                            continue;
+                        }
+                        match retry_after {
+                            Some(timeout) => {
+                                log::info!("Sleeping for {:?} (Retry-After)...", timeout);
+                                timer.sleep_for(timeout).await;
+                            }
+                            None => {
+                                log::info!("Sleeping for ~{:?}...", timer.current_timeout);
+                                timer.sleep().await;
+                            }
+                        }
+                        continue;
+                    }
+                    if let Some(reset) = exhausted_rate_limit_reset(status, &headers) {
+                        return Err(ObservatoryError::RateLimited { reset }.into());
+                    }
+                    eyre::bail!("{} (request ID: {})", logging_string, request_id);
+                }
+
+                log::debug!("{}. Headers: {:?}", logging_string, headers);
+                return Ok(RawResponse {
+                    status,
+                    body: ResponseBody::Text(body.unwrap()),
+                    headers,
+                    permit: None,
+                });
+            }
+            Err(e) => {
+                log::error!(
+                    "{}: error at {}: HTTP {:?}: {:?}",
+                    context,
+                    error_url(&e, url.as_ref()),
+                    e.status(),
+                    e
+                );
+                return Err(e.into());
+            }
+        }
+    }
+    eyre::bail!("{}: exhausted retries for {:?}, giving up", context, url)
+}
+
+/// Check GitHub App credentials before they are used: the app ID must be a positive number, and the private key
+/// must be an RSA key in PEM format (as downloaded from the app settings). Otherwise, the client would only fail
+/// when it tries to generate a JWT during its first request.
+pub fn validate_app_credentials(app_id: &str, key: &str) -> Result<()> {
+    if !app_id.parse::<i64>().is_ok_and(|id| id > 0) {
+        eyre::bail!(
+            "Invalid GitHub App ID {:?}: expected a positive number",
+            app_id
+        );
+    }
+    jsonwebtoken::EncodingKey::from_rsa_pem(key.as_bytes())
+        .map_err(|e| eyre::eyre!("Failed to parse the GitHub App private key: {}", e))?;
+    Ok(())
+}
+
+/// How the client authenticates, see [`ClientBuilder`].
+#[derive(Debug, Clone)]
+enum Auth {
+    App { app_id: String, key: String },
+    Token(String),
+}
+
+/// Configures a [`Client`] step by step. Everything except authentication is optional:
+/// ```ignore
+/// let client = ClientBuilder::new()
+///     .app_auth(app_id, private_key)
+///     .api_root("https://github.example.com/api/v3")
+///     .web_root("https://github.example.com")
+///     .max_retries(5)
+///     .build()?;
+/// ```
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    auth: Option<Auth>,
+    api_root: Option<String>,
+    web_root: Option<String>,
+    timeout: Option<Duration>,
+    max_retries: Option<i32>,
+    http_client: Option<reqwest::Client>,
+    token_store: Option<Arc<dyn TokenStore>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    bot_login: Option<String>,
+    max_concurrent_requests: Option<usize>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Authenticate as a GitHub App (replaces any previously set authentication).
+    pub fn app_auth(mut self, app_id: String, key: String) -> Self {
+        self.auth = Some(Auth::App { app_id, key });
+        self
+    }
+
+    /// Authenticate with a personal access token (replaces any previously set authentication).
+    /// See [`Client::with_token`] for limitations.
+    pub fn token_auth(mut self, pat: String) -> Self {
+        self.auth = Some(Auth::Token(pat));
+        self
+    }
+
+    /// Root of the REST API, `https://api.github.com` by default.
+    pub fn api_root(mut self, api_root: &str) -> Self {
+        self.api_root = Some(api_root.to_string());
+        self
+    }
+
+    /// Root of the web interface, `https://github.com` by default.
+    pub fn web_root(mut self, web_root: &str) -> Self {
+        self.web_root = Some(web_root.to_string());
+        self
+    }
+
+    /// Limit how long a single request attempt may take (see [`DEFAULT_REQUEST_TIMEOUT`]).
+    /// Can't be combined with a custom HTTP client, which has its own timeouts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How many times a failed request is retried after the first attempt (see [`DEFAULT_MAX_ATTEMPTS`]).
+    pub fn max_retries(mut self, max_retries: i32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Send requests through a preconfigured HTTP client, see [`Client::with_http_client`].
+    pub fn http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Keep generated tokens in a custom store, see [`Client::with_token_store`].
+    pub fn token_store(mut self, store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = Some(store);
+        self
+    }
+
+    /// Report every API request to `metrics`, see [`Client::with_metrics`].
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Limit requests in flight at the same time, see [`Client::with_max_concurrent_requests`].
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = Some(max_concurrent_requests);
+        self
+    }
+
+    /// Recognize own comments by this login, see [`Client::with_bot_login`].
+    pub fn bot_login(mut self, bot_login: &str) -> Self {
+        self.bot_login = Some(bot_login.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let http_client = match (self.http_client, self.timeout) {
+            (Some(_), Some(_)) => {
+                eyre::bail!("A timeout can't be set together with a custom HTTP client")
+            }
+            (Some(http_client), None) => http_client,
+            (None, timeout) => Client::http_client(
+                timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT),
+                DEFAULT_CONNECT_TIMEOUT,
+            ),
+        };
+        let mut client = match self.auth {
+            None => eyre::bail!("No authentication method set for the GitHub client"),
+            Some(Auth::App { app_id, key }) => {
+                validate_app_credentials(&app_id, &key)?;
+                Client::with_http_client(app_id, key, http_client)
+            }
+            Some(Auth::Token(pat)) => {
+                let mut client =
+                    Client::with_http_client(String::new(), String::new(), http_client);
+                client.personal_token = Some(pat);
+                client
+            }
+        };
+        client.urls = GitHub::new(
+            self.api_root.as_deref().unwrap_or(GITHUB_API_ROOT),
+            self.web_root.as_deref().unwrap_or(GITHUB_ROOT),
+        );
+        if let Some(max_retries) = self.max_retries {
+            client = client.with_max_attempts(max_retries.saturating_add(1));
+        }
+        if let Some(store) = self.token_store {
+            client = client.with_token_store(store);
+        }
+        if let Some(metrics) = self.metrics {
+            client = client.with_metrics(metrics);
+        }
+        if let Some(bot_login) = self.bot_login {
+            client = client.with_bot_login(&bot_login);
+        }
+        if let Some(max_concurrent_requests) = self.max_concurrent_requests {
+            client = client.with_max_concurrent_requests(max_concurrent_requests);
+        }
+        Ok(client)
+    }
+}
+
+impl Client {
+    /// Start configuring a client, see [`ClientBuilder`].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Same as [`GitHubInterface::new`], but fails early if the credentials are malformed (see [`validate_app_credentials`]).
+    pub fn try_new(app_id: String, key: String) -> Result<Self> {
+        validate_app_credentials(&app_id, &key)?;
+        Ok(<Self as GitHubInterface>::new(app_id, key))
+    }
+
+    /// Create a client which authenticates with a (fine-grained) personal access token instead of as a GitHub App.
+    /// Meant for local testing and small deployments: app-level endpoints (installations, app info) are unavailable,
+    /// but everything scoped to repositories the token has access to works as usual.
+    pub fn with_token(pat: String) -> Self {
+        let mut client = <Self as GitHubInterface>::new(String::new(), String::new());
+        client.personal_token = Some(pat);
+        client
+    }
+
+    /// Create a client which sends requests through a preconfigured HTTP client (e.g. one with a proxy, custom root
+    /// certificates, or a connection pool shared with other code). The client is used as is, so any timeouts have to be
+    /// set on it beforehand; note that [`Client::with_timeouts`] replaces it with a fresh one.
+    pub fn with_http_client(app_id: String, key: String, http_client: reqwest::Client) -> Self {
+        Self {
+            app_id,
+            key,
+            http_client,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            urls: GitHub::default(),
+            last_rate_limit: Arc::new(Mutex::new(None)),
+            tokens: Arc::new(InMemoryTokenStore::default()),
+            installations: Arc::new(Mutex::new(HashMap::new())),
+            repo_installations: Arc::new(Mutex::new(HashMap::new())),
+            pulls_cache: Arc::new(Mutex::new(PullsCache::new(PULLS_CACHE_PAGES))),
+            use_files_api: false,
+            personal_token: None,
+            metrics: Arc::new(NoopMetrics),
+            throttle: Arc::new(Throttle::default()),
+            bot_login: None,
+            diff_generation_delay: DIFF_GENERATION_DELAY,
+            discovery_concurrency: DISCOVERY_CONCURRENCY,
+            discovery_timeout: DISCOVERY_TIMEOUT,
+            request_limit: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_REQUESTS)),
+            muted_repos: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Set how many times a single API request is attempted before giving up (see [`DEFAULT_MAX_ATTEMPTS`]).
+    /// Only server errors and rate limit errors with a `Retry-After` header are retried.
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = std::cmp::max(max_attempts, 1);
+        self
+    }
+
+    /// Limit how many requests are in flight at the same time (see [`MAX_CONCURRENT_REQUESTS`]), on top of limits of
+    /// individual operations such as [`DIFF_CONCURRENCY`]. Clones of the client share the limit. Requests wait for
+    /// a free slot before they are sent, and hold it until the response is read, retries included.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.request_limit = Arc::new(tokio::sync::Semaphore::new(std::cmp::max(
+            max_concurrent_requests,
+            1,
+        )));
+        self
+    }
+
+    /// Limit how long a single request attempt may take, so that a stalled connection doesn't hang the app.
+    pub fn with_timeouts(mut self, request_timeout: Duration, connect_timeout: Duration) -> Self {
+        self.http_client = Self::http_client(request_timeout, connect_timeout);
+        self
+    }
+
+    fn http_client(request_timeout: Duration, connect_timeout: Duration) -> reqwest::Client {
+        reqwest::Client::builder()
+            .timeout(request_timeout)
+            .connect_timeout(connect_timeout)
+            .build()
+            .expect("Failed to initialize the HTTP client")
+    }
+
+    /// Keep generated tokens in a custom store instead of the memory of the current process (see [`TokenStore`]).
+    pub fn with_token_store(mut self, store: Arc<dyn TokenStore>) -> Self {
+        self.tokens = store;
+        self
+    }
+
+    /// Report every API request to `metrics` (see [`RequestMetrics`] for what's recorded).
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Read pull request diffs through the paginated files API rather than the `.diff` link.
+    ///
+    /// This is slower (one request per 100 files), but GitHub may truncate large `.diff` downloads, silently dropping
+    /// files; the files API lists up to 3000 of them. Files without a textual patch (binary or huge ones) are still
+    /// listed, just without hunks.
+    pub fn with_files_api(mut self, enabled: bool) -> Self {
+        self.use_files_api = enabled;
+        self
+    }
+
+    /// Point the client to a different GitHub instance (e.g. GitHub Enterprise Server).
+    /// See [`GitHub::new`] for the expected format of the roots.
+    pub fn with_base_urls(mut self, api_root: &str, root: &str) -> Self {
+        self.urls = GitHub::new(api_root, root);
+        self
+    }
+
+    /// Set the login the app comments as, for recognizing its own comments (see [`is_bot_login`]). Needed when
+    /// comments are posted under another account, e.g. with a personal access token, or when several apps
+    /// with different names share the same code.
+    pub fn with_bot_login(mut self, bot_login: &str) -> Self {
+        self.bot_login = Some(bot_login.to_string());
+        self
+    }
+
+    /// Whether the client is slowing down after hitting GitHub's secondary rate limits, e.g. for logging.
+    /// Requests are paused for a while after every hit (longer if it happens again), and then sent one at a time.
+    pub fn throttle_state(&self) -> ThrottleState {
+        self.throttle.state()
+    }
+
+    /// Counts of known installations and cached tokens, e.g. for a status page. Only tokens of known installations
+    /// are counted, since token stores can't be listed.
+    pub async fn status(&self) -> ClientStatus {
+        let (installations, repositories, installation_ids) = {
+            let installations = self.installations.lock().unwrap();
+            (
+                installations.len(),
+                installations.values().map(|i| i.repositories.len()).sum(),
+                installations.keys().copied().collect::<Vec<_>>(),
+            )
+        };
+        let mut live_installation_tokens = 0;
+        for id in installation_ids {
+            if self.is_live(&TokenType::Installation(id)).await {
+                live_installation_tokens += 1;
+            }
+        }
+        ClientStatus {
+            installations,
+            repositories,
+            live_jwt_tokens: usize::from(self.is_live(&TokenType::JWT).await),
+            live_installation_tokens,
+        }
+    }
+
+    async fn is_live(&self, ttype: &TokenType) -> bool {
+        self.tokens
+            .get(ttype)
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|token| !token.expired())
+    }
+
+    /// Rate limit information from the most recent successful API response, if there was one.
+    ///
+    /// Note that installations have separate quotas; for the quota of a specific one, see [`Client::rate_limit`].
+    pub fn last_rate_limit(&self) -> Option<structs::RateLimit> {
+        self.last_rate_limit.lock().unwrap().clone()
+    }
+
+    /// Fetch current rate limit status for the installation which has access to `full_repo_name`.
+    /// Requests to this endpoint don't count against the quota.
+    pub async fn rate_limit(&self, full_repo_name: &str) -> error::Result<structs::RateLimit> {
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .get(self.urls.rate_limit())
+            .bearer_auth(token);
+        let response: structs::RateLimitResponse = self.json("rate_limit", req).await?;
+        Ok(response.rate)
+    }
+
+    /// Fetch a pull request as a `.patch` file, which unlike the `.diff` keeps commits separate and includes their
+    /// messages. File changes are parsed if possible; otherwise, only the raw text is returned.
+    pub async fn read_pull_patch(
+        &self,
+        full_repo_name: &str,
+        pull_number: i32,
+    ) -> error::Result<PullPatch> {
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .get(self.urls.patch_url(full_repo_name, pull_number))
+            .bearer_auth(token);
+        let text = self
+            .text(RequestContext::repo("read_pull_patch", full_repo_name), req)
+            .await?;
+        let diff = match parse_diff(&text) {
+            Ok(diff) => Some(diff),
+            Err(e) => {
+                log::warn!(
+                    "Failed to parse file changes in the patch of pull #{} in {}: {}",
+                    pull_number,
+                    full_repo_name,
+                    e
+                );
+                None
+            }
+        };
+        Ok(PullPatch { text, diff })
+    }
+
+    /// List pulls in the given state, oldest first. [`GitHubInterface::pulls`] lists open ones; closed ones
+    /// (including merged) are useful for looking back at past conflicts.
+    pub async fn pulls_with_state(
+        &self,
+        full_repo_name: &str,
+        state: structs::PullState,
+    ) -> error::Result<Vec<structs::PullRequest>> {
+        // Every pull ends up in memory anyway, so the pages might as well be cached.
+        let mut pulls = self.pulls_stream_with_cache(full_repo_name, state, true);
+        let mut out = Vec::new();
+        while let Some(pull) = pulls.next().await {
+            out.push(pull?);
+        }
+        Ok(out)
+    }
+
+    /// Send a GraphQL query with the installation token of the repository (or the personal access token) and return
+    /// its `data`. Errors reported by GitHub fail the whole query, even if some data was returned.
+    ///
+    /// https://docs.github.com/en/graphql/guides/forming-calls-with-graphql
+    pub async fn graphql<T>(
+        &self,
+        full_repo_name: &str,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> error::Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let body = serde_json::to_string(&structs::GraphQLRequest {
+            query: query.to_string(),
+            variables,
+        })
+        .unwrap();
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .post(self.urls.graphql())
+            .body(body)
+            .bearer_auth(token);
+        let response: structs::GraphQLResponse<T> = self
+            .json(RequestContext::repo("graphql", full_repo_name), req)
+            .await?;
+        if !response.errors.is_empty() {
+            let messages: Vec<_> = response.errors.into_iter().map(|e| e.message).collect();
+            return Err(eyre::eyre!("GraphQL query failed: {}", messages.join("; ")).into());
+        }
+        Ok(response
+            .data
+            .ok_or_else(|| eyre::eyre!("GraphQL response has no data"))?)
+    }
+
+    /// List open pulls, oldest first, with their changed files already in [`structs::PullRequest::diff`] (as files
+    /// without hunks, see [`pull_from_graphql`]). This takes one request per 50 pulls, instead of a request per page
+    /// of pulls plus one per diff with [`GitHubInterface::pulls`] and [`Client::load_diffs`].
+    ///
+    /// The diffs only have file paths, so they are only good for comparing pulls file by file (i.e. without
+    /// [`conflicts::CompareConfig::line_level_overlaps`], [`conflicts::CompareConfig::line_hints`] and
+    /// [`conflicts::CompareConfig::default_branch_baseline`]). Pulls with more than 100 files have their diffs
+    /// downloaded as usual.
+    pub async fn pulls_with_files_graphql(
+        &self,
+        full_repo_name: &str,
+    ) -> error::Result<Vec<structs::PullRequest>> {
+        let (owner, name) = full_repo_name
+            .split_once('/')
+            .ok_or_else(|| eyre::eyre!("Invalid repository name {}", full_repo_name))?;
+        let mut pulls = Vec::new();
+        let mut incomplete = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let data: structs::GraphQLPullsWithFiles = self
+                .graphql(
+                    full_repo_name,
+                    PULLS_WITH_FILES_QUERY,
+                    serde_json::json!({ "owner": owner, "name": name, "cursor": cursor }),
+                )
+                .await?;
+            let page = data.repository.pull_requests;
+            for pull in page.nodes {
+                let complete = pull.files.as_ref().is_some_and(|files| files.is_complete());
+                let mut pull = pull_from_graphql(pull)?;
+                if complete {
+                    pulls.push(pull);
+                } else {
+                    pull.diff = None;
+                    incomplete.push(pull);
+                }
+            }
+            match page.page_info.filter(|p| p.has_next_page) {
+                Some(page_info) => cursor = page_info.end_cursor,
+                None => break,
+            }
+        }
+        if !incomplete.is_empty() {
+            log::info!(
+                "{} pulls in {} have too many files for GraphQL, loading their diffs...",
+                incomplete.len(),
+                full_repo_name
+            );
+            self.load_diffs(full_repo_name, &mut incomplete).await;
+            pulls.extend(incomplete);
+            pulls.sort_by_key(|p| (p.created_at, p.number));
+        }
+        Ok(pulls)
+    }
+
+    /// Iterate over open pulls, oldest first, fetching them page by page. Unlike [`GitHubInterface::pulls`],
+    /// this doesn't keep every pull in memory at once.
+    pub fn pulls_stream(&self, full_repo_name: &str) -> PullsStream<'_> {
+        self.pulls_stream_with_state(full_repo_name, structs::PullState::Open)
+    }
+
+    /// Same as [`Client::pulls_stream`], but for pulls in the given state.
+    pub fn pulls_stream_with_state(
+        &self,
+        full_repo_name: &str,
+        state: structs::PullState,
+    ) -> PullsStream<'_> {
+        self.pulls_stream_with_cache(full_repo_name, state, false)
+    }
+
+    fn pulls_stream_with_cache(
+        &self,
+        full_repo_name: &str,
+        state: structs::PullState,
+        cache_pages: bool,
+    ) -> PullsStream<'_> {
+        let first_page = reqwest::Url::parse_with_params(
+            &self.urls.pulls(full_repo_name),
+            &[
+                ("state", state.as_str()),
+                ("direction", "asc"),
+                ("sort", "created"),
+                ("per_page", "100"),
+            ],
+        )
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| self.urls.pulls(full_repo_name));
+        PullsStream {
+            client: self,
+            full_repo_name: full_repo_name.to_string(),
+            token: None,
+            next_url: Some(first_page),
+            page: Default::default(),
+            seen: Default::default(),
+            cache_pages,
+        }
+    }
+
+    /// List open pulls which were updated at or after `since`, most recently updated first.
+    ///
+    /// GitHub can't filter pulls by date, but it can sort them by update time, so paging stops at the first pull
+    /// older than `since`. This makes incremental scans of large repositories cheap: usually a single request.
+    pub async fn pulls_updated_since(
+        &self,
+        full_repo_name: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> error::Result<Vec<structs::PullRequest>> {
+        let token = self.pick_token(full_repo_name).await?;
+        let mut url = reqwest::Url::parse_with_params(
+            &self.urls.pulls(full_repo_name),
+            &[
+                ("state", "open"),
+                ("direction", "desc"),
+                ("sort", "updated"),
+                ("per_page", "100"),
+            ],
+        )
+        .map_err(eyre::Report::from)?;
+        let mut out = Vec::new();
+        loop {
+            let page = self
+                .pulls_page(
+                    RequestContext::repo("pulls", full_repo_name),
+                    &url,
+                    &token,
+                    true,
+                )
+                .await?;
+            let page_len = page.pulls.len();
+            let recent: Vec<_> = page
+                .pulls
+                .into_iter()
+                .filter(|p| p.updated_at >= since)
+                .collect();
+            let reached_older = recent.len() < page_len;
+            out.extend(recent);
+            if reached_older {
+                break;
+            }
+            match page.next {
+                Some(next) => url = reqwest::Url::parse(&next).map_err(eyre::Report::from)?,
+                None => break,
+            }
+        }
+        Ok(dedup_pulls(out))
+    }
+
+    /// React to an issue or a pull request, e.g. with 👀 to show that it's being processed without posting a comment.
+    /// Reacting with the same content twice is not an error; GitHub returns the existing reaction.
+    pub async fn add_reaction(
+        &self,
+        full_repo_name: &str,
+        issue_number: i32,
+        content: structs::ReactionContent,
+    ) -> error::Result<structs::Reaction> {
+        let reaction = serde_json::to_string(&structs::PostReaction { content }).unwrap();
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .post(self.urls.reactions(full_repo_name, issue_number))
+            .header(
+                "Accept",
+                "application/vnd.github.squirrel-girl-preview+json",
+            )
+            .body(reaction)
+            .bearer_auth(token);
+        Ok(self
+            .json(RequestContext::repo("add_reaction", full_repo_name), req)
+            .await?)
+    }
+
+    /// Report the conflict status of a commit (normally the head of a pull request) as a completed check run,
+    /// so that conflicts can block merging through branch protection. The summary is rendered as Markdown.
+    ///
+    /// Check runs can only be created by GitHub Apps, not with personal access tokens.
+    pub async fn create_check_run(
+        &self,
+        full_repo_name: &str,
+        head_sha: &str,
+        conclusion: structs::CheckConclusion,
+        summary: String,
+    ) -> error::Result<structs::CheckRun> {
+        let title = match conclusion {
+            structs::CheckConclusion::Failure => "Conflicts found",
+            _ => "No conflicts",
+        };
+        let check_run = serde_json::to_string(&structs::PostCheckRun {
+            name: CHECK_RUN_NAME.to_string(),
+            head_sha: head_sha.to_string(),
+            status: "completed".to_string(),
+            conclusion,
+            output: structs::CheckRunOutput {
+                title: title.to_string(),
+                summary,
+            },
+        })
+        .unwrap();
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .post(self.urls.check_runs(full_repo_name))
+            .body(check_run)
+            .bearer_auth(token);
+        Ok(self
+            .json(
+                RequestContext::repo("create_check_run", full_repo_name),
+                req,
+            )
+            .await?)
+    }
+
+    /// Fetch diffs for a batch of pulls concurrently (up to [`DIFF_CONCURRENCY`] at a time) and store them in the pulls.
+    ///
+    /// Pulls whose diffs failed to load are left untouched, so that one broken pull doesn't affect others.
+    pub async fn load_diffs(&self, full_repo_name: &str, pulls: &mut [structs::PullRequest]) {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!("{} is muted, not loading diffs", full_repo_name);
+            return;
+        }
+        let mut tasks = tokio::task::JoinSet::new();
+        for i in 0..pulls.len() {
+            if tasks.len() >= DIFF_CONCURRENCY {
+                if let Some(result) = tasks.join_next().await {
+                    Self::store_diff(full_repo_name, pulls, result);
+                }
+            }
+            let client = self.clone();
+            let repo = full_repo_name.to_string();
+            let pull_number = pulls[i].number;
+            tasks.spawn(async move { (i, client.read_pull_diff(&repo, pull_number).await) });
+        }
+        while let Some(result) = tasks.join_next().await {
+            Self::store_diff(full_repo_name, pulls, result);
+        }
+    }
+
+    /// Find all conflicts between open pulls of a repository as they are right now (with the default settings,
+    /// see [`Client::scan_repo_with`]). Meant for one-off checks, e.g. from a command line tool.
+    pub async fn scan_repo(&self, full_repo_name: &str) -> error::Result<Vec<conflicts::Conflict>> {
+        self.scan_repo_with(full_repo_name, &conflicts::CompareConfig::default())
+            .await
+    }
+
+    /// Fetch open pulls of a repository along with their diffs, and compare them (see [`conflicts::find_all_conflicts`]).
+    /// Pulls whose diffs fail to load are left out of the comparison.
+    pub async fn scan_repo_with(
+        &self,
+        full_repo_name: &str,
+        config: &conflicts::CompareConfig,
+    ) -> error::Result<Vec<conflicts::Conflict>> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!("{} is muted, skipping the scan", full_repo_name);
+            return Ok(Vec::new());
+        }
+        log::info!("Scanning {} for conflicts...", full_repo_name);
+        let mut pulls = self.pulls(full_repo_name).await?;
+        log::info!(
+            "Fetched {} open pulls from {}, loading diffs...",
+            pulls.len(),
+            full_repo_name
+        );
+        self.load_diffs(full_repo_name, &mut pulls).await;
+        pulls.retain(|p| p.diff.is_some());
+        log::info!(
+            "Loaded {} diffs from {}, comparing pulls...",
+            pulls.len(),
+            full_repo_name
+        );
+        let found = conflicts::find_all_conflicts(&pulls, config);
+        log::info!("Found {} conflicts in {}", found.len(), full_repo_name);
+        Ok(found)
+    }
+
+    /// Post a batch of comments (repository, issue or pull number, body), e.g. after a scan, up to
+    /// [`COMMENT_CONCURRENCY`] at a time and pausing after secondary rate limits (see [`Client::throttle_state`]).
+    /// Once the rate limit is exhausted, the remaining comments aren't sent and fail with
+    /// [`ObservatoryError::RateLimited`].
+    ///
+    /// Returns the result of each comment (the created comment if it was posted, see [`GitHubInterface::post_comment`]),
+    /// in the same order.
+    pub async fn post_comments(
+        &self,
+        items: Vec<(String, i32, String)>,
+    ) -> Vec<error::Result<Option<structs::IssueCommentResponse>>> {
+        let mut results: Vec<Option<error::Result<Option<structs::IssueCommentResponse>>>> =
+            items.iter().map(|_| None).collect();
+        let mut rate_limit_reset = None;
+        let mut tasks = tokio::task::JoinSet::new();
+        for (i, (full_repo_name, issue_number, body)) in items.into_iter().enumerate() {
+            if tasks.len() >= COMMENT_CONCURRENCY {
+                if let Some(result) = tasks.join_next().await {
+                    Self::store_comment_result(&mut results, &mut rate_limit_reset, result);
+                }
+            }
+            if let Some(reset) = rate_limit_reset {
+                results[i] = Some(Err(ObservatoryError::RateLimited { reset }));
+                continue;
+            }
+            let client = self.clone();
+            tasks.spawn(async move {
+                let result = client
+                    .post_comment(&full_repo_name, issue_number, body)
+                    .await;
+                if let Err(e) = &result {
+                    log::error!(
+                        "Failed to post a comment in {}: {}",
+                        client.urls.pull_url(&full_repo_name, issue_number),
+                        e
+                    );
+                }
+                (i, result)
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            Self::store_comment_result(&mut results, &mut rate_limit_reset, result);
+        }
+        results
+            .into_iter()
+            .map(|r| r.unwrap_or_else(|| Err(eyre::eyre!("Comment posting task failed").into())))
+            .collect()
+    }
+
+    fn store_comment_result(
+        results: &mut [Option<error::Result<Option<structs::IssueCommentResponse>>>],
+        rate_limit_reset: &mut Option<chrono::DateTime<chrono::Utc>>,
+        result: std::result::Result<
+            (usize, error::Result<Option<structs::IssueCommentResponse>>),
+            tokio::task::JoinError,
+        >,
+    ) {
+        match result {
+            Ok((i, result)) => {
+                if let Err(ObservatoryError::RateLimited { reset }) = &result {
+                    *rate_limit_reset = Some(*reset);
+                }
+                results[i] = Some(result);
+            }
+            Err(e) => log::error!("Comment posting task failed: {:?}", e),
         }
     }
-}
 
-async fn __json<T>(rb: reqwest::RequestBuilder) -> Result<T>
-where
-    T: for<'de> serde::Deserialize<'de>,
-{
-    __text(rb)
-        .await
-        .map(|body| Ok(serde_json::from_str(&body)?))?
-}
+    fn store_diff(
+        full_repo_name: &str,
+        pulls: &mut [structs::PullRequest],
+        result: std::result::Result<
+            (usize, error::Result<unidiff::PatchSet>),
+            tokio::task::JoinError,
+        >,
+    ) {
+        match result {
+            Ok((i, Ok(diff))) => pulls[i].diff = Some(diff),
+            Ok((i, Err(e))) => log::error!(
+                "Failed to fetch diff for pull #{} in {}: {:?}",
+                pulls[i].number,
+                full_repo_name,
+                e
+            ),
+            Err(e) => log::error!("Diff fetching task for {} failed: {:?}", full_repo_name, e),
+        }
+    }
 
-const INTERESTING_HEADERS: [&str; 7] = [
-    "etag",
-    "x-ratelimit-limit",
-    "x-ratelimit-remaining",
-    "x-ratelimit-reset",
-    "x-ratelimit-used",
-    "x-ratelimit-resource",
-    "x-github-request-id",
-];
+    async fn json<T>(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let body = self.text(context, rb).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
 
-async fn __text(rb: reqwest::RequestBuilder) -> Result<String> {
-    let prepared_request = rb.headers(Client::default_headers());
-    let mut url: Option<reqwest::Url> = None;
+    /// Same as [`Client::json`], but also return response headers (only those listed in [`INTERESTING_HEADERS`]).
+    async fn json_with_headers<T>(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<(T, HashMap<String, String>)>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let (body, headers) = self.text_with_headers(context, rb).await?;
+        Ok((serde_json::from_str(&body)?, headers))
+    }
 
-    let mut timer = ProgressiveTimeout::new(10);
-    while timer.tick().is_ok() {
-        match prepared_request.try_clone().unwrap().send().await {
-            Ok(response) => {
-                // Yes, you have to deconstruct the response by itself if you step from the trodden path
-                // (access URL and body, and do status checks at the same time).
-                // https://github.com/seanmonstar/reqwest/issues/1542
-                let headers: HashMap<String, String> = response
-                    .headers()
-                    .iter()
-                    .map(|(k, v)| {
-                        (
-                            k.to_string().to_lowercase(),
-                            v.to_str().unwrap_or("<none>").to_owned(),
-                        )
-                    })
-                    .filter(|(k, _)| INTERESTING_HEADERS.contains(&k.as_str()))
-                    .collect();
-                let status = response.status();
-                url = Some(response.url().clone());
-                let body = response.text().await;
+    async fn text(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<String> {
+        self.text_with_headers(context, rb)
+            .await
+            .map(|(body, _)| body)
+    }
 
-                let logging_string = format!(
-                    "HTTP {} {} ({}/{})",
-                    status,
-                    url.as_ref().unwrap(),
-                    timer.current_retry(),
-                    timer.max_retries(),
-                );
-                if status.is_client_error() || status.is_server_error() || body.is_err() {
-                    let can_be_retried = RETRYABLE_ERRORS.contains(&status.as_u16());
-                    let log_level = if can_be_retried {
-                        log::Level::Warn
-                    } else {
-                        log::Level::Error
-                    };
-                    log::log!(
-                        log_level,
-                        "{}. Headers: {:?} + body: {:?}",
-                        logging_string,
-                        headers,
-                        body
-                    );
+    async fn text_with_headers(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<(String, HashMap<String, String>)> {
+        let response = self.request(context, rb).await?;
+        Ok((response.body, response.headers))
+    }
 
-                    if can_be_retried {
-                        log::info!("Sleeping for {:?}...", timer.current_timeout);
-                        timer.sleep();
-                        continue;
-                    }
-                    eyre::bail!(logging_string);
+    /// Download the `.diff` of a pull and parse it as it arrives (see [`StreamingDiffParser`]), waiting for GitHub
+    /// to generate it if needed (see [`DIFF_GENERATION_ATTEMPTS`]).
+    async fn stream_pull_diff(
+        &self,
+        full_repo_name: &str,
+        pull_number: i32,
+        token: &str,
+    ) -> Result<unidiff::PatchSet> {
+        for attempt in 1..=DIFF_GENERATION_ATTEMPTS {
+            let req = self
+                .http_client
+                .get(self.urls.diff_url(full_repo_name, pull_number))
+                .bearer_auth(token);
+            let mut response = self
+                .stream(RequestContext::repo("read_pull_diff", full_repo_name), req)
+                .await?;
+            if response.status != reqwest::StatusCode::ACCEPTED {
+                let parse_error =
+                    |e: unidiff::Error| diff_parse_error(full_repo_name, pull_number, e);
+                let mut parser = StreamingDiffParser::new();
+                while let Some(chunk) = response.body.chunk().await? {
+                    parser.feed(&chunk).map_err(parse_error)?;
                 }
-
-                log::debug!("{}. Headers: {:?}", logging_string, headers);
-                return Ok(body.unwrap());
+                return Ok(parser.finish().map_err(parse_error)?);
             }
-            Err(e) => {
-                log::error!(
-                    "Error at {}: HTTP {:?}: {:?}",
-                    e.url().unwrap(),
-                    e.status(),
-                    e
-                );
-                return Err(e.into());
+            // Let go of the response (and its request slot, see `Client::with_max_concurrent_requests`) while waiting.
+            drop(response);
+            log::info!(
+                "Diff of pull #{} in {} is still being generated (attempt {}/{})",
+                pull_number,
+                full_repo_name,
+                attempt,
+                DIFF_GENERATION_ATTEMPTS
+            );
+            if attempt < DIFF_GENERATION_ATTEMPTS {
+                tokio::time::sleep(self.diff_generation_delay).await;
+            }
+        }
+        eyre::bail!(
+            "Diff of pull #{} in {} is still being generated after {} attempts",
+            pull_number,
+            full_repo_name,
+            DIFF_GENERATION_ATTEMPTS
+        )
+    }
+
+    /// Send a request (retrying if needed), and report it to [`Client::with_metrics`] under the name of the logical
+    /// operation it's a part of (e.g. `pulls`, see [`RequestContext`]).
+    async fn request(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<RawResponse> {
+        let response = self.send(context.into(), rb, false).await?;
+        Ok(RawResponse {
+            status: response.status,
+            body: response.body.into_text(),
+            headers: response.headers,
+            permit: None,
+        })
+    }
+
+    /// Same as [`Client::request`], but the body of the response is left to be read by the caller as it arrives,
+    /// e.g. with [`reqwest::Response::chunk`].
+    async fn stream(
+        &self,
+        context: impl Into<RequestContext>,
+        rb: reqwest::RequestBuilder,
+    ) -> Result<RawResponse<reqwest::Response>> {
+        let response = self.send(context.into(), rb, true).await?;
+        Ok(RawResponse {
+            status: response.status,
+            body: response.body.into_stream(),
+            headers: response.headers,
+            permit: response.permit,
+        })
+    }
+
+    async fn send(
+        &self,
+        mut context: RequestContext,
+        rb: reqwest::RequestBuilder,
+        stream_body: bool,
+    ) -> Result<RawResponse<ResponseBody>> {
+        if context.installation_id.is_none() && self.personal_token.is_none() {
+            context.installation_id = context
+                .full_repo_name
+                .as_deref()
+                .and_then(|full_repo_name| self.installation_for_repo(full_repo_name));
+        }
+        let permit = Arc::clone(&self.request_limit)
+            .acquire_owned()
+            .await
+            .map_err(eyre::Report::from)?;
+        let started_at = std::time::Instant::now();
+        let mut stats = RequestStats::default();
+        let response = __request(
+            &context,
+            rb,
+            self.max_attempts,
+            &self.throttle,
+            &mut stats,
+            stream_body,
+        )
+        .await;
+        self.metrics.record(&RequestMetrics {
+            operation: context.operation,
+            status: stats.status.map(|s| s.as_u16()),
+            latency: started_at.elapsed(),
+            retries: std::cmp::max(stats.attempts - 1, 0),
+        });
+        let mut response = response?;
+        if let Some(rate_limit) = rate_limit_from_headers(&response.headers) {
+            *self.last_rate_limit.lock().unwrap() = Some(rate_limit);
+        }
+        if stream_body {
+            response.permit = Some(permit);
+        }
+        Ok(response)
+    }
+
+    /// Fetch a page of pulls, sending the `ETag` of the previous response for the same URL (if it is still cached,
+    /// see [`PULLS_CACHE_PAGES`]).
+    /// GitHub answers with `304 Not Modified` if the page is unchanged, which doesn't count against the rate limit;
+    /// the previously parsed page is returned in that case. Without `use_cache`, the page is simply fetched.
+    async fn pulls_page(
+        &self,
+        context: RequestContext,
+        url: &reqwest::Url,
+        token: &str,
+        use_cache: bool,
+    ) -> Result<PullsPage> {
+        let cached = if use_cache {
+            self.pulls_cache.lock().unwrap().get(url.as_str())
+        } else {
+            None
+        };
+        let mut req = self.http_client.get(url.clone()).bearer_auth(token);
+        if let Some(page) = &cached {
+            req = req.header("If-None-Match", page.etag.as_str());
+        }
+        let response = self.request(context, req).await?;
+        if let Some(page) = cached {
+            if response.status == reqwest::StatusCode::NOT_MODIFIED {
+                log::debug!("{} is not modified, using cached pulls", url);
+                return Ok(page);
             }
         }
+        let page = PullsPage {
+            etag: response.headers.get("etag").cloned().unwrap_or_default(),
+            pulls: serde_json::from_str(&response.body)?,
+            next: response.headers.get("link").and_then(|l| next_page_url(l)),
+        };
+        if use_cache && !page.etag.is_empty() {
+            self.pulls_cache
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), page.clone());
+        }
+        Ok(page)
     }
-    eyre::bail!("Exhausted retries for {:?}, giving up", url)
-}
 
-impl Client {
     fn default_headers() -> reqwest::header::HeaderMap {
         let mut m = reqwest::header::HeaderMap::new();
         m.insert("Accept", "application/vnd.github+json".try_into().unwrap());
@@ -317,77 +2427,207 @@ impl Client {
     }
 
     // https://docs.github.com/en/developers/apps/building-github-apps/authenticating-with-github-apps#generating-a-json-web-token-jwt
-    fn generate_jwt(&self) -> Token {
+    fn generate_jwt(&self) -> Result<Token> {
         let claims = Claims::new(&self.app_id);
+        let key = jsonwebtoken::EncodingKey::from_rsa_pem(self.key.as_bytes())
+            .map_err(|e| eyre::eyre!("Failed to parse the GitHub App private key: {}", e))?;
         let t = jsonwebtoken::encode(
             &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
             &claims,
-            &jsonwebtoken::EncodingKey::from_rsa_pem(self.key.as_bytes()).unwrap(),
+            &key,
         )
-        .expect("failed to generate JWT");
-        Token {
+        .map_err(|e| eyre::eyre!("Failed to generate JWT: {}", e))?;
+        Ok(Token {
             t,
             ttype: TokenType::JWT,
             created_at: claims.created_at,
             expires_at: claims.expires_at,
+            repositories: None,
+        })
+    }
+
+    /// Point the repositories of an installation to it, forgetting the ones it no longer has access to.
+    fn index_installation(&self, installation: &structs::Installation) {
+        let mut index = self.repo_installations.lock().unwrap();
+        index.retain(|_, iid| *iid != installation.id);
+        for repo in installation.repositories.iter() {
+            index.insert(repo.full_name.clone(), installation.id);
         }
     }
 
+    /// Build the repository index from scratch, in case it went out of sync with the installations.
+    fn rebuild_repo_index(&self) {
+        let installations = self.installations.lock().unwrap();
+        let mut index = self.repo_installations.lock().unwrap();
+        index.clear();
+        for installation in installations.values() {
+            for repo in installation.repositories.iter() {
+                index.insert(repo.full_name.clone(), installation.id);
+            }
+        }
+    }
+
+    /// ID of the installation which has access to `full_repo_name` (the one whose token is used for its requests),
+    /// if any of the known installations does. Always `None` when using a personal access token.
+    pub fn installation_for_repo(&self, full_repo_name: &str) -> Option<i64> {
+        let cached = self
+            .repo_installations
+            .lock()
+            .unwrap()
+            .get(full_repo_name)
+            .copied();
+        cached.or_else(|| {
+            self.rebuild_repo_index();
+            self.repo_installations
+                .lock()
+                .unwrap()
+                .get(full_repo_name)
+                .copied()
+        })
+    }
+
+    fn is_suspended(&self, installation_id: i64) -> bool {
+        self.installations
+            .lock()
+            .unwrap()
+            .get(&installation_id)
+            .is_some_and(|i| i.is_suspended())
+    }
+
     async fn pick_token(&self, full_repo_name: &str) -> Result<String> {
-        let mut installation_id = None;
-        for (k, v) in self.installations.lock().unwrap().iter() {
-            if v.repositories.iter().any(|r| r.full_name == full_repo_name) {
-                installation_id = Some(*k);
-                break;
+        if let Some(pat) = &self.personal_token {
+            return Ok(pat.clone());
+        }
+        match self.installation_for_repo(full_repo_name) {
+            Some(iid) if self.is_suspended(iid) => {
+                log::warn!(
+                    "Installation {} with access to {} is suspended, not using its token",
+                    iid,
+                    full_repo_name
+                );
+                Err(ObservatoryError::NoTokenForRepo(full_repo_name.to_string()).into())
+            }
+            None => Err(ObservatoryError::NoTokenForRepo(full_repo_name.to_string()).into()),
+            Some(iid) => {
+                let mut token = self.installation_token(iid).await?;
+                if !token.covers(full_repo_name) {
+                    // The repository may have been added to the installation after the token was created.
+                    log::info!(
+                        "Token of installation {} doesn't cover {}, requesting a new one",
+                        iid,
+                        full_repo_name
+                    );
+                    let ttype = TokenType::Installation(iid);
+                    if let Err(e) = self.tokens.remove(&ttype).await {
+                        log::warn!("Failed to remove {:?} from the token store: {:?}", ttype, e);
+                    }
+                    token = self.installation_token(iid).await?;
+                }
+                if !token.covers(full_repo_name) {
+                    log::error!(
+                        "Installation {} has access to {}, but its token is limited to other repositories: {:?}",
+                        iid,
+                        full_repo_name,
+                        token.repositories.unwrap_or_default()
+                    );
+                    return Err(ObservatoryError::NoTokenForRepo(full_repo_name.to_string()).into());
+                }
+                Ok(token.t)
             }
         }
-        match installation_id {
-            None => eyre::bail!("No GitHub token for {} found", full_repo_name),
-            Some(iid) => self.get_installation_token(iid).await,
+    }
+
+    /// Fetch every page of a list endpoint, starting with `first_page` and then following `rel="next"` links
+    /// until GitHub stops providing them.
+    async fn all_pages<T>(
+        &self,
+        context: impl Into<RequestContext>,
+        first_page: reqwest::RequestBuilder,
+        token: &str,
+    ) -> Result<Vec<T>>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let context = context.into();
+        let mut out = Vec::new();
+        let mut req = first_page;
+        loop {
+            let (mut response, headers): (Vec<T>, _) =
+                self.json_with_headers(context.clone(), req).await?;
+            out.append(&mut response);
+            match headers.get("link").and_then(|l| next_page_url(l)) {
+                Some(url) => req = self.http_client.get(url).bearer_auth(token),
+                None => break,
+            }
         }
+        Ok(out)
     }
 
-    async fn cached_token(&self, ttype: &TokenType) -> Option<String> {
-        let tokens = self.tokens.lock().unwrap();
-        if let Some(tt) = tokens.get(ttype) {
-            if !tt.expired() {
-                return Some(tt.t.clone());
+    async fn cached_token(&self, ttype: &TokenType) -> Option<Token> {
+        match self.tokens.get(ttype).await {
+            Ok(Some(tt)) if !tt.expires_within(TOKEN_EXPIRY_MARGIN) => Some(tt),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Failed to read {:?} from the token store: {:?}", ttype, e);
+                None
             }
         }
-        None
     }
 
-    async fn get_jwt_token(&self) -> String {
+    async fn store_token(&self, token: Token) {
+        let ttype = token.ttype.clone();
+        if let Err(e) = self.tokens.put(token).await {
+            log::warn!("Failed to save {:?} to the token store: {:?}", ttype, e);
+        }
+    }
+
+    async fn get_jwt_token(&self) -> Result<String> {
         let ttype = TokenType::JWT;
         match self.cached_token(&ttype).await {
-            Some(t) => t,
+            Some(t) => Ok(t.t),
             None => {
-                let token = self.generate_jwt();
-                self.tokens.lock().unwrap().insert(ttype, token.clone());
-                token.t
+                let token = self.generate_jwt()?;
+                self.store_token(token.clone()).await;
+                Ok(token.t)
             }
         }
     }
 
     async fn get_installation_token(&self, installation_id: i64) -> Result<String> {
+        if let Some(pat) = &self.personal_token {
+            return Ok(pat.clone());
+        }
+        Ok(self.installation_token(installation_id).await?.t)
+    }
+
+    /// A cached or a new installation token, along with the repositories it's limited to (see [`Token::repositories`]).
+    async fn installation_token(&self, installation_id: i64) -> Result<Token> {
         let ttype = TokenType::Installation(installation_id);
         match self.cached_token(&ttype).await {
             Some(t) => Ok(t),
             None => {
-                let jwt = self.get_jwt_token().await;
+                let jwt = self.get_jwt_token().await?;
                 let req = self
                     .http_client
-                    .post(GitHub::installation_tokens(installation_id))
+                    .post(self.urls.installation_tokens(installation_id))
                     .bearer_auth(jwt);
-                let response: structs::InstallationToken = __json(req).await?;
+                let response: structs::InstallationToken = self
+                    .json(
+                        RequestContext::installation("installation_token", installation_id),
+                        req,
+                    )
+                    .await?;
                 let token = Token {
                     t: response.token,
-                    ttype: ttype.clone(),
+                    ttype,
                     created_at: chrono::Utc::now(),
                     expires_at: response.expires_at - chrono::Duration::minutes(5),
+                    repositories: response
+                        .repositories
+                        .map(|repos| repos.into_iter().map(|r| r.full_name).collect()),
                 };
-                self.tokens.lock().unwrap().insert(ttype, token.clone());
-                Ok(token.t)
+                self.store_token(token.clone()).await;
+                Ok(token)
             }
         }
     }
@@ -396,21 +2636,33 @@ impl Client {
 #[async_trait]
 impl GitHubInterface for Client {
     fn new(app_id: String, key: String) -> Self {
-        Self {
+        Self::with_http_client(
             app_id,
             key,
-            http_client: reqwest::Client::new(),
-            tokens: Arc::new(Mutex::new(HashMap::new())),
-            installations: Arc::new(Mutex::new(HashMap::new())),
-        }
+            Self::http_client(DEFAULT_REQUEST_TIMEOUT, DEFAULT_CONNECT_TIMEOUT),
+        )
+    }
+
+    fn urls(&self) -> &GitHub {
+        &self.urls
+    }
+
+    fn bot_login(&self) -> Option<String> {
+        self.bot_login.clone()
     }
 
-    async fn app(&self) -> Result<structs::App> {
+    async fn app(&self) -> error::Result<structs::App> {
+        if self.personal_token.is_some() {
+            return Err(eyre::eyre!(
+                "App information is not available when using a personal access token"
+            )
+            .into());
+        }
         let pp = self
             .http_client
-            .get(GitHub::app())
-            .bearer_auth(self.get_jwt_token().await);
-        let app: structs::App = __json(pp).await?;
+            .get(self.urls.app())
+            .bearer_auth(self.get_jwt_token().await?);
+        let app: structs::App = self.json("app", pp).await?;
         Ok(app)
     }
 
@@ -424,36 +2676,148 @@ impl GitHubInterface for Client {
     }
 
     fn update_cached_installation(&self, installation: structs::Installation) {
+        self.index_installation(&installation);
         self.installations
             .lock()
             .unwrap()
             .insert(installation.id, installation);
     }
 
+    /// Add and remove repositories of a cached installation, returning its updated version.
+    /// Unknown installations are cached as well, though they will only list the added repositories.
+    fn apply_installation_repositories_event(
+        &self,
+        evt: &structs::InstallationRepositoriesEvent,
+    ) -> structs::Installation {
+        let updated = {
+            let mut installations = self.installations.lock().unwrap();
+            let installation = installations
+                .entry(evt.installation.id)
+                .or_insert_with(|| evt.installation.clone());
+            installation
+                .apply_repository_changes(&evt.repositories_added, &evt.repositories_removed);
+            installation.clone()
+        };
+        self.index_installation(&updated);
+        updated
+    }
+
     // TODO: confirm that this is actually needed (see similar stuff below)
-    async fn installations(&self) -> Result<Vec<structs::Installation>> {
-        let pp = self
+    async fn installations(&self) -> error::Result<Vec<structs::Installation>> {
+        if self.personal_token.is_some() {
+            return Err(eyre::eyre!(
+                "Installations are not available when using a personal access token"
+            )
+            .into());
+        }
+        let token = self.get_jwt_token().await?;
+        let req = self
             .http_client
-            .get(GitHub::app_installations())
-            .bearer_auth(self.get_jwt_token().await);
-        let items: Vec<structs::Installation> = __json(pp).await?;
-        Ok(items)
+            .get(self.urls.app_installations())
+            .query(&[("per_page", "100")])
+            .bearer_auth(token.clone());
+        Ok(self.all_pages("installations", req, &token).await?)
     }
 
-    async fn discover_installations(&self) -> Result<Vec<structs::Installation>> {
-        let mut ret = Vec::new();
-        if let Ok(installations) = self.installations().await {
-            for installation in installations {
-                ret.push(self.add_installation(installation).await?);
+    async fn discover_installations(&self) -> error::Result<Discovery> {
+        let mut discovery = Discovery::default();
+        if self.personal_token.is_some() {
+            // There is nothing to discover: the token already gives access to every repository.
+            return Ok(discovery);
+        }
+        let installations = self.installations().await?;
+        // Installations are set up concurrently, each with a time limit, so that a slow one doesn't hold up the rest.
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut results = Vec::new();
+        // Installations whose tasks are spawned, so that the ones whose tasks panicked can still be reported.
+        let mut spawned = Vec::new();
+        for (i, installation) in installations.into_iter().enumerate() {
+            if installation.is_suspended() {
+                log::warn!(
+                    "Skipping installation {} ({}), which is suspended",
+                    installation.id,
+                    installation.account.login
+                );
+                // Still cache it (replacing an older version, if any), so that its token isn't used anymore.
+                self.update_cached_installation(installation.clone());
+                discovery.suspended.push(installation);
+                continue;
+            }
+            if tasks.len() >= self.discovery_concurrency {
+                if let Some(result) = tasks.join_next().await {
+                    results.push(result);
+                }
+            }
+            let client = self.clone();
+            spawned.push((i, installation.clone()));
+            tasks.spawn(async move {
+                let result = tokio::time::timeout(
+                    client.discovery_timeout,
+                    client.add_installation(installation.clone()),
+                )
+                .await;
+                (i, installation, result)
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            results.push(result);
+        }
+
+        let mut results: Vec<_> = results
+            .into_iter()
+            .filter_map(|r| match r {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    log::error!("Installation setup task failed: {:?}", e);
+                    None
+                }
+            })
+            .collect();
+        for (i, installation) in spawned {
+            if !results.iter().any(|(j, _, _)| *j == i) {
+                discovery
+                    .failed
+                    .push((installation, "Setup task failed".to_string()));
+            }
+        }
+        results.sort_by_key(|(i, _, _)| *i);
+        for (_, installation, result) in results {
+            match result {
+                Ok(Ok(installation)) => discovery.added.push(installation),
+                Ok(Err(e)) => {
+                    log::error!(
+                        "Failed to set up installation {} ({}), skipping it: {}",
+                        installation.id,
+                        installation.account.login,
+                        e
+                    );
+                    discovery.failed.push((installation, e.to_string()));
+                }
+                Err(_) => {
+                    log::error!(
+                        "Setting up installation {} ({}) took longer than {:?}, skipping it",
+                        installation.id,
+                        installation.account.login,
+                        self.discovery_timeout
+                    );
+                    let reason = format!("Timed out after {:?}", self.discovery_timeout);
+                    discovery.failed.push((installation, reason));
+                }
             }
         }
-        Ok(ret)
+        log::info!(
+            "Discovered {} installations ({} suspended, {} failed)",
+            discovery.added.len(),
+            discovery.suspended.len(),
+            discovery.failed.len()
+        );
+        Ok(discovery)
     }
 
     async fn add_installation(
         &self,
         mut installation: structs::Installation,
-    ) -> Result<structs::Installation> {
+    ) -> error::Result<structs::Installation> {
         match self.get_installation_token(installation.id).await {
             Err(e) => {
                 log::error!(
@@ -461,17 +2825,23 @@ impl GitHubInterface for Client {
                     installation.id,
                     e
                 );
-                Err(e)
+                Err(e.into())
             }
             Ok(token) => {
                 let req = self
                     .http_client
-                    .get(GitHub::installation_repos())
+                    .get(self.urls.installation_repos())
                     .bearer_auth(token);
-                match __json::<structs::InstallationRepositories>(req).await {
+                match self
+                    .json::<structs::InstallationRepositories>(
+                        RequestContext::installation("installation_repositories", installation.id),
+                        req,
+                    )
+                    .await
+                {
                     Err(e) => {
                         log::error!("Failed to fetch list of repositories for a fresh installation {}: {:?}", installation.id, e);
-                        Err(e)
+                        Err(e.into())
                     }
                     Ok(response) => {
                         installation.repositories = response.repositories;
@@ -483,39 +2853,73 @@ impl GitHubInterface for Client {
         }
     }
 
-    fn remove_installation(&self, installation: &structs::Installation) {
+    async fn remove_installation(&self, installation: &structs::Installation) {
         self.installations.lock().unwrap().remove(&installation.id);
-        self.tokens
+        self.repo_installations
             .lock()
             .unwrap()
-            .remove(&TokenType::Installation(installation.id));
+            .retain(|_, iid| *iid != installation.id);
+        let ttype = TokenType::Installation(installation.id);
+        if let Err(e) = self.tokens.remove(&ttype).await {
+            log::warn!("Failed to remove {:?} from the token store: {:?}", ttype, e);
+        }
     }
 
-    async fn pulls(&self, full_repo_name: &str) -> Result<Vec<structs::PullRequest>> {
-        let mut out = Vec::new();
-        let token = self.pick_token(full_repo_name).await?;
-        let per_page = 100;
+    async fn refresh_installation(
+        &self,
+        installation_id: i64,
+    ) -> error::Result<structs::Installation> {
+        let cached = self
+            .installations
+            .lock()
+            .unwrap()
+            .get(&installation_id)
+            .cloned();
+        match cached {
+            Some(installation) => self.add_installation(installation).await,
+            None => Err(eyre::eyre!("Installation {} is not known", installation_id).into()),
+        }
+    }
 
-        for page in 1..100 {
-            let req = self
-                .http_client
-                .get(GitHub::pulls(full_repo_name))
-                .query(&[
-                    ("state", "open"),
-                    ("direction", "asc"),
-                    ("sort", "created"),
-                    ("per_page", &per_page.to_string()),
-                    ("page", &page.to_string()),
-                ])
-                .bearer_auth(token.clone());
-            let mut response: Vec<structs::PullRequest> = __json(req).await?;
-            let is_last_page = response.len() < per_page;
-            out.append(&mut response);
-            if is_last_page {
-                break;
-            }
+    fn mute_repo(&self, full_repo_name: &str) {
+        log::info!("Muting {}", full_repo_name);
+        self.muted_repos
+            .lock()
+            .unwrap()
+            .insert(full_repo_name.to_string());
+    }
+
+    fn unmute_repo(&self, full_repo_name: &str) {
+        log::info!("Unmuting {}", full_repo_name);
+        self.muted_repos.lock().unwrap().remove(full_repo_name);
+    }
+
+    fn is_repo_muted(&self, full_repo_name: &str) -> bool {
+        self.muted_repos.lock().unwrap().contains(full_repo_name)
+    }
+
+    async fn pulls(&self, full_repo_name: &str) -> error::Result<Vec<structs::PullRequest>> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!("{} is muted, skipping its pulls", full_repo_name);
+            return Ok(Vec::new());
         }
-        Ok(out)
+        self.pulls_with_state(full_repo_name, structs::PullState::Open)
+            .await
+    }
+
+    async fn get_pull(
+        &self,
+        full_repo_name: &str,
+        pull_number: i32,
+    ) -> error::Result<structs::PullRequest> {
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .get(self.urls.pull(full_repo_name, pull_number))
+            .bearer_auth(token);
+        Ok(self
+            .json(RequestContext::repo("get_pull", full_repo_name), req)
+            .await?)
     }
 
     async fn post_comment(
@@ -523,16 +2927,26 @@ impl GitHubInterface for Client {
         full_repo_name: &str,
         issue_number: i32,
         body: String,
-    ) -> Result<()> {
+    ) -> error::Result<Option<structs::IssueCommentResponse>> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, not posting a comment to {}",
+                full_repo_name,
+                self.urls.pull_url(full_repo_name, issue_number)
+            );
+            return Ok(None);
+        }
         let comment = serde_json::to_string(&structs::PostIssueComment { body }).unwrap();
         let token = self.pick_token(full_repo_name).await?;
         let req = self
             .http_client
-            .post(GitHub::comments(full_repo_name, issue_number))
+            .post(self.urls.comments(full_repo_name, issue_number))
             .body(comment)
             .bearer_auth(token);
-        __json::<structs::IssueComment>(req).await?;
-        Ok(())
+        Ok(Some(
+            self.json(RequestContext::repo("post_comment", full_repo_name), req)
+                .await?,
+        ))
     }
 
     async fn update_comment(
@@ -540,15 +2954,46 @@ impl GitHubInterface for Client {
         full_repo_name: &str,
         comment_id: i64,
         body: String,
-    ) -> Result<()> {
+    ) -> error::Result<()> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, not updating comment #{}",
+                full_repo_name,
+                comment_id
+            );
+            return Ok(());
+        }
         let comment = serde_json::to_string(&structs::PostIssueComment { body }).unwrap();
         let token = self.pick_token(full_repo_name).await?;
         let req = self
             .http_client
-            .patch(GitHub::issue_comment(full_repo_name, comment_id))
+            .patch(self.urls.issue_comment(full_repo_name, comment_id))
             .body(comment)
             .bearer_auth(token);
-        __json::<structs::IssueComment>(req).await?;
+        self.json::<structs::IssueComment>(
+            RequestContext::repo("update_comment", full_repo_name),
+            req,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_comment(&self, full_repo_name: &str, comment_id: i64) -> error::Result<()> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, not deleting comment #{}",
+                full_repo_name,
+                comment_id
+            );
+            return Ok(());
+        }
+        let token = self.pick_token(full_repo_name).await?;
+        let req = self
+            .http_client
+            .delete(self.urls.issue_comment(full_repo_name, comment_id))
+            .bearer_auth(token);
+        self.request(RequestContext::repo("delete_comment", full_repo_name), req)
+            .await?;
         Ok(())
     }
 
@@ -556,43 +3001,92 @@ impl GitHubInterface for Client {
         &self,
         full_repo_name: &str,
         issue_number: i32,
-    ) -> Result<Vec<structs::IssueComment>> {
-        let mut out = Vec::new();
+    ) -> error::Result<Vec<structs::IssueComment>> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, not listing comments of {}",
+                full_repo_name,
+                self.urls.pull_url(full_repo_name, issue_number)
+            );
+            return Ok(Vec::new());
+        }
         let token = self.pick_token(full_repo_name).await?;
-        let per_page = 100;
+        let req = self
+            .http_client
+            .get(self.urls.comments(full_repo_name, issue_number))
+            .query(&[("per_page", "100")])
+            .bearer_auth(token.clone());
+        Ok(self
+            .all_pages(
+                RequestContext::repo("list_comments", full_repo_name),
+                req,
+                &token,
+            )
+            .await?)
+    }
 
-        for page in 1..100 {
+    async fn read_pull_diff(
+        &self,
+        full_repo_name: &str,
+        pull_number: i32,
+    ) -> error::Result<unidiff::PatchSet> {
+        if self.is_repo_muted(full_repo_name) {
+            log::info!(
+                "{} is muted, not reading the diff of pull #{}",
+                full_repo_name,
+                pull_number
+            );
+            return Ok(unidiff::PatchSet::new());
+        }
+        let token = self.pick_token(full_repo_name).await?;
+        let diff = if self.use_files_api {
             let req = self
                 .http_client
-                .get(GitHub::comments(full_repo_name, issue_number))
-                .query(&[
-                    ("per_page", &per_page.to_string()),
-                    ("page", &page.to_string()),
-                ])
+                .get(self.urls.pull_files(full_repo_name, pull_number))
+                .query(&[("per_page", "100")])
                 .bearer_auth(token.clone());
-            let mut response: Vec<structs::IssueComment> = __json(req).await?;
-            let is_last_page = response.len() < per_page;
-            out.append(&mut response);
-            if is_last_page {
-                break;
-            }
+            let files: Vec<structs::PullRequestFile> = self
+                .all_pages(
+                    RequestContext::repo("read_pull_diff", full_repo_name),
+                    req,
+                    &token,
+                )
+                .await?;
+            unidiff::PatchSet::from_str(&files_to_diff(&files))
+                .map_err(|e| diff_parse_error(full_repo_name, pull_number, e))?
+        } else {
+            self.stream_pull_diff(full_repo_name, pull_number, &token)
+                .await?
+        };
+        if diff.is_empty() {
+            log::warn!(
+                "Diff of pull #{} in {} has no files (only merge commits or mode changes?)",
+                pull_number,
+                full_repo_name
+            );
         }
-        Ok(out)
+        Ok(diff)
     }
 
-    async fn read_pull_diff(
+    async fn read_file(
         &self,
         full_repo_name: &str,
-        pull_number: i32,
-    ) -> Result<unidiff::PatchSet> {
+        path: &str,
+        ref_name: &str,
+    ) -> error::Result<String> {
         let token = self.pick_token(full_repo_name).await?;
         let req = self
             .http_client
-            .get(GitHub::diff_url(full_repo_name, pull_number))
+            .get(self.urls.contents(full_repo_name, path))
+            .query(&[("ref", ref_name)])
+            .header("Accept", "application/vnd.github.raw")
             .bearer_auth(token);
-        let response = __text(req).await?;
-        Ok(unidiff::PatchSet::from_str(&response)?)
+        Ok(self
+            .text(RequestContext::repo("read_file", full_repo_name), req)
+            .await?)
     }
 }
 
-// TODO: add tests
+#[cfg(test)]
+#[path = "github_test.rs"]
+pub(crate) mod tests;
@@ -0,0 +1,2025 @@
+use super::*;
+use crate::test;
+
+fn make_token(expires_at: chrono::DateTime<chrono::Utc>) -> Token {
+    Token {
+        t: "token".to_string(),
+        ttype: TokenType::Installation(1),
+        created_at: chrono::Utc::now() - chrono::Duration::hours(1),
+        expires_at,
+        repositories: None,
+    }
+}
+
+#[test]
+fn token_expired() {
+    let token = make_token(chrono::Utc::now() - chrono::Duration::minutes(1));
+    assert!(token.expired());
+}
+
+#[test]
+fn token_not_expired() {
+    let token = make_token(chrono::Utc::now() + chrono::Duration::minutes(10));
+    assert!(!token.expired());
+}
+
+#[test]
+fn token_expires_within_margin() {
+    let token = make_token(chrono::Utc::now() + chrono::Duration::seconds(10));
+    assert!(!token.expired());
+    assert!(token.expires_within(TOKEN_EXPIRY_MARGIN));
+    assert!(!token.expires_within(Duration::from_secs(1)));
+}
+
+#[test]
+fn retry_after_parsing() {
+    assert_eq!(parse_retry_after("60"), Some(Duration::from_secs(60)));
+    assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+}
+
+#[test]
+fn retryable_statuses() {
+    let retry_after = Some(Duration::from_secs(1));
+    for code in [500, 502, 503, 504] {
+        let status = reqwest::StatusCode::from_u16(code).unwrap();
+        assert!(is_retryable(status, None));
+    }
+    for code in [403, 429] {
+        let status = reqwest::StatusCode::from_u16(code).unwrap();
+        assert!(is_retryable(status, retry_after));
+        assert!(!is_retryable(status, None));
+    }
+    for code in [400, 401, 404, 422] {
+        let status = reqwest::StatusCode::from_u16(code).unwrap();
+        assert!(!is_retryable(status, retry_after));
+    }
+}
+
+#[test]
+fn jitter_is_bounded() {
+    let timeout = Duration::from_secs(1);
+    for _ in 0..10 {
+        let t = with_jitter(timeout);
+        assert!(t >= timeout);
+        assert!(t <= timeout.mul_f32(1.25));
+    }
+}
+
+#[test]
+fn link_header_pagination() {
+    // First page: both "next" and "last" are present.
+    let first_page = r#"<https://api.github.com/repositories/1/pulls?state=open&per_page=100&page=2>; rel="next", <https://api.github.com/repositories/1/pulls?state=open&per_page=100&page=2>; rel="last""#;
+    assert_eq!(
+        next_page_url(first_page),
+        Some(
+            "https://api.github.com/repositories/1/pulls?state=open&per_page=100&page=2"
+                .to_string()
+        )
+    );
+
+    // Last page: only links backwards, so the pagination stops.
+    let last_page = r#"<https://api.github.com/repositories/1/pulls?state=open&per_page=100&page=1>; rel="prev", <https://api.github.com/repositories/1/pulls?state=open&per_page=100&page=1>; rel="first""#;
+    assert_eq!(next_page_url(last_page), None);
+}
+
+#[test]
+fn link_header_garbage() {
+    assert_eq!(next_page_url(""), None);
+    assert_eq!(next_page_url("not a link"), None);
+    assert_eq!(next_page_url(r#"<https://example.com>; rel="last""#), None);
+}
+
+#[test]
+fn default_urls() {
+    let urls = GitHub::default();
+    assert_eq!(
+        urls.pulls("ppy/osu-wiki"),
+        "https://api.github.com/repos/ppy/osu-wiki/pulls"
+    );
+    assert_eq!(
+        urls.pull("ppy/osu-wiki", 1),
+        "https://api.github.com/repos/ppy/osu-wiki/pulls/1"
+    );
+    assert_eq!(
+        urls.diff_url("ppy/osu-wiki", 1),
+        "https://github.com/ppy/osu-wiki/pull/1.diff"
+    );
+    assert_eq!(urls.graphql(), "https://api.github.com/graphql");
+}
+
+#[test]
+fn contents_url_encoding() {
+    let urls = GitHub::default();
+    assert_eq!(
+        urls.contents("ppy/osu-wiki", "wiki/Article/en.md"),
+        "https://api.github.com/repos/ppy/osu-wiki/contents/wiki/Article/en.md"
+    );
+    assert_eq!(
+        urls.contents("ppy/osu-wiki", "wiki/C# & more/Ünïcode?.md"),
+        "https://api.github.com/repos/ppy/osu-wiki/contents/wiki/C%23%20%26%20more/%C3%9Cn%C3%AFcode%3F.md"
+    );
+}
+
+#[test]
+fn enterprise_urls() {
+    let urls = GitHub::new(
+        "https://github.example.com/api/v3/",
+        "https://github.example.com/",
+    );
+    assert_eq!(
+        urls.comments("ppy/osu-wiki", 1),
+        "https://github.example.com/api/v3/repos/ppy/osu-wiki/issues/1/comments"
+    );
+    assert_eq!(
+        urls.pull_url("ppy/osu-wiki", 1),
+        "https://github.example.com/ppy/osu-wiki/pull/1"
+    );
+    assert_eq!(
+        urls.diff_url("ppy/osu-wiki", 1),
+        "https://github.example.com/ppy/osu-wiki/pull/1.diff"
+    );
+    assert_eq!(urls.graphql(), "https://github.example.com/api/graphql");
+}
+
+// https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries#testing-the-webhook-payload-validation
+const WEBHOOK_SECRET: &str = "It's a Secret to Everybody";
+const WEBHOOK_SIGNATURE: &str =
+    "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+#[test]
+fn signature_valid() {
+    assert!(verify_signature(
+        WEBHOOK_SECRET,
+        b"Hello, World!",
+        WEBHOOK_SIGNATURE
+    ));
+}
+
+#[test]
+fn signature_tampered_body() {
+    assert!(!verify_signature(
+        WEBHOOK_SECRET,
+        b"Hello, World?",
+        WEBHOOK_SIGNATURE
+    ));
+    assert!(!verify_signature(
+        "wrong secret",
+        b"Hello, World!",
+        WEBHOOK_SIGNATURE
+    ));
+}
+
+#[test]
+fn signature_malformed_header() {
+    let payload = b"Hello, World!";
+    assert!(!verify_signature(WEBHOOK_SECRET, payload, ""));
+    assert!(!verify_signature(
+        WEBHOOK_SECRET,
+        payload,
+        WEBHOOK_SIGNATURE.strip_prefix("sha256=").unwrap()
+    ));
+    assert!(!verify_signature(WEBHOOK_SECRET, payload, "sha256=abc"));
+    assert!(!verify_signature(WEBHOOK_SECRET, payload, "sha256=zz"));
+}
+
+// A ping sent when a GitHub App is created (no repository), trimmed.
+const PING_EVENT: &str = r#"{
+  "zen": "Design for failure.",
+  "hook_id": 419528421,
+  "hook": {
+    "type": "App",
+    "id": 419528421,
+    "name": "web",
+    "active": true,
+    "events": ["installation", "installation_repositories", "pull_request"],
+    "config": {"content_type": "json", "insecure_ssl": "0", "secret": "********", "url": "https://example.com/github"},
+    "updated_at": "2023-06-05T18:03:43Z",
+    "created_at": "2023-06-05T18:03:43Z",
+    "app_id": 341723,
+    "deliveries_url": "https://api.github.com/app/hook/deliveries"
+  }
+}"#;
+
+// A command left on a pull request, trimmed.
+const ISSUE_COMMENT_EVENT: &str = r#"{
+  "action": "created",
+  "comment": {
+    "id": 1,
+    "body": "/observatory rescan",
+    "user": {"id": 2, "login": "ppy"},
+    "created_at": "2023-01-01T00:00:00Z",
+    "updated_at": "2023-01-01T00:00:00Z"
+  },
+  "issue": {
+    "number": 3,
+    "title": "Update article",
+    "state": "open",
+    "user": {"id": 2, "login": "ppy"},
+    "pull_request": {
+      "url": "https://api.github.com/repos/ppy/osu-wiki/pulls/3",
+      "html_url": "https://github.com/ppy/osu-wiki/pull/3"
+    }
+  },
+  "repository": {"id": 4, "name": "osu-wiki", "full_name": "ppy/osu-wiki"},
+  "installation": {"id": 5},
+  "sender": {"id": 2, "login": "ppy"}
+}"#;
+
+// A review submitted on a pull request, trimmed.
+const PULL_REQUEST_REVIEW_EVENT: &str = r#"{
+  "action": "submitted",
+  "review": {
+    "id": 1,
+    "user": {"id": 1, "login": "BanchoBot"},
+    "body": "Checked the other pull, no conflicts",
+    "state": "commented",
+    "submitted_at": "2023-01-02T00:00:00Z"
+  },
+  "pull_request": {
+    "id": 3,
+    "number": 3,
+    "state": "open",
+    "title": "Update article",
+    "user": {"id": 1, "login": "BanchoBot"},
+    "html_url": "https://github.com/ppy/osu-wiki/pull/3",
+    "created_at": "2023-01-01T00:00:00Z",
+    "updated_at": "2023-01-01T00:00:00Z",
+    "draft": false,
+    "base": {"ref": "master", "sha": "2c8c1482b97", "repo": null},
+    "head": {"ref": "branch", "sha": "5483f282a0a", "repo": null}
+  },
+  "repository": {"id": 4, "name": "osu-wiki", "full_name": "ppy/osu-wiki"},
+  "installation": {"id": 5},
+  "sender": {"id": 1, "login": "BanchoBot"}
+}"#;
+
+#[test]
+fn webhook_events() {
+    match WebhookEvent::parse("ping", PING_EVENT).unwrap() {
+        WebhookEvent::Ping(evt) => {
+            assert_eq!(evt.hook_id, 419528421);
+            assert_eq!(evt.zen, "Design for failure.");
+            assert!(evt.repository.is_none());
+        }
+        evt => panic!("Expected a ping, got {:?}", evt),
+    }
+    let repository = r#"{"id": 1, "name": "osu-wiki", "full_name": "ppy/osu-wiki", "fork": false}"#;
+    let ping = format!(
+        r#"{{"zen": "Keep it logically awesome.", "hook_id": 1, "repository": {repository}}}"#
+    );
+    assert!(matches!(
+        WebhookEvent::parse("ping", &ping).unwrap(),
+        WebhookEvent::Ping(evt) if evt.repository.as_ref().is_some_and(|r| r.full_name == "ppy/osu-wiki")
+    ));
+
+    // A ping isn't a pull request event.
+    assert!(WebhookEvent::parse("pull_request", PING_EVENT).is_err());
+    assert!(matches!(
+        WebhookEvent::parse("star", "not even JSON").unwrap(),
+        WebhookEvent::Other(event_type) if event_type == "star"
+    ));
+}
+
+#[test]
+fn comment_and_review_events() {
+    match WebhookEvent::parse("issue_comment", ISSUE_COMMENT_EVENT).unwrap() {
+        WebhookEvent::IssueComment(evt) => {
+            assert_eq!(evt.action, "created");
+            assert_eq!(evt.comment.body, "/observatory rescan");
+            assert_eq!(evt.issue.number, 3);
+            assert!(evt.issue.is_pull_request());
+        }
+        evt => panic!("Expected an issue comment, got {:?}", evt),
+    }
+    match WebhookEvent::parse("pull_request_review", PULL_REQUEST_REVIEW_EVENT).unwrap() {
+        WebhookEvent::PullRequestReview(evt) => {
+            assert_eq!(evt.action, "submitted");
+            assert_eq!(evt.review.id, 1);
+            assert_eq!(evt.pull_request.number, 3);
+            assert_eq!(evt.repository.full_name, "ppy/osu-wiki");
+        }
+        evt => panic!("Expected a pull request review, got {:?}", evt),
+    }
+
+    // The payloads aren't interchangeable.
+    assert!(WebhookEvent::parse("issue_comment", PULL_REQUEST_REVIEW_EVENT).is_err());
+    assert!(WebhookEvent::parse("pull_request_review", ISSUE_COMMENT_EVENT).is_err());
+}
+
+#[test]
+fn error_without_url() {
+    let e = reqwest::Client::new().get("not a url").build().unwrap_err();
+    assert!(e.url().is_none());
+    assert_eq!(error_url(&e, None), "unknown URL");
+
+    let last_url = reqwest::Url::parse("https://api.github.com/app").unwrap();
+    assert_eq!(error_url(&e, Some(&last_url)), "https://api.github.com/app");
+}
+
+#[test]
+fn rate_limit_headers() {
+    let mut headers: HashMap<String, String> = [
+        ("x-ratelimit-limit", "5000"),
+        ("x-ratelimit-remaining", "4987"),
+        ("x-ratelimit-used", "13"),
+        ("x-ratelimit-reset", "1372700873"),
+    ]
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect();
+    assert_eq!(
+        rate_limit_from_headers(&headers),
+        Some(structs::RateLimit {
+            limit: 5000,
+            remaining: 4987,
+            used: 13,
+            reset: chrono::Utc.timestamp_opt(1372700873, 0).unwrap(),
+        })
+    );
+
+    headers.remove("x-ratelimit-reset");
+    assert_eq!(rate_limit_from_headers(&headers), None);
+}
+
+#[test]
+fn rate_limit_response() {
+    let response: structs::RateLimitResponse = serde_json::from_str(
+        r#"{
+            "resources": {},
+            "rate": {"limit": 5000, "remaining": 4999, "reset": 1372700873, "used": 1, "resource": "core"}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(response.rate.remaining, 4999);
+    assert_eq!(response.rate.reset.timestamp(), 1372700873);
+}
+
+#[tokio::test]
+async fn load_diffs_failure_keeps_pulls() {
+    // No installations are known, so every diff request fails before reaching the network.
+    let client = Client::new("123".to_string(), "key".to_string());
+    let mut pulls: Vec<_> = (1..=(DIFF_CONCURRENCY as i64 + 2))
+        .map(|i| {
+            let mut p = crate::test::make_pull(i, &["wiki/Article/en.md"]);
+            p.diff = None;
+            p
+        })
+        .collect();
+
+    client.load_diffs("test/repo", &mut pulls).await;
+    assert!(pulls.iter().all(|p| p.diff.is_none()));
+}
+
+fn make_repo(id: i64, full_name: &str) -> structs::Repository {
+    structs::Repository {
+        id,
+        name: full_name.rsplit('/').next().unwrap().to_string(),
+        full_name: full_name.to_string(),
+        fork: None,
+        owner: None,
+        default_branch: None,
+    }
+}
+
+fn make_installation(id: i64, repositories: Vec<structs::Repository>) -> structs::Installation {
+    structs::Installation {
+        id,
+        account: structs::Actor {
+            id: 1,
+            login: "ppy".to_string(),
+        },
+        app_id: 123,
+        repositories,
+        suspended_at: None,
+    }
+}
+
+#[test]
+fn fork_pull_diff_url() {
+    let mut pull = crate::test::make_pull(1, &["wiki/Article/en.md"]);
+    pull.head = crate::test::make_ref("master", "contributor/osu-wiki");
+    assert!(pull.is_from_fork());
+    assert_eq!(pull.repo_name(), Some("test/repo"));
+    assert_eq!(pull.head_repo_name(), Some("contributor/osu-wiki"));
+    assert_eq!(
+        GitHub::default().diff_url(pull.repo_name().unwrap(), pull.number),
+        "https://github.com/test/repo/pull/1.diff"
+    );
+
+    pull.head.repo = None;
+    assert!(pull.is_from_fork());
+    assert_eq!(pull.head_repo_name(), None);
+
+    let same_repo = crate::test::make_pull(2, &["wiki/Article/en.md"]);
+    assert!(!same_repo.is_from_fork());
+}
+
+#[test]
+fn installation_repositories_event() {
+    let client = Client::new("123".to_string(), "key".to_string());
+    client.update_cached_installation(make_installation(
+        1,
+        vec![make_repo(1, "ppy/osu-wiki"), make_repo(2, "ppy/osu-web")],
+    ));
+
+    let evt = structs::InstallationRepositoriesEvent {
+        action: "added".to_string(),
+        installation: make_installation(1, Vec::new()),
+        sender: structs::Actor {
+            id: 1,
+            login: "ppy".to_string(),
+        },
+        repositories_added: vec![make_repo(3, "ppy/osu"), make_repo(1, "ppy/osu-wiki")],
+        repositories_removed: vec![make_repo(2, "ppy/osu-web")],
+    };
+    let updated = client.apply_installation_repositories_event(&evt);
+
+    let names: Vec<_> = updated
+        .repositories
+        .iter()
+        .map(|r| r.full_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["ppy/osu-wiki", "ppy/osu"]);
+    assert_eq!(
+        client.cached_installations()[0].repositories.len(),
+        updated.repositories.len()
+    );
+}
+
+#[tokio::test]
+async fn repo_installation_index() {
+    let client = Client::new("123".to_string(), "key".to_string());
+    let installation = make_installation(1, vec![make_repo(1, "ppy/osu-wiki")]);
+    client.update_cached_installation(installation.clone());
+    client.update_cached_installation(make_installation(2, vec![make_repo(2, "ppy/osu-web")]));
+    assert_eq!(client.installation_for_repo("ppy/osu-wiki"), Some(1));
+    assert_eq!(client.installation_for_repo("ppy/osu-web"), Some(2));
+    assert_eq!(client.installation_for_repo("ppy/osu"), None);
+
+    let evt = structs::InstallationRepositoriesEvent {
+        action: "added".to_string(),
+        installation: make_installation(1, Vec::new()),
+        sender: structs::Actor {
+            id: 1,
+            login: "ppy".to_string(),
+        },
+        repositories_added: vec![make_repo(3, "ppy/osu")],
+        repositories_removed: vec![make_repo(1, "ppy/osu-wiki")],
+    };
+    client.apply_installation_repositories_event(&evt);
+    assert_eq!(client.installation_for_repo("ppy/osu"), Some(1));
+    assert_eq!(client.installation_for_repo("ppy/osu-wiki"), None);
+
+    client.remove_installation(&installation).await;
+    assert_eq!(client.installation_for_repo("ppy/osu"), None);
+    assert_eq!(client.installation_for_repo("ppy/osu-web"), Some(2));
+
+    // Installations inserted directly are picked up once the index misses.
+    client
+        .installations
+        .lock()
+        .unwrap()
+        .insert(3, make_installation(3, vec![make_repo(4, "ppy/osu-api")]));
+    assert_eq!(client.installation_for_repo("ppy/osu-api"), Some(3));
+}
+
+#[tokio::test]
+async fn personal_access_token() {
+    let client = Client::with_token("github_pat_123".to_string());
+    assert_eq!(
+        client.pick_token("ppy/osu-wiki").await.unwrap(),
+        "github_pat_123"
+    );
+    assert_eq!(
+        client.get_installation_token(1).await.unwrap(),
+        "github_pat_123"
+    );
+    assert!(client.installations().await.is_err());
+    assert!(client
+        .discover_installations()
+        .await
+        .unwrap()
+        .added
+        .is_empty());
+}
+
+fn make_file(
+    filename: &str,
+    status: &str,
+    previous_filename: Option<&str>,
+    patch: Option<&str>,
+) -> structs::PullRequestFile {
+    structs::PullRequestFile {
+        filename: filename.to_string(),
+        status: status.to_string(),
+        previous_filename: previous_filename.map(|s| s.to_string()),
+        patch: patch.map(|s| s.to_string()),
+    }
+}
+
+#[test]
+fn files_api_diff() {
+    let files = vec![
+        make_file(
+            "wiki/Article/en.md",
+            "modified",
+            None,
+            Some("@@ -1,1 +1,1 @@\n-old\n+new"),
+        ),
+        make_file(
+            "wiki/New/en.md",
+            "added",
+            None,
+            Some("@@ -0,0 +1,1 @@\n+text\n"),
+        ),
+        make_file("wiki/Old/en.md", "removed", None, None),
+        make_file(
+            "wiki/Moved/en.md",
+            "renamed",
+            Some("wiki/Original/en.md"),
+            None,
+        ),
+        make_file("wiki/img/image.png", "added", None, None),
+    ];
+    let diff = unidiff::PatchSet::from_str(&files_to_diff(&files)).unwrap();
+
+    let paths: Vec<_> = diff
+        .files()
+        .iter()
+        .map(|f| (f.source_file.as_str(), f.target_file.as_str()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("a/wiki/Article/en.md", "b/wiki/Article/en.md"),
+            ("/dev/null", "b/wiki/New/en.md"),
+            ("a/wiki/Old/en.md", "/dev/null"),
+            ("a/wiki/Original/en.md", "b/wiki/Moved/en.md"),
+            ("/dev/null", "b/wiki/img/image.png"),
+        ]
+    );
+    assert_eq!(diff.files()[0].added(), 1);
+    assert_eq!(diff.files()[0].removed(), 1);
+    assert_eq!(diff.files()[1].added(), 1);
+}
+
+#[test]
+fn duplicate_pulls_across_pages() {
+    // The pull list shifted between requests, so #2 ended up on both pages.
+    let first_page = vec![
+        crate::test::make_pull(1, &[]),
+        crate::test::make_pull(2, &[]),
+    ];
+    let second_page = vec![
+        crate::test::make_pull(2, &[]),
+        crate::test::make_pull(3, &[]),
+    ];
+    let pulls = dedup_pulls(first_page.into_iter().chain(second_page).collect());
+    let numbers: Vec<_> = pulls.iter().map(|p| p.number).collect();
+    assert_eq!(numbers, vec![1, 2, 3]);
+}
+
+/// A canned HTTP response: status line (e.g. `200 OK`), extra header lines, and body.
+/// `{root}` in headers is replaced with the server root, for links to other pages.
+type MockResponse = (&'static str, Vec<String>, String);
+
+/// Accept one HTTP connection per response, reply with them in order, and return the raw requests that were received.
+fn serve(responses: Vec<MockResponse>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let root = format!("http://{}", listener.local_addr().unwrap());
+    let root_url = root.clone();
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (status, headers, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            request.push_str(&String::from_utf8(request_body).unwrap());
+            let headers: String = headers
+                .iter()
+                .map(|h| format!("{}\r\n", h.replace("{root}", &root_url)))
+                .collect();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\n{headers}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (root, handle)
+}
+
+/// Same as [`serve`], for a single response.
+fn serve_once(
+    status: &'static str,
+    body: &'static str,
+) -> (String, std::thread::JoinHandle<String>) {
+    let (root, handle) = serve(vec![(status, Vec::new(), body.to_string())]);
+    let handle = std::thread::spawn(move || handle.join().unwrap().remove(0));
+    (root, handle)
+}
+
+#[tokio::test]
+async fn add_reaction() {
+    let (root, server) = serve_once("201 Created", r#"{"id":1,"content":"eyes"}"#);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let reaction = client
+        .add_reaction("ppy/osu-wiki", 1, structs::ReactionContent::Eyes)
+        .await
+        .unwrap();
+    assert_eq!(reaction.content, structs::ReactionContent::Eyes);
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /repos/ppy/osu-wiki/issues/1/reactions HTTP/1.1\r\n"));
+    assert!(request
+        .to_lowercase()
+        .contains("accept: application/vnd.github.squirrel-girl-preview+json\r\n"));
+    assert!(request.ends_with("\r\n\r\n{\"content\":\"eyes\"}"));
+}
+
+#[tokio::test]
+async fn injected_http_client() {
+    let (root, server) = serve_once("200 OK", r#"{"id":1,"content":"rocket"}"#);
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("X-Proxy-Auth", "secret".try_into().unwrap());
+    let http_client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let mut client = Client::with_http_client("123".to_string(), "key".to_string(), http_client)
+        .with_base_urls(&root, &root);
+    client.personal_token = Some("pat".to_string());
+
+    client
+        .add_reaction("ppy/osu-wiki", 1, structs::ReactionContent::Rocket)
+        .await
+        .unwrap();
+    let request = server.join().unwrap().to_lowercase();
+    assert!(request.contains("x-proxy-auth: secret\r\n"));
+}
+
+#[tokio::test]
+async fn pulls_not_modified() {
+    let pulls = vec![
+        crate::test::make_pull(1, &[]),
+        crate::test::make_pull(2, &[]),
+    ];
+    let etag = r#"ETag: W/"abc""#.to_string();
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            vec![etag.clone()],
+            serde_json::to_string(&pulls).unwrap(),
+        ),
+        // An empty body would fail to parse, so the cached pulls have to be used.
+        ("304 Not Modified", vec![etag], String::new()),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let first: Vec<_> = client.pulls("ppy/osu-wiki").await.unwrap();
+    let second: Vec<_> = client.pulls("ppy/osu-wiki").await.unwrap();
+    let numbers =
+        |pulls: &[structs::PullRequest]| pulls.iter().map(|p| p.number).collect::<Vec<_>>();
+    assert_eq!(numbers(&first), vec![1, 2]);
+    assert_eq!(numbers(&second), vec![1, 2]);
+
+    let requests = server.join().unwrap();
+    assert!(!requests[0].to_lowercase().contains("if-none-match"));
+    assert!(requests[1]
+        .to_lowercase()
+        .contains("if-none-match: w/\"abc\"\r\n"));
+}
+
+#[tokio::test]
+async fn pulls_stream_not_cached() {
+    let etag = r#"ETag: W/"abc""#.to_string();
+    let body = serde_json::to_string(&[crate::test::make_pull(1, &[])]).unwrap();
+    let (root, server) = serve(vec![
+        ("200 OK", vec![etag.clone()], body.clone()),
+        ("200 OK", vec![etag], body),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    for _ in 0..2 {
+        let mut pulls = client.pulls_stream("ppy/osu-wiki");
+        assert_eq!(pulls.next().await.unwrap().unwrap().number, 1);
+        assert!(pulls.next().await.is_none());
+    }
+    assert!(client.pulls_cache.lock().unwrap().pages.is_empty());
+    let requests = server.join().unwrap();
+    assert!(!requests[1].to_lowercase().contains("if-none-match"));
+}
+
+#[test]
+fn pulls_cache_bounded() {
+    let page = |etag: &str| PullsPage {
+        etag: etag.to_string(),
+        pulls: Vec::new(),
+        next: None,
+    };
+    let mut cache = PullsCache::new(2);
+    cache.insert("a".to_string(), page("1"));
+    cache.insert("b".to_string(), page("2"));
+    // Using a page keeps it around longer than ones which weren't used since.
+    assert_eq!(cache.get("a").unwrap().etag, "1");
+    cache.insert("c".to_string(), page("3"));
+    assert!(cache.get("b").is_none());
+    assert_eq!(cache.get("a").unwrap().etag, "1");
+    assert_eq!(cache.get("c").unwrap().etag, "3");
+
+    cache.insert("a".to_string(), page("4"));
+    assert_eq!(cache.get("a").unwrap().etag, "4");
+    assert_eq!(cache.pages.len(), 2);
+    assert_eq!(cache.order.len(), 2);
+}
+
+#[tokio::test]
+async fn malformed_private_key() {
+    let client = Client::new("123".to_string(), "not a key".to_string());
+    let err = client.get_jwt_token().await.unwrap_err();
+    assert!(err.to_string().contains("private key"));
+    assert!(client.app().await.is_err());
+    assert!(client.get_installation_token(1).await.is_err());
+}
+
+#[tokio::test]
+async fn create_check_run() {
+    let (root, server) = serve_once(
+        "201 Created",
+        r#"{"id":1,"head_sha":"abc","conclusion":"failure","html_url":null}"#,
+    );
+    let client = Client::with_token("token".to_string()).with_base_urls(&root, &root);
+
+    let check_run = client
+        .create_check_run(
+            "ppy/osu-wiki",
+            "abc",
+            structs::CheckConclusion::Failure,
+            "- #1".to_string(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        check_run.conclusion,
+        Some(structs::CheckConclusion::Failure)
+    );
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /repos/ppy/osu-wiki/check-runs HTTP/1.1\r\n"));
+    let body: serde_json::Value =
+        serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "name": "observatory",
+            "head_sha": "abc",
+            "status": "completed",
+            "conclusion": "failure",
+            "output": {"title": "Conflicts found", "summary": "- #1"},
+        })
+    );
+}
+
+/// Cache a token so that requests needing it don't try to generate or fetch one.
+async fn cache_token(client: &Client, ttype: TokenType) {
+    client
+        .tokens
+        .put(Token {
+            t: "token".to_string(),
+            ttype,
+            created_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now() + chrono::Duration::minutes(10),
+            repositories: None,
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn installations_pagination() {
+    let repos = |id, name| {
+        serde_json::to_string(&structs::InstallationRepositories {
+            total_count: 1,
+            repositories: vec![make_repo(id, name)],
+        })
+        .unwrap()
+    };
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            vec![r#"Link: <{root}/app/installations?per_page=100&page=2>; rel="next""#.to_string()],
+            serde_json::to_string(&[make_installation(1, Vec::new())]).unwrap(),
+        ),
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&[make_installation(2, Vec::new())]).unwrap(),
+        ),
+        ("200 OK", Vec::new(), repos(1, "ppy/osu-wiki")),
+        ("200 OK", Vec::new(), repos(2, "ppy/osu-web")),
+    ]);
+    let mut client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    // Repositories of both installations are listed at the same URL, so they have to be requested in order.
+    client.discovery_concurrency = 1;
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(1)).await;
+    cache_token(&client, TokenType::Installation(2)).await;
+
+    let installations = client.discover_installations().await.unwrap().added;
+    assert_eq!(installations.len(), 2);
+    assert_eq!(client.installation_for_repo("ppy/osu-wiki"), Some(1));
+    assert_eq!(client.installation_for_repo("ppy/osu-web"), Some(2));
+
+    let requests = server.join().unwrap();
+    assert!(requests[1].starts_with("GET /app/installations?per_page=100&page=2 "));
+}
+
+#[tokio::test]
+async fn discovery_failures_skipped() {
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&[
+                make_installation(1, Vec::new()),
+                make_installation(2, Vec::new()),
+            ])
+            .unwrap(),
+        ),
+        // The token of the first installation can't be created.
+        ("404 Not Found", Vec::new(), "{}".to_string()),
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&structs::InstallationRepositories {
+                total_count: 1,
+                repositories: vec![make_repo(2, "ppy/osu-web")],
+            })
+            .unwrap(),
+        ),
+    ]);
+    let mut client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    client.discovery_concurrency = 1;
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(2)).await;
+
+    let discovery = client.discover_installations().await.unwrap();
+    assert_eq!(discovery.added.len(), 1);
+    assert_eq!(discovery.added[0].id, 2);
+    assert_eq!(discovery.failed.len(), 1);
+    assert_eq!(discovery.failed[0].0.id, 1);
+    assert_eq!(client.installation_for_repo("ppy/osu-web"), Some(2));
+    assert_eq!(server.join().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn refresh_installation() {
+    let (root, server) = serve_once(
+        "200 OK",
+        r#"{"total_count": 1, "repositories": [{"id": 1, "name": "osu-wiki-renamed", "full_name": "ppy/osu-wiki-renamed"}]}"#,
+    );
+    let client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    cache_token(&client, TokenType::Installation(1)).await;
+    client.update_cached_installation(make_installation(1, vec![make_repo(1, "ppy/osu-wiki")]));
+    client.update_cached_installation(make_installation(2, vec![make_repo(2, "ppy/osu-web")]));
+
+    let refreshed = client.refresh_installation(1).await.unwrap();
+    assert_eq!(refreshed.repositories[0].full_name, "ppy/osu-wiki-renamed");
+    assert_eq!(
+        client.installation_for_repo("ppy/osu-wiki-renamed"),
+        Some(1)
+    );
+    assert_eq!(client.installation_for_repo("ppy/osu-wiki"), None);
+    assert_eq!(client.installation_for_repo("ppy/osu-web"), Some(2));
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with("GET /installation/repositories "));
+
+    assert!(client.refresh_installation(3).await.is_err());
+}
+
+#[tokio::test]
+async fn discovery_timeout() {
+    use std::io::{BufRead, BufReader, Write};
+
+    // Lists one installation, and then never answers the request for its repositories.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let root = format!("http://{}", listener.local_addr().unwrap());
+    let installations = serde_json::to_string(&[make_installation(1, Vec::new())]).unwrap();
+    let _server = std::thread::spawn(move || {
+        let mut streams = Vec::new();
+        for response in [Some(installations), None] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            if let Some(body) = response {
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+            streams.push(stream);
+        }
+        std::thread::sleep(Duration::from_secs(5));
+    });
+    let mut client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    client.discovery_timeout = Duration::from_millis(200);
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(1)).await;
+
+    let discovery = client.discover_installations().await.unwrap();
+    assert!(discovery.added.is_empty());
+    assert_eq!(discovery.failed.len(), 1);
+    assert!(discovery.failed[0].1.starts_with("Timed out"));
+    assert_eq!(client.installation_for_repo("ppy/osu-wiki"), None);
+}
+
+#[tokio::test]
+async fn discovery_timeout_during_backoff() {
+    // The request for repositories of the installation is to be retried in a minute.
+    let (root, _server) = serve(vec![
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&[make_installation(1, Vec::new())]).unwrap(),
+        ),
+        (
+            "503 Service Unavailable",
+            vec!["Retry-After: 60".to_string()],
+            "{}".to_string(),
+        ),
+    ]);
+    let mut client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    client.discovery_timeout = Duration::from_millis(200);
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(1)).await;
+
+    let started = std::time::Instant::now();
+    let discovery = client.discover_installations().await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(discovery.failed.len(), 1);
+    assert!(discovery.failed[0].1.starts_with("Timed out"));
+}
+
+#[tokio::test]
+async fn discovery_without_installations_list() {
+    let (root, _server) = serve_once("404 Not Found", "{}");
+    let client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    cache_token(&client, TokenType::JWT).await;
+
+    // Failing to list installations isn't the same as having none.
+    assert!(client.discover_installations().await.is_err());
+}
+
+#[test]
+fn client_builder() {
+    let client = Client::builder()
+        .app_auth("123".to_string(), test::APP_KEY.to_string())
+        .api_root("https://github.example.com/api/v3/")
+        .web_root("https://github.example.com")
+        .max_retries(4)
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+    assert_eq!(client.app_id, "123");
+    assert_eq!(client.max_attempts, 5);
+    assert_eq!(
+        client.urls().pull("ppy/osu-wiki", 1),
+        "https://github.example.com/api/v3/repos/ppy/osu-wiki/pulls/1"
+    );
+    assert_eq!(
+        client.urls().pull_url("ppy/osu-wiki", 1),
+        "https://github.example.com/ppy/osu-wiki/pull/1"
+    );
+
+    let client = Client::builder()
+        .app_auth("123".to_string(), test::APP_KEY.to_string())
+        .token_auth("pat".to_string())
+        .build()
+        .unwrap();
+    assert_eq!(client.personal_token.as_deref(), Some("pat"));
+    assert_eq!(client.max_attempts, DEFAULT_MAX_ATTEMPTS);
+    assert_eq!(
+        client.urls().pull_url("ppy/osu-wiki", 1),
+        "https://github.com/ppy/osu-wiki/pull/1"
+    );
+}
+
+#[test]
+fn client_builder_errors() {
+    assert!(Client::builder().build().is_err());
+    assert!(Client::builder()
+        .token_auth("pat".to_string())
+        .http_client(reqwest::Client::new())
+        .timeout(Duration::from_secs(5))
+        .build()
+        .is_err());
+}
+
+#[test]
+fn app_credentials_validated() {
+    assert!(validate_app_credentials("123", test::APP_KEY).is_ok());
+    for app_id in ["", "0", "-1", "app"] {
+        assert!(validate_app_credentials(app_id, test::APP_KEY).is_err());
+    }
+    let err = validate_app_credentials("123", "not a key").unwrap_err();
+    assert!(err.to_string().contains("private key"));
+
+    assert!(Client::try_new("123".to_string(), test::APP_KEY.to_string()).is_ok());
+    assert!(Client::try_new("123".to_string(), "not a key".to_string()).is_err());
+    assert!(Client::builder()
+        .app_auth("app".to_string(), test::APP_KEY.to_string())
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn unparseable_diff() {
+    let (root, _server) = serve_once("200 OK", "@@ -1,1 +1,1 @@\n-a\n+b");
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let err = client.read_pull_diff("ppy/osu-wiki", 1).await.unwrap_err();
+    match err {
+        ObservatoryError::DiffParse {
+            full_repo_name,
+            pull_number,
+            reason,
+        } => {
+            assert_eq!(full_repo_name, "ppy/osu-wiki");
+            assert_eq!(pull_number, 1);
+            assert_eq!(
+                reason,
+                unidiff::PatchSet::from_str("@@ -1,1 +1,1 @@\n-a\n+b")
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn empty_diff() {
+    let (root, _server) = serve_once("200 OK", "");
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let mut pull = crate::test::make_pull(1, &["wiki/Article/en.md"]);
+    assert!(pull.has_loadable_diff());
+    pull.diff = Some(client.read_pull_diff("ppy/osu-wiki", 1).await.unwrap());
+    assert!(!pull.has_loadable_diff());
+    pull.diff = None;
+    assert!(!pull.has_loadable_diff());
+}
+
+const SIMPLE_DIFF: &str = "diff --git a/wiki/Article/en.md b/wiki/Article/en.md
+index 5483f282a0a..2c8c1482b97 100644
+--- a/wiki/Article/en.md
++++ b/wiki/Article/en.md
+@@ -1,1 +1,1 @@
+-a
++b
+";
+
+#[test]
+fn streaming_diff_parser() {
+    let text = format!(
+        "{SIMPLE_DIFF}{}{}",
+        "diff --git a/wiki/Article/fr.md b/wiki/Article/fr.md
+index 5483f282a0a..2c8c1482b97 100644
+--- a/wiki/Article/fr.md
++++ b/wiki/Article/fr.md
+@@ -1,2 +1,2 @@
+ Première ligne
+-a
++b
+",
+        "diff --git a/wiki/img/image.png b/wiki/img/image.png
+new file mode 100644
+index 0000000000..2c8c1482b97
+Binary files /dev/null and b/wiki/img/image.png differ
+diff --git a/wiki/New/en.md b/wiki/New/en.md
+new file mode 100644
+index 0000000000..2c8c1482b97
+--- /dev/null
++++ b/wiki/New/en.md
+@@ -0,0 +1,1 @@
++text"
+    );
+    // Binary files are kept without hunks, everything else is the same as when parsing the whole text at once.
+    let expected = parse_diff(&text).unwrap();
+    assert_eq!(expected.len(), 4);
+    assert_eq!(
+        unidiff::PatchSet::from_str(&text).unwrap().to_string(),
+        [0, 1, 3].map(|i| expected[i].to_string()).join("\n")
+    );
+    assert_eq!(expected[2].source_file, "/dev/null");
+    assert_eq!(expected[2].target_file, "b/wiki/img/image.png");
+    assert!(expected[2].hunks().is_empty());
+
+    // Chunks of any size, including ones which split multi-byte characters, give the same result.
+    for chunk_size in [1, 2, 7, 64, text.len()] {
+        let mut parser = StreamingDiffParser::new();
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            parser.feed(chunk).unwrap();
+        }
+        let diff = parser.finish().unwrap();
+        assert_eq!(diff.to_string(), expected.to_string(), "{}", chunk_size);
+    }
+
+    let mut parser = StreamingDiffParser::new();
+    parser.feed(b"@@ -1,1 +1,1 @@\n-a\n").unwrap();
+    parser.feed(b"+b").unwrap();
+    assert!(parser.finish().is_err());
+}
+
+#[tokio::test]
+async fn concurrent_requests_limited() {
+    let (root, server) = serve(vec![
+        ("200 OK", Vec::new(), SIMPLE_DIFF.to_string()),
+        ("200 OK", Vec::new(), SIMPLE_DIFF.to_string()),
+    ]);
+    let client = Client::with_token("pat".to_string())
+        .with_base_urls(&root, &root)
+        .with_max_concurrent_requests(1);
+    let clone = client.clone();
+    let req = || client.http_client.get(format!("{root}/file"));
+
+    // A streamed body holds the only slot until it's read, even for clones of the client.
+    let response = client.stream("test", req()).await.unwrap();
+    assert_eq!(clone.request_limit.available_permits(), 0);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), clone.request("test", req()))
+            .await
+            .is_err()
+    );
+    drop(response);
+    assert_eq!(clone.request_limit.available_permits(), 1);
+    clone.request("test", req()).await.unwrap();
+    assert_eq!(client.request_limit.available_permits(), 1);
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    let client = Client::with_token("pat".to_string()).with_max_concurrent_requests(0);
+    assert_eq!(client.request_limit.available_permits(), 1);
+    let client = Client::builder()
+        .token_auth("pat".to_string())
+        .max_concurrent_requests(3)
+        .build()
+        .unwrap();
+    assert_eq!(client.request_limit.available_permits(), 3);
+    assert_eq!(
+        Client::with_token("pat".to_string())
+            .request_limit
+            .available_permits(),
+        MAX_CONCURRENT_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn diff_still_generating() {
+    let (root, server) = serve(vec![
+        ("202 Accepted", Vec::new(), String::new()),
+        ("200 OK", Vec::new(), SIMPLE_DIFF.to_string()),
+    ]);
+    let mut client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+    client.diff_generation_delay = Duration::from_millis(10);
+
+    let diff = client.read_pull_diff("ppy/osu-wiki", 1).await.unwrap();
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].target_file, "b/wiki/Article/en.md");
+    assert_eq!(server.join().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn diff_never_generated() {
+    let (root, server) = serve(vec![
+        ("202 Accepted", Vec::new(), String::new());
+        DIFF_GENERATION_ATTEMPTS as usize
+    ]);
+    let mut client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+    client.diff_generation_delay = Duration::from_millis(10);
+
+    assert!(client.read_pull_diff("ppy/osu-wiki", 1).await.is_err());
+    assert_eq!(
+        server.join().unwrap().len(),
+        DIFF_GENERATION_ATTEMPTS as usize
+    );
+}
+
+#[tokio::test]
+async fn delete_comment() {
+    let (root, server) = serve_once("204 No Content", "");
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    client.delete_comment("ppy/osu-wiki", 123).await.unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with("DELETE /repos/ppy/osu-wiki/issues/comments/123 "));
+}
+
+#[tokio::test]
+async fn read_file() {
+    let (root, server) = serve_once("200 OK", "# Article\n");
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let contents = client
+        .read_file("ppy/osu-wiki", "wiki/Article/en.md", "master")
+        .await
+        .unwrap();
+    assert_eq!(contents, "# Article\n");
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /repos/ppy/osu-wiki/contents/wiki/Article/en.md?ref=master "));
+    assert!(request
+        .to_lowercase()
+        .contains("accept: application/vnd.github.raw\r\n"));
+}
+
+#[tokio::test]
+async fn client_status() {
+    let client = Client::new("123".to_string(), "key".to_string());
+    assert_eq!(
+        client.status().await,
+        ClientStatus {
+            installations: 0,
+            repositories: 0,
+            live_jwt_tokens: 0,
+            live_installation_tokens: 0,
+        }
+    );
+
+    client.update_cached_installation(make_installation(
+        1,
+        vec![make_repo(1, "ppy/osu-wiki"), make_repo(2, "ppy/osu-web")],
+    ));
+    client.update_cached_installation(make_installation(2, vec![make_repo(3, "ppy/osu")]));
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(1)).await;
+    client
+        .tokens
+        .put(Token {
+            t: "expired".to_string(),
+            ttype: TokenType::Installation(2),
+            created_at: chrono::Utc::now() - chrono::Duration::hours(2),
+            expires_at: chrono::Utc::now() - chrono::Duration::hours(1),
+            repositories: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        client.status().await,
+        ClientStatus {
+            installations: 2,
+            repositories: 3,
+            live_jwt_tokens: 1,
+            live_installation_tokens: 1,
+        }
+    );
+}
+
+#[tokio::test]
+async fn shared_token_store() {
+    let store: Arc<dyn TokenStore> = Arc::new(InMemoryTokenStore::default());
+    let first =
+        Client::new("123".to_string(), "not a key".to_string()).with_token_store(store.clone());
+    let second =
+        Client::new("123".to_string(), "not a key".to_string()).with_token_store(store.clone());
+
+    // Neither client can generate tokens with a malformed key, so they have to come from the store.
+    assert!(first.get_installation_token(1).await.is_err());
+    cache_token(&first, TokenType::Installation(1)).await;
+    assert_eq!(second.get_installation_token(1).await.unwrap(), "token");
+
+    first
+        .remove_installation(&make_installation(1, Vec::new()))
+        .await;
+    assert!(store
+        .get(&TokenType::Installation(1))
+        .await
+        .unwrap()
+        .is_none());
+    assert!(second.get_installation_token(1).await.is_err());
+}
+
+#[tokio::test]
+async fn token_repository_scope() {
+    let scoped = || {
+        (
+            "201 Created",
+            Vec::new(),
+            r#"{"token": "scoped", "expires_at": "2099-01-01T00:00:00Z", "permissions": {}, "repositories": [{"id": 1, "name": "osu-wiki", "full_name": "ppy/osu-wiki"}]}"#.to_string(),
+        )
+    };
+    let (root, server) = serve(vec![
+        scoped(),
+        scoped(),
+        (
+            "201 Created",
+            Vec::new(),
+            r#"{"token": "rescoped", "expires_at": "2099-01-01T00:00:00Z", "permissions": {}, "repositories": [{"id": 1, "name": "osu-wiki", "full_name": "ppy/osu-wiki"}, {"id": 2, "name": "osu-web", "full_name": "ppy/osu-web"}]}"#.to_string(),
+        ),
+    ]);
+    let client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    cache_token(&client, TokenType::JWT).await;
+    client.update_cached_installation(make_installation(
+        1,
+        vec![make_repo(1, "ppy/osu-wiki"), make_repo(2, "ppy/osu-web")],
+    ));
+
+    assert_eq!(client.pick_token("ppy/osu-wiki").await.unwrap(), "scoped");
+    let token = client
+        .tokens
+        .get(&TokenType::Installation(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(token.covers("ppy/osu-wiki"));
+    assert!(!token.covers("ppy/osu-web"));
+
+    // The installation has access to the repository, but the token doesn't, even after requesting a new one.
+    assert!(matches!(
+        ObservatoryError::from(client.pick_token("ppy/osu-web").await.unwrap_err()),
+        ObservatoryError::NoTokenForRepo(repo) if repo == "ppy/osu-web"
+    ));
+    assert_eq!(client.get_installation_token(1).await.unwrap(), "scoped");
+
+    // Once the repository is added to the installation, new tokens include it.
+    assert_eq!(client.pick_token("ppy/osu-web").await.unwrap(), "rescoped");
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|r| r.starts_with("POST /app/installations/1/access_tokens ")));
+}
+
+#[tokio::test]
+async fn request_context_in_errors() {
+    let (root, server) = serve(vec![(
+        "404 Not Found",
+        vec!["X-GitHub-Request-Id: ABCD:1234".to_string()],
+        r#"{"message": "Not Found"}"#.to_string(),
+    )]);
+    let client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    cache_token(&client, TokenType::Installation(7)).await;
+    client.update_cached_installation(make_installation(7, vec![make_repo(1, "ppy/osu-wiki")]));
+
+    let err = client.get_pull("ppy/osu-wiki", 1).await.unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("get_pull in ppy/osu-wiki (installation 7): HTTP 404"),
+        "{}",
+        message
+    );
+    assert!(message.contains("(request ID: ABCD:1234)"), "{}", message);
+    server.join().unwrap();
+
+    assert_eq!(RequestContext::from("app").to_string(), "app",);
+    assert_eq!(
+        RequestContext::installation("installation_token", 7).to_string(),
+        "installation_token (installation 7)"
+    );
+}
+
+#[tokio::test]
+async fn post_comment_response() {
+    let (root, server) = serve_once(
+        "201 Created",
+        r#"{"id": 1296, "node_id": "IC_kwDOA", "body": "Hello", "user": {"id": 1, "login": "observatory[bot]"}, "html_url": "https://github.com/ppy/osu-wiki/pull/3#issuecomment-1296", "created_at": "2023-01-01T00:00:00Z", "updated_at": "2023-01-01T00:00:00Z"}"#,
+    );
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let comment = client
+        .post_comment("ppy/osu-wiki", 3, "Hello".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(comment.id, 1296);
+    assert_eq!(comment.body, "Hello");
+    assert_eq!(comment.user.login, "observatory[bot]");
+    assert_eq!(
+        comment.html_url,
+        "https://github.com/ppy/osu-wiki/pull/3#issuecomment-1296"
+    );
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with("POST /repos/ppy/osu-wiki/issues/3/comments "));
+}
+
+#[tokio::test]
+async fn post_comments_batch() {
+    let created = || {
+        (
+            "201 Created",
+            Vec::new(),
+            r#"{"id": 1, "body": "", "user": {"id": 1, "login": "bot"}, "html_url": "https://github.com/ppy/osu-wiki/pull/1#issuecomment-1", "created_at": "2023-01-01T00:00:00Z", "updated_at": "2023-01-01T00:00:00Z"}"#.to_string(),
+        )
+    };
+    let (root, server) = serve(vec![
+        created(),
+        ("404 Not Found", Vec::new(), "{}".to_string()),
+        created(),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let items: Vec<_> = (1..=3)
+        .map(|i| ("ppy/osu-wiki".to_string(), i, format!("comment {i}")))
+        .collect();
+    let results = client.post_comments(items).await;
+    assert_eq!(results.len(), 3);
+    // Comments are posted concurrently, so any of them may get the error.
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+
+    let mut requests = server.join().unwrap();
+    requests.sort();
+    for (i, request) in requests.iter().enumerate() {
+        assert!(request.starts_with(&format!(
+            "POST /repos/ppy/osu-wiki/issues/{}/comments ",
+            i + 1
+        )));
+        assert!(request.ends_with(&format!(r#"{{"body":"comment {}"}}"#, i + 1)));
+    }
+}
+
+#[tokio::test]
+async fn post_comments_rate_limited() {
+    let rate_limited = || {
+        (
+            "403 Forbidden",
+            vec![
+                "x-ratelimit-remaining: 0".to_string(),
+                "x-ratelimit-reset: 1700000000".to_string(),
+            ],
+            "{}".to_string(),
+        )
+    };
+    let (root, server) = serve(vec![rate_limited(); COMMENT_CONCURRENCY]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let items: Vec<_> = (1..=4)
+        .map(|i| ("ppy/osu-wiki".to_string(), i, "comment".to_string()))
+        .collect();
+    let results = client.post_comments(items).await;
+    assert_eq!(results.len(), 4);
+    for result in results {
+        assert!(
+            matches!(result, Err(ObservatoryError::RateLimited { reset }) if reset.timestamp() == 1700000000)
+        );
+    }
+    // Comments after the first failure aren't even sent.
+    assert_eq!(server.join().unwrap().len(), COMMENT_CONCURRENCY);
+}
+
+#[tokio::test]
+async fn rate_limited_error() {
+    let (root, _server) = serve(vec![(
+        "403 Forbidden",
+        vec![
+            "x-ratelimit-remaining: 0".to_string(),
+            "x-ratelimit-reset: 1700000000".to_string(),
+        ],
+        "{}".to_string(),
+    )]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    match client.get_pull("ppy/osu-wiki", 1).await {
+        Err(ObservatoryError::RateLimited { reset }) => assert_eq!(reset.timestamp(), 1700000000),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn no_token_for_repo_error() {
+    let client = Client::new("123".to_string(), "key".to_string());
+    assert!(matches!(
+        client.get_pull("ppy/osu-wiki", 1).await,
+        Err(ObservatoryError::NoTokenForRepo(repo)) if repo == "ppy/osu-wiki"
+    ));
+}
+
+#[tokio::test]
+async fn pulls_updated_since() {
+    let now = chrono::Utc::now();
+    let updated = |number: i64, hours_ago: i64| {
+        let mut pull = crate::test::make_pull(number, &[]);
+        pull.updated_at = now - chrono::Duration::hours(hours_ago);
+        pull
+    };
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            vec![r#"Link: <{root}/repos/ppy/osu-wiki/pulls?page=2>; rel="next""#.to_string()],
+            serde_json::to_string(&[updated(3, 1), updated(1, 2)]).unwrap(),
+        ),
+        (
+            "200 OK",
+            vec![r#"Link: <{root}/repos/ppy/osu-wiki/pulls?page=3>; rel="next""#.to_string()],
+            serde_json::to_string(&[updated(2, 3), updated(4, 48)]).unwrap(),
+        ),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let pulls = client
+        .pulls_updated_since("ppy/osu-wiki", now - chrono::Duration::hours(24))
+        .await
+        .unwrap();
+    assert_eq!(
+        pulls.iter().map(|p| p.number).collect::<Vec<_>>(),
+        vec![3, 1, 2]
+    );
+
+    // The third page is never requested.
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].starts_with(
+        "GET /repos/ppy/osu-wiki/pulls?state=open&direction=desc&sort=updated&per_page=100 "
+    ));
+}
+
+#[tokio::test]
+async fn read_pull_patch() {
+    let patch = r#"From 2c8c1482b97 Mon Sep 17 00:00:00 2001
+From: BanchoBot <bancho@example.com>
+Date: Mon, 1 Jan 2024 00:00:00 +0000
+Subject: [PATCH] Update article
+
+---
+ wiki/Article/en.md | 2 +-
+ 1 file changed, 1 insertion(+), 1 deletion(-)
+
+diff --git a/wiki/Article/en.md b/wiki/Article/en.md
+index 5483f28..2c8c148 100644
+--- a/wiki/Article/en.md
++++ b/wiki/Article/en.md
+@@ -1,1 +1,1 @@
+-old
++new
+-- 
+2.39.0
+"#;
+    let (root, server) = serve_once("200 OK", patch);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let response = client.read_pull_patch("ppy/osu-wiki", 1).await.unwrap();
+    assert_eq!(response.text, patch);
+    assert_eq!(
+        response.diff.unwrap().files()[0].target_file,
+        "b/wiki/Article/en.md"
+    );
+    assert!(server
+        .join()
+        .unwrap()
+        .starts_with("GET /ppy/osu-wiki/pull/1.patch "));
+
+    let (root, _server) = serve_once("200 OK", "@@ -1,1 +1,1 @@\n-a\n+b");
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+    let response = client.read_pull_patch("ppy/osu-wiki", 1).await.unwrap();
+    assert!(response.diff.is_none());
+}
+
+#[derive(Debug, Default)]
+struct RecordingMetrics {
+    requests: Mutex<Vec<RequestMetrics>>,
+}
+
+impl Metrics for RecordingMetrics {
+    fn record(&self, request: &RequestMetrics) {
+        self.requests.lock().unwrap().push(request.clone());
+    }
+}
+
+#[tokio::test]
+async fn metrics_recorded() {
+    let (root, _server) = serve(vec![
+        ("502 Bad Gateway", Vec::new(), String::new()),
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&crate::test::make_pull(1, &[])).unwrap(),
+        ),
+        ("404 Not Found", Vec::new(), String::new()),
+    ]);
+    let metrics = Arc::new(RecordingMetrics::default());
+    let client = Client::with_token("pat".to_string())
+        .with_base_urls(&root, &root)
+        .with_metrics(metrics.clone());
+
+    client.get_pull("ppy/osu-wiki", 1).await.unwrap();
+    assert!(client.delete_comment("ppy/osu-wiki", 1).await.is_err());
+
+    let requests = metrics.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].operation, "get_pull");
+    assert_eq!(requests[0].status_class(), "2xx");
+    assert_eq!(requests[0].retries, 1);
+    assert_eq!(requests[1].operation, "delete_comment");
+    assert_eq!(requests[1].status, Some(404));
+    assert_eq!(requests[1].retries, 0);
+}
+
+#[tokio::test]
+async fn suspended_installations_skipped() {
+    let mut suspended = make_installation(1, Vec::new());
+    suspended.suspended_at = Some(chrono::Utc::now());
+    let (root, _server) = serve(vec![
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&[suspended, make_installation(2, Vec::new())]).unwrap(),
+        ),
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&structs::InstallationRepositories {
+                total_count: 1,
+                repositories: vec![make_repo(2, "ppy/osu-web")],
+            })
+            .unwrap(),
+        ),
+    ]);
+    let client = Client::new("123".to_string(), "key".to_string()).with_base_urls(&root, &root);
+    cache_token(&client, TokenType::JWT).await;
+    cache_token(&client, TokenType::Installation(1)).await;
+    cache_token(&client, TokenType::Installation(2)).await;
+    // The installation was known before it got suspended.
+    client.update_cached_installation(make_installation(1, vec![make_repo(1, "ppy/osu-wiki")]));
+    assert_eq!(client.pick_token("ppy/osu-wiki").await.unwrap(), "token");
+
+    let discovery = client.discover_installations().await.unwrap();
+    assert_eq!(discovery.added.len(), 1);
+    assert_eq!(discovery.added[0].id, 2);
+    assert_eq!(discovery.suspended.len(), 1);
+    assert_eq!(discovery.suspended[0].id, 1);
+    assert!(matches!(
+        ObservatoryError::from(client.pick_token("ppy/osu-wiki").await.unwrap_err()),
+        ObservatoryError::NoTokenForRepo(_)
+    ));
+    assert_eq!(client.pick_token("ppy/osu-web").await.unwrap(), "token");
+}
+
+#[tokio::test]
+async fn suspended_installation_token_unused() {
+    let client = Client::new("123".to_string(), "key".to_string());
+    cache_token(&client, TokenType::Installation(1)).await;
+    let mut installation = make_installation(1, vec![make_repo(1, "ppy/osu-wiki")]);
+    installation.suspended_at = Some(chrono::Utc::now());
+    client.update_cached_installation(installation);
+
+    assert!(client.pick_token("ppy/osu-wiki").await.is_err());
+}
+
+#[tokio::test]
+async fn pulls_stream() {
+    let page = |numbers: &[i64]| {
+        let pulls: Vec<_> = numbers
+            .iter()
+            .map(|n| crate::test::make_pull(*n, &[]))
+            .collect();
+        serde_json::to_string(&pulls).unwrap()
+    };
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            vec![r#"Link: <{root}/repos/ppy/osu-wiki/pulls?page=2>; rel="next""#.to_string()],
+            page(&[1, 2]),
+        ),
+        ("200 OK", Vec::new(), page(&[2, 3])),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let mut stream = client.pulls_stream("ppy/osu-wiki");
+    let mut numbers = Vec::new();
+    while let Some(pull) = stream.next().await {
+        numbers.push(pull.unwrap().number);
+    }
+    assert_eq!(numbers, vec![1, 2, 3]);
+    assert!(stream.next().await.is_none());
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    let client = Client::new("123".to_string(), "key".to_string());
+    let mut stream = client.pulls_stream("ppy/osu-wiki");
+    assert!(stream.next().await.unwrap().is_err());
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn pulls_with_state() {
+    for (state, query) in [
+        (structs::PullState::Open, "state=open"),
+        (structs::PullState::Closed, "state=closed"),
+        (structs::PullState::All, "state=all"),
+    ] {
+        assert_eq!(
+            serde_json::to_string(&state).unwrap(),
+            format!("\"{}\"", state.as_str())
+        );
+        let (root, server) = serve(vec![(
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&[crate::test::make_pull(1, &[])]).unwrap(),
+        )]);
+        let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+        let pulls = client
+            .pulls_with_state("ppy/osu-wiki", state)
+            .await
+            .unwrap();
+        assert_eq!(pulls.len(), 1);
+        let requests = server.join().unwrap();
+        let request_line = requests[0].lines().next().unwrap();
+        assert!(request_line.contains(query), "{}", request_line);
+    }
+    assert_eq!(structs::PullState::default(), structs::PullState::Open);
+}
+
+fn graphql_pull(number: i32, files: &[&str], more_files: bool) -> serde_json::Value {
+    let repo =
+        serde_json::json!({"databaseId": 1, "name": "osu-wiki", "nameWithOwner": "ppy/osu-wiki"});
+    serde_json::json!({
+        "databaseId": 100 + number,
+        "number": number,
+        "state": "OPEN",
+        "title": "Update article",
+        "url": format!("https://github.com/ppy/osu-wiki/pull/{number}"),
+        "author": {"login": "someone", "databaseId": 5},
+        "createdAt": format!("2023-01-0{number}T00:00:00Z"),
+        "updatedAt": "2023-01-10T00:00:00Z",
+        "isDraft": false,
+        "mergedAt": null,
+        "baseRefName": "master",
+        "baseRefOid": "aaa",
+        "headRefName": "update",
+        "headRefOid": "bbb",
+        "baseRepository": repo,
+        "headRepository": repo,
+        "labels": {"nodes": [{"name": "translation", "color": "ffffff"}]},
+        "files": {
+            "pageInfo": {"hasNextPage": more_files, "endCursor": "x"},
+            "nodes": files.iter().map(|path| serde_json::json!({"path": path, "changeType": "MODIFIED"})).collect::<Vec<_>>(),
+        },
+    })
+}
+
+#[tokio::test]
+async fn pulls_with_files_graphql() {
+    let page = |pulls: Vec<serde_json::Value>, next: Option<&str>| {
+        serde_json::json!({"data": {"repository": {"pullRequests": {
+            "pageInfo": {"hasNextPage": next.is_some(), "endCursor": next},
+            "nodes": pulls,
+        }}}})
+        .to_string()
+    };
+    let (root, server) = serve(vec![
+        (
+            "200 OK",
+            Vec::new(),
+            page(
+                vec![graphql_pull(1, &["wiki/Article/en.md"], false)],
+                Some("cursor1"),
+            ),
+        ),
+        (
+            "200 OK",
+            Vec::new(),
+            page(vec![graphql_pull(2, &["wiki/Other/en.md"], true)], None),
+        ),
+        ("200 OK", Vec::new(), SIMPLE_DIFF.to_string()),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let pulls = client
+        .pulls_with_files_graphql("ppy/osu-wiki")
+        .await
+        .unwrap();
+    assert_eq!(
+        pulls.iter().map(|p| p.number).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(pulls[0].id, 101);
+    assert!(pulls[0].is_open());
+    assert_eq!(pulls[0].repo_name(), Some("ppy/osu-wiki"));
+    assert!(pulls[0].has_label("translation"));
+    let diff = pulls[0].diff.as_ref().unwrap();
+    assert_eq!(diff.len(), 1);
+    assert_eq!(diff[0].target_file, "b/wiki/Article/en.md");
+    assert!(diff[0].hunks().is_empty());
+    // Too many files for GraphQL: the diff is downloaded instead.
+    assert_eq!(
+        pulls[1].diff.as_ref().unwrap()[0].target_file,
+        "b/wiki/Article/en.md"
+    );
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].starts_with("POST /graphql "));
+    assert!(requests[0].contains(r#""owner":"ppy""#));
+    assert!(requests[0].contains(r#""cursor":null"#));
+    assert!(requests[1].contains(r#""cursor":"cursor1""#));
+    assert!(requests[2].starts_with("GET /ppy/osu-wiki/pull/2.diff "));
+}
+
+#[tokio::test]
+async fn graphql_errors() {
+    let (root, server) = serve_once(
+        "200 OK",
+        r#"{"data": null, "errors": [{"message": "Could not resolve to a Repository"}]}"#,
+    );
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let err = client
+        .graphql::<serde_json::Value>(
+            "ppy/osu-wiki",
+            "query { viewer { login } }",
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Could not resolve to a Repository"));
+    server.join().unwrap();
+}
+
+#[tokio::test]
+async fn scan_repo() {
+    let pulls: Vec<_> = [1, 2]
+        .iter()
+        .map(|n| crate::test::make_pull(*n, &[]))
+        .collect();
+    let diff = "diff --git a/wiki/Article/en.md b/wiki/Article/en.md
+index 5483f282a0a..2c8c1482b97 100644
+--- a/wiki/Article/en.md
++++ b/wiki/Article/en.md
+@@ -1,1 +1,1 @@
+-old
++new
+";
+    let (root, server) = serve(vec![
+        ("200 OK", Vec::new(), serde_json::to_string(&pulls).unwrap()),
+        ("200 OK", Vec::new(), diff.to_string()),
+        ("200 OK", Vec::new(), diff.to_string()),
+    ]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+
+    let found = client.scan_repo("ppy/osu-wiki").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].trigger, found[0].original), (2, 1));
+    assert_eq!(found[0].file_set, vec!["wiki/Article/en.md"]);
+    assert_eq!(server.join().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn muted_repo() {
+    let (root, server) = serve(vec![(
+        "200 OK",
+        Vec::new(),
+        serde_json::to_string(&[crate::test::make_pull(1, &[])]).unwrap(),
+    )]);
+    let client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+    client.mute_repo("ppy/osu-wiki");
+    assert!(client.clone().is_repo_muted("ppy/osu-wiki"));
+    assert!(!client.is_repo_muted("ppy/osu-web"));
+
+    assert!(client.pulls("ppy/osu-wiki").await.unwrap().is_empty());
+    assert!(client.scan_repo("ppy/osu-wiki").await.unwrap().is_empty());
+    assert!(client
+        .post_comment("ppy/osu-wiki", 1, "Hello".to_string())
+        .await
+        .unwrap()
+        .is_none());
+    client
+        .update_comment("ppy/osu-wiki", 1, "Hello".to_string())
+        .await
+        .unwrap();
+    client.delete_comment("ppy/osu-wiki", 1).await.unwrap();
+
+    // Nothing was sent while the repository was muted.
+    client.unmute_repo("ppy/osu-wiki");
+    assert_eq!(client.pulls("ppy/osu-wiki").await.unwrap().len(), 1);
+    assert_eq!(server.join().unwrap().len(), 1);
+}
+
+const SECONDARY_RATE_LIMIT_BODY: &str = r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#;
+
+#[tokio::test]
+async fn secondary_rate_limit_throttles() {
+    let (root, _server) = serve(vec![(
+        "403 Forbidden",
+        vec![
+            "x-ratelimit-remaining: 4000".to_string(),
+            "x-github-request-id: ABCD:1234".to_string(),
+        ],
+        SECONDARY_RATE_LIMIT_BODY.to_string(),
+    )]);
+    let client = Client::with_token("pat".to_string())
+        .with_base_urls(&root, &root)
+        .with_max_attempts(1);
+    assert!(!client.throttle_state().is_throttled());
+
+    assert!(client.get_pull("ppy/osu-wiki", 1).await.is_err());
+    let state = client.throttle_state();
+    assert!(state.is_throttled());
+    assert_eq!(state.hits, 1);
+    assert_eq!(state.backoff, SECONDARY_RATE_LIMIT_BACKOFF);
+    assert!(state.paused_until < state.cooldown_until);
+}
+
+#[tokio::test]
+async fn secondary_rate_limit_retried() {
+    let (root, server) = serve(vec![
+        (
+            "429 Too Many Requests",
+            Vec::new(),
+            SECONDARY_RATE_LIMIT_BODY.to_string(),
+        ),
+        (
+            "200 OK",
+            Vec::new(),
+            serde_json::to_string(&crate::test::make_pull(1, &[])).unwrap(),
+        ),
+    ]);
+    let mut client = Client::with_token("pat".to_string()).with_base_urls(&root, &root);
+    client.throttle = Arc::new(Throttle::with_backoff(Duration::from_millis(50)));
+
+    let started_at = std::time::Instant::now();
+    assert_eq!(client.get_pull("ppy/osu-wiki", 1).await.unwrap().number, 1);
+    assert!(started_at.elapsed() >= Duration::from_millis(50));
+    assert_eq!(server.join().unwrap().len(), 2);
+    assert_eq!(client.throttle_state().hits, 1);
+}
+
+#[test]
+fn secondary_rate_limit_backoff() {
+    let throttle = Throttle::with_backoff(Duration::from_secs(60));
+    assert_eq!(throttle.hit(None), Duration::from_secs(60));
+    assert_eq!(throttle.hit(None), Duration::from_secs(120));
+    assert_eq!(
+        throttle.hit(Some(Duration::from_secs(5))),
+        Duration::from_secs(5)
+    );
+    assert_eq!(throttle.state().hits, 3);
+    for _ in 0..10 {
+        throttle.hit(None);
+    }
+    assert_eq!(throttle.state().backoff, MAX_SECONDARY_RATE_LIMIT_BACKOFF);
+
+    let headers = HashMap::from([("x-ratelimit-remaining".to_string(), "0".to_string())]);
+    let forbidden = reqwest::StatusCode::FORBIDDEN;
+    assert!(is_secondary_rate_limit(
+        forbidden,
+        &HashMap::new(),
+        SECONDARY_RATE_LIMIT_BODY
+    ));
+    assert!(!is_secondary_rate_limit(
+        forbidden,
+        &headers,
+        SECONDARY_RATE_LIMIT_BODY
+    ));
+    assert!(!is_secondary_rate_limit(forbidden, &HashMap::new(), "{}"));
+}
+
+#[test]
+fn bot_login_matching() {
+    assert!(is_bot_login("observatory[bot]", "observatory"));
+    assert!(is_bot_login("observatory[bot]", "observatory[bot]"));
+    assert!(is_bot_login("Observatory[bot]", "observatory"));
+    assert!(!is_bot_login("observatory", "observatory[bot]"));
+    assert!(!is_bot_login("not-observatory[bot]", "observatory"));
+
+    let client = Client::with_token("pat".to_string());
+    assert_eq!(client.bot_login(), None);
+    let client = client.with_bot_login("observatory");
+    assert_eq!(client.bot_login().as_deref(), Some("observatory"));
+    let client = Client::builder()
+        .token_auth("pat".to_string())
+        .bot_login("observatory[bot]")
+        .build()
+        .unwrap();
+    assert_eq!(client.bot_login().as_deref(), Some("observatory[bot]"));
+}
@@ -3,24 +3,15 @@ use viz::{Request, RequestExt, StatusCode};
 
 use crate::{controller, github, structs};
 
-pub async fn pull_request_event(req: Request, body: String) -> viz::Result<()> {
+pub async fn pull_request_event(req: Request, evt: structs::PullRequestEvent) -> viz::Result<()> {
     let controller = req
         .state::<controller::Controller<github::Client>>()
         .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_error())?;
 
-    let evt: structs::PullRequestEvent = serde_json::from_str(&body).map_err(|e| {
-        log::error!(
-            "Failed to deserialize a pull request event coming from GitHub: {:?}. JSON: {:?}",
-            e,
-            body
-        );
-        StatusCode::INTERNAL_SERVER_ERROR.into_error()
-    })?;
-
     let pull_number = evt.pull_request.number;
     log::debug!("Pull #{}: received event \"{}\"", pull_number, evt.action);
     match evt.action.as_str() {
-        "synchronize" | "opened" | "reopened" => {
+        "synchronize" | "opened" | "reopened" | "ready_for_review" => {
             controller
                 .add_pull(&evt.repository.full_name, evt.pull_request, true)
                 .await
@@ -40,16 +31,11 @@ pub async fn pull_request_event(req: Request, body: String) -> viz::Result<()> {
     Ok(())
 }
 
-pub async fn installation_event(req: Request, body: String) -> viz::Result<()> {
+pub async fn installation_event(req: Request, evt: structs::InstallationEvent) -> viz::Result<()> {
     let controller = req
         .state::<controller::Controller<github::Client>>()
         .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_error())?;
 
-    let evt: structs::InstallationEvent = serde_json::from_str(&body).map_err(|e| {
-        log::error!("Failed to deserialize an installation request event coming from GitHub: {:?}. JSON: {:?}", e, body);
-        StatusCode::INTERNAL_SERVER_ERROR.into_error()
-    })?;
-
     let installation_id = evt.installation.id;
     log::debug!(
         "Installation #{}: received event \"{}\"",
@@ -75,42 +61,22 @@ pub async fn installation_event(req: Request, body: String) -> viz::Result<()> {
                 });
         }
         "deleted" => {
-            controller.remove_installation(evt.installation);
+            controller.remove_installation(evt.installation).await;
         }
         _ => {}
     }
     Ok(())
 }
 
-pub async fn installation_repositories_event(req: Request, body: String) -> viz::Result<()> {
+pub async fn installation_repositories_event(
+    req: Request,
+    evt: structs::InstallationRepositoriesEvent,
+) -> viz::Result<()> {
     let controller = req
         .state::<controller::Controller<github::Client>>()
         .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_error())?;
 
-    let evt: structs::InstallationRepositoriesEvent = serde_json::from_str(&body).map_err(|e| {
-        log::error!("Failed to deserialize an installation repositories event coming from GitHub: {:?}. JSON: {:?}", e, body);
-        StatusCode::INTERNAL_SERVER_ERROR.into_error()
-    })?;
-
-    // TODO: this should be more elegant
-    let mut cached_installations = controller.installations();
-    for inst in cached_installations.iter_mut() {
-        if inst.id == evt.installation.id {
-            let removed_repos: Vec<_> = evt.repositories_removed.iter().map(|r| r.id).collect();
-            let mut i = 0;
-            while i < inst.repositories.len() {
-                if removed_repos.contains(&inst.repositories[i].id) {
-                    inst.repositories.remove(i);
-                } else {
-                    i += 1;
-                }
-            }
-            inst.repositories
-                .append(&mut evt.repositories_added.clone());
-            controller.update_cached_installation(inst.clone());
-            break;
-        }
-    }
+    controller.update_installation_repositories(&evt);
     for repo in evt.repositories_added {
         if let Err(e) = controller.add_repository(&repo).await {
             log::error!(
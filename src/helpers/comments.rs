@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::helpers::conflicts::ConflictType;
 use crate::helpers::ToMarkdown;
+use crate::structs;
 
 /// Warn the author of a new pull request about someone else's unmerged work.
 pub const OVERLAP_TEMPLATE: &str = "Someone else has edited same files as you did. Please check their changes in case they conflict with yours:\n";
@@ -11,9 +12,118 @@ pub const OVERLAP_TEMPLATE: &str = "Someone else has edited same files as you di
 /// Warn the author of a translation request about new changes in the original article.
 pub const INCOMPLETE_TRANSLATION_TEMPLATE: &str = "Your translation may not include changes from these pull requests. Please update it after they are merged:\n";
 
+/// Remind the author of a pull which changes both an original article and its translations to keep them in sync.
+pub const MIXED_LANGUAGE_CHANGE_TEMPLATE: &str = "This pull request changes both original articles and their translations. Please make sure these translations match the final version of the originals:\n";
+
+/// Warn the author of a new pull request about someone else replacing the same images or other non-article files.
+pub const ASSET_CHANGE_TEMPLATE: &str = "Someone else has changed the same images or other files as you did. Only one version of each file can be kept, so please agree on which one:\n";
+
+/// Introductions of comments about each type of conflict in a single language, see [`localized_templates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Templates {
+    pub overlap: &'static str,
+    pub incomplete_translation: &'static str,
+    pub mixed_language_change: &'static str,
+    pub asset_change: &'static str,
+}
+
+impl Templates {
+    pub fn get(&self, conflict_type: &ConflictType) -> &'static str {
+        match conflict_type {
+            ConflictType::Overlap => self.overlap,
+            ConflictType::IncompleteTranslation => self.incomplete_translation,
+            ConflictType::MixedLanguageChange => self.mixed_language_change,
+            ConflictType::AssetChange => self.asset_change,
+        }
+    }
+}
+
+pub const ENGLISH_TEMPLATES: Templates = Templates {
+    overlap: OVERLAP_TEMPLATE,
+    incomplete_translation: INCOMPLETE_TRANSLATION_TEMPLATE,
+    mixed_language_change: MIXED_LANGUAGE_CHANGE_TEMPLATE,
+    asset_change: ASSET_CHANGE_TEMPLATE,
+};
+
+pub const FRENCH_TEMPLATES: Templates = Templates {
+    overlap: "Quelqu'un d'autre a modifié les mêmes fichiers que vous. Veuillez vérifier ses modifications au cas où elles entreraient en conflit avec les vôtres :\n",
+    incomplete_translation: "Votre traduction n'inclut peut-être pas les modifications de ces pull requests. Veuillez la mettre à jour une fois qu'elles auront été fusionnées :\n",
+    mixed_language_change: "Cette pull request modifie à la fois des articles originaux et leurs traductions. Veuillez vous assurer que ces traductions correspondent à la version finale des originaux :\n",
+    asset_change: "Quelqu'un d'autre a modifié les mêmes images ou autres fichiers que vous. Une seule version de chaque fichier peut être conservée, veuillez donc vous mettre d'accord sur laquelle garder :\n",
+};
+
+/// Comment templates by language code (as in article file names). New languages only need to be added here.
+pub const LOCALIZED_TEMPLATES: &[(&str, Templates)] =
+    &[("en", ENGLISH_TEMPLATES), ("fr", FRENCH_TEMPLATES)];
+
+/// Templates in the given language (case-insensitive), or English ones if there is no translation.
+pub fn localized_templates(language: &str) -> &'static Templates {
+    LOCALIZED_TEMPLATES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(language))
+        .map(|(_, templates)| templates)
+        .unwrap_or(&ENGLISH_TEMPLATES)
+}
+
 pub const HTML_COMMENT_START: &str = "<!--";
 pub const HTML_COMMENT_END: &str = "-->";
 
+/// Start of the hidden marker which identifies comments about a specific conflict, see [`TrackingMarker`].
+pub const MARKER_PREFIX: &str = "observatory:pull:";
+
+/// A small line for the end of comments which tells what posted them and where to report false positives
+/// (`feedback_url`, left out if empty), e.g. `<sub>observatory v0.2.4 · [feedback](https://...)</sub>`.
+pub fn footer(feedback_url: &str) -> String {
+    let name = format!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    if feedback_url.is_empty() {
+        format!("<sub>{name}</sub>")
+    } else {
+        format!("<sub>{name} · [feedback]({feedback_url})</sub>")
+    }
+}
+
+/// Prefix of commands which can be given to the bot in comments, e.g. `/observatory rescan`.
+pub const DEFAULT_COMMAND_PREFIX: &str = "/observatory";
+
+/// Extract a command addressed to the bot from a comment: for `/observatory rescan` and prefix `/observatory`,
+/// this returns `rescan`. The command must be on its own line, and only the first command in a comment is considered.
+pub fn extract_command<'a>(body: &'a str, prefix: &str) -> Option<&'a str> {
+    body.lines()
+        .filter_map(|line| line.trim().strip_prefix(prefix))
+        .find(|rest| rest.starts_with(char::is_whitespace))
+        .map(|rest| rest.trim())
+        .filter(|command| !command.is_empty())
+}
+
+/// Check if a comment made by the bot (normally one about a conflict) was addressed by the author of the pull:
+/// they submitted a review after the comment was last updated. A dismissed review doesn't count.
+pub fn is_addressed_by_review(
+    comment: &structs::IssueComment,
+    review: &structs::Review,
+    pull: &structs::PullRequest,
+) -> bool {
+    review.user.id == pull.user.id
+        && !review.state.eq_ignore_ascii_case("dismissed")
+        && review
+            .submitted_at
+            .is_some_and(|submitted_at| submitted_at > comment.updated_at)
+}
+
+/// Find a comment which makes posting `body` pointless: one with the same text, or one about the same conflict
+/// (i.e. with the same marker, see [`TrackingMarker`]).
+pub fn find_duplicate<'a>(
+    comments: &'a [structs::IssueComment],
+    body: &str,
+) -> Option<&'a structs::IssueComment> {
+    let marker = TrackingMarker::from_comment(body);
+    comments.iter().find(|c| {
+        c.body == body
+            || marker
+                .as_ref()
+                .is_some_and(|marker| TrackingMarker::all_from_comment(&c.body).contains(marker))
+    })
+}
+
 /// Structured header for comments made by the bot, designed to avoid tedious and error-prone parsing.
 #[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
 pub struct CommentHeader {
@@ -46,6 +156,15 @@ impl CommentHeader {
         }
         serde_yaml::from_str(&lines.join("\n")).ok()
     }
+
+    /// Marker for the comment about this conflict posted in pull `trigger`, see [`TrackingMarker`].
+    pub fn tracking_marker(&self, trigger: i32) -> TrackingMarker {
+        TrackingMarker {
+            trigger,
+            original: self.pull_number,
+            kind: self.conflict_type.clone(),
+        }
+    }
 }
 
 impl ToMarkdown for CommentHeader {
@@ -59,6 +178,63 @@ impl ToMarkdown for CommentHeader {
     }
 }
 
+/// Machine-readable reference to a conflict, rendered as a one-line hidden marker which follows the header
+/// in comments, e.g. `<!-- observatory:pull:1:Overlap:2 -->` for an overlap of pull #2 (where the comment is)
+/// with pull #1. Unlike the header, it can be found anywhere in a comment, which allows finding the comment about
+/// a conflict later to edit or dismiss it, including comments about several conflicts at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackingMarker {
+    pub trigger: i32,
+    pub original: i32,
+    pub kind: ConflictType,
+}
+
+impl TrackingMarker {
+    /// Attempt to extract the first marker from anywhere in a Markdown comment.
+    pub fn from_comment(body: &str) -> Option<Self> {
+        Self::all_from_comment(body).into_iter().next()
+    }
+
+    /// Extract every well-formed marker of a Markdown comment, in order.
+    pub fn all_from_comment(body: &str) -> Vec<Self> {
+        let start = format!("{} {}", HTML_COMMENT_START, MARKER_PREFIX);
+        body.split(start.as_str())
+            .skip(1)
+            .filter_map(|rest| Self::parse_fields(&rest[..rest.find(HTML_COMMENT_END)?]))
+            .collect()
+    }
+
+    fn parse_fields(fields: &str) -> Option<Self> {
+        let mut fields = fields.trim().split(':');
+        let original = fields.next()?.parse().ok()?;
+        let kind = serde_yaml::from_str(fields.next()?).ok()?;
+        let trigger = fields.next()?.parse().ok()?;
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            trigger,
+            original,
+            kind,
+        })
+    }
+}
+
+impl ToMarkdown for TrackingMarker {
+    fn to_markdown(&self) -> String {
+        format!(
+            "{} {}{}:{}:{} {}",
+            HTML_COMMENT_START,
+            MARKER_PREFIX,
+            self.original,
+            // Same representation as in the header, so that it's read back by `parse_fields`.
+            serde_yaml::to_string(&self.kind).unwrap().trim(),
+            self.trigger,
+            HTML_COMMENT_END
+        )
+    }
+}
+
 #[cfg(test)]
 #[path = "comments_test.rs"]
 pub(crate) mod tests;
@@ -13,6 +13,7 @@ pull_number: 12
 conflict_type: Overlap
 -->"#
     );
+    assert_eq!(CommentHeader::from_comment(&hdr.to_markdown()), Some(hdr));
 }
 
 #[test]
@@ -50,3 +51,270 @@ Some text here."#;
         })
     );
 }
+
+#[test]
+fn extract_command_ok() {
+    assert_eq!(
+        extract_command("/observatory rescan", DEFAULT_COMMAND_PREFIX),
+        Some("rescan")
+    );
+    assert_eq!(
+        extract_command(
+            "Thanks!\n\n  /observatory   rescan  \n",
+            DEFAULT_COMMAND_PREFIX
+        ),
+        Some("rescan")
+    );
+    assert_eq!(extract_command("!bot rescan", "!bot"), Some("rescan"));
+}
+
+#[test]
+fn extract_command_missing() {
+    assert_eq!(extract_command("rescan", DEFAULT_COMMAND_PREFIX), None);
+    assert_eq!(
+        extract_command("/observatory", DEFAULT_COMMAND_PREFIX),
+        None
+    );
+    assert_eq!(
+        extract_command("/observatoryrescan", DEFAULT_COMMAND_PREFIX),
+        None
+    );
+    assert_eq!(
+        extract_command("please run /observatory rescan", DEFAULT_COMMAND_PREFIX),
+        None
+    );
+}
+
+#[test]
+fn issue_comment_event() {
+    let payload = r#"{
+        "action": "created",
+        "comment": {
+            "id": 1,
+            "body": "/observatory rescan",
+            "user": {"id": 2, "login": "ppy"},
+            "created_at": "2023-01-01T00:00:00Z",
+            "updated_at": "2023-01-01T00:00:00Z"
+        },
+        "issue": {
+            "number": 3,
+            "title": "Update article",
+            "state": "open",
+            "user": {"id": 2, "login": "ppy"},
+            "pull_request": {
+                "url": "https://api.github.com/repos/ppy/osu-wiki/pulls/3",
+                "html_url": "https://github.com/ppy/osu-wiki/pull/3"
+            }
+        },
+        "repository": {"id": 4, "name": "osu-wiki", "full_name": "ppy/osu-wiki"},
+        "installation": {"id": 5},
+        "sender": {"id": 2, "login": "ppy"}
+    }"#;
+    let evt: crate::structs::IssueCommentEvent = serde_json::from_str(payload).unwrap();
+    assert!(evt.issue.is_pull_request());
+    assert_eq!(
+        extract_command(&evt.comment.body, DEFAULT_COMMAND_PREFIX),
+        Some("rescan")
+    );
+}
+
+#[test]
+fn pull_request_review_event() {
+    let payload = r#"{
+        "action": "submitted",
+        "review": {
+            "id": 1,
+            "user": {"id": 1, "login": "BanchoBot"},
+            "body": "Checked the other pull, no conflicts",
+            "state": "commented",
+            "submitted_at": "2023-01-02T00:00:00Z"
+        },
+        "pull_request": {
+            "id": 3,
+            "number": 3,
+            "state": "open",
+            "title": "Update article",
+            "user": {"id": 1, "login": "BanchoBot"},
+            "html_url": "https://github.com/ppy/osu-wiki/pull/3",
+            "created_at": "2023-01-01T00:00:00Z",
+            "updated_at": "2023-01-01T00:00:00Z",
+            "draft": false,
+            "base": {"ref": "master", "sha": "2c8c1482b97", "repo": null},
+            "head": {"ref": "branch", "sha": "5483f282a0a", "repo": null}
+        },
+        "repository": {"id": 4, "name": "osu-wiki", "full_name": "ppy/osu-wiki"},
+        "installation": {"id": 5},
+        "sender": {"id": 1, "login": "BanchoBot"}
+    }"#;
+    let evt: structs::PullRequestReviewEvent = serde_json::from_str(payload).unwrap();
+    let mut comment = structs::IssueComment {
+        id: 6,
+        body: "conflict".to_string(),
+        user: structs::Actor {
+            id: 7,
+            login: "test-app[bot]".to_string(),
+        },
+        created_at: "2023-01-01T12:00:00Z".parse().unwrap(),
+        updated_at: "2023-01-01T12:00:00Z".parse().unwrap(),
+    };
+    assert!(is_addressed_by_review(
+        &comment,
+        &evt.review,
+        &evt.pull_request
+    ));
+
+    // The comment was updated with new conflicts after the review.
+    comment.updated_at = "2023-01-03T00:00:00Z".parse().unwrap();
+    assert!(!is_addressed_by_review(
+        &comment,
+        &evt.review,
+        &evt.pull_request
+    ));
+
+    // Reviews by someone other than the author don't count.
+    comment.updated_at = comment.created_at;
+    let mut review = evt.review.clone();
+    review.user.id = 2;
+    assert!(!is_addressed_by_review(
+        &comment,
+        &review,
+        &evt.pull_request
+    ));
+}
+
+#[test]
+fn find_duplicate_comment() {
+    let header = CommentHeader {
+        pull_number: 12,
+        conflict_type: ConflictType::Overlap,
+    };
+    let comment_body = |header: &CommentHeader, text: &str| {
+        format!(
+            "{}\n{}\n{}",
+            header.to_markdown(),
+            header.tracking_marker(2).to_markdown(),
+            text
+        )
+    };
+    let body = comment_body(&header, "Files changed.");
+    let comment = |id: i64, body: &str| structs::IssueComment {
+        id,
+        body: body.to_string(),
+        user: structs::Actor {
+            id: 1,
+            login: "test-app[bot]".to_string(),
+        },
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    };
+
+    let unrelated = CommentHeader {
+        pull_number: 123,
+        conflict_type: ConflictType::Overlap,
+    };
+    let comments = vec![
+        comment(1, "Thanks!"),
+        comment(2, &comment_body(&unrelated, "Files changed.")),
+    ];
+    assert!(find_duplicate(&comments, &body).is_none());
+
+    // Same conflict, but the file list has changed since.
+    let comments = vec![comment(3, &comment_body(&header, "Other files."))];
+    assert_eq!(find_duplicate(&comments, &body).unwrap().id, 3);
+
+    let comments = vec![comment(4, "Thanks!")];
+    assert_eq!(find_duplicate(&comments, "Thanks!").unwrap().id, 4);
+}
+
+#[test]
+fn localized_templates_fallback() {
+    assert_eq!(localized_templates("fr"), &FRENCH_TEMPLATES);
+    assert_eq!(localized_templates("en"), &ENGLISH_TEMPLATES);
+    assert_eq!(localized_templates("zh-tw"), &ENGLISH_TEMPLATES);
+    for (_, templates) in LOCALIZED_TEMPLATES {
+        for conflict_type in [
+            ConflictType::Overlap,
+            ConflictType::IncompleteTranslation,
+            ConflictType::MixedLanguageChange,
+            ConflictType::AssetChange,
+        ] {
+            // The file list follows on the next line.
+            assert!(templates.get(&conflict_type).ends_with(":\n"));
+        }
+    }
+}
+
+#[test]
+fn tracking_marker_round_trip() {
+    let header = CommentHeader {
+        pull_number: 1,
+        conflict_type: ConflictType::IncompleteTranslation,
+    };
+    let marker = header.tracking_marker(2);
+    assert_eq!(
+        marker.to_markdown(),
+        "<!-- observatory:pull:1:IncompleteTranslation:2 -->"
+    );
+
+    let body = format!(
+        "{}\n{}\nSome text here.",
+        header.to_markdown(),
+        marker.to_markdown()
+    );
+    assert_eq!(TrackingMarker::from_comment(&body), Some(marker.clone()));
+
+    // Comments about several conflicts have a marker for each of them.
+    let other = CommentHeader {
+        pull_number: 3,
+        conflict_type: ConflictType::Overlap,
+    }
+    .tracking_marker(2);
+    let body = format!(
+        "Some text here.\n{}\n<!-- observatory:pull:x:Overlap:2 -->\n{}",
+        marker.to_markdown(),
+        other.to_markdown()
+    );
+    assert_eq!(TrackingMarker::all_from_comment(&body), vec![marker, other]);
+
+    for kind in [
+        ConflictType::Overlap,
+        ConflictType::IncompleteTranslation,
+        ConflictType::MixedLanguageChange,
+        ConflictType::AssetChange,
+    ] {
+        let marker = TrackingMarker {
+            trigger: 2,
+            original: 1,
+            kind,
+        };
+        assert_eq!(
+            TrackingMarker::from_comment(&marker.to_markdown()),
+            Some(marker)
+        );
+    }
+}
+
+#[test]
+fn tracking_marker_missing_or_malformed() {
+    assert_eq!(TrackingMarker::from_comment("test comment"), None);
+    assert_eq!(
+        TrackingMarker::from_comment("<!-- observatory:pull:1:Overlap -->"),
+        None
+    );
+    assert_eq!(
+        TrackingMarker::from_comment("<!-- observatory:pull:1:Overlap:x -->"),
+        None
+    );
+    assert_eq!(
+        TrackingMarker::from_comment("<!-- observatory:pull:1:Unknown:2 -->"),
+        None
+    );
+    assert_eq!(
+        TrackingMarker::from_comment("<!-- observatory:pull:1:Overlap:2:3 -->"),
+        None
+    );
+    assert_eq!(
+        TrackingMarker::from_comment("<!-- observatory:pull:1:Overlap:2"),
+        None
+    );
+}
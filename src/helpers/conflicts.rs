@@ -1,13 +1,13 @@
 /// `pulls` contains structures and helpers for detecting conflicts between two pull requests.
 use std::cmp::{PartialEq, PartialOrd};
 use std::collections::hash_map::{Entry, HashMap};
-use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
+use eyre::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::helpers::comments;
-use crate::helpers::ToMarkdown;
+use crate::helpers::{code_fence, escape_markdown, glob_match, ToMarkdown, ToPlainText};
 use crate::structs;
 
 /// Types of pull conflicts
@@ -20,20 +20,185 @@ pub enum ConflictType {
     /// A new pull request affects an article for which there's a translation open.
     /// Target = old pull (translation), reference = new pull (original).
     IncompleteTranslation,
+
+    /// A single pull request changes an original article together with its translation, which may fall behind
+    /// if the original is changed again during review. Target = reference = the pull itself.
+    /// See [`check_pull`].
+    MixedLanguageChange,
+
+    /// Two pull requests change the same non-article file, e.g. an image (see [`CompareConfig::asset_conflicts`]).
+    /// Target = new pull, reference = old pull.
+    AssetChange,
 }
 
 impl ToMarkdown for ConflictType {
     fn to_markdown(&self) -> String {
+        comments::ENGLISH_TEMPLATES.get(self).to_string()
+    }
+}
+
+impl ConflictType {
+    /// Same as [`ToMarkdown::to_markdown`], but in another language if there is a translation for it
+    /// (see [`comments::localized_templates`]).
+    pub fn to_markdown_localized(&self, language: &str) -> String {
+        comments::localized_templates(language)
+            .get(self)
+            .to_string()
+    }
+}
+
+impl ToPlainText for ConflictType {
+    fn to_plain_text(&self) -> String {
+        // Templates don't contain any markup, only the trailing line break.
+        self.to_markdown().trim_end().to_string()
+    }
+}
+
+/// How urgent a conflict is, from least to most, for deciding which conflicts are worth a comment.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// A heads-up, e.g. the original article changed while a translation is open.
+    Info,
+    /// Concurrent edits to the same files.
+    Warning,
+    /// Concurrent edits which can't be resolved by a simple rebase: a file is deleted by one of the pulls,
+    /// or the overlap is too large to review at a glance (see [`BLOCKING_FILE_COUNT`]).
+    Blocking,
+}
+
+/// How a pull request changes a file.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Added,
+    /// Edits and renames (the latter touch both the old and the new path).
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    fn verb(&self) -> &'static str {
         match self {
-            ConflictType::Overlap => comments::OVERLAP_TEMPLATE,
-            ConflictType::IncompleteTranslation => comments::INCOMPLETE_TRANSLATION_TEMPLATE,
+            ChangeKind::Added => "added",
+            ChangeKind::Modified => "edited",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// Changes made to a single file by both pull requests of a conflict.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    /// Change made by the trigger pull.
+    pub trigger: ChangeKind,
+    /// Change made by the original pull.
+    pub original: ChangeKind,
+}
+
+impl FileChange {
+    /// Check if both pulls create the file (e.g. two people started the same new article), so only one of the new
+    /// versions can be kept.
+    pub fn is_added_by_both(&self) -> bool {
+        self.trigger == ChangeKind::Added && self.original == ChangeKind::Added
+    }
+
+    /// A sentence about both changes, e.g. `wiki/Article/en.md is edited in #2 but deleted in #1`.
+    fn describe(&self, path: &str, trigger: i32, original: i32) -> String {
+        if self.is_added_by_both() {
+            return format!("{path} is created by both #{trigger} and #{original}");
+        }
+        format!(
+            "{} is {} in #{} but {} in #{}",
+            path,
+            self.trigger.verb(),
+            trigger,
+            self.original.verb(),
+            original
+        )
+    }
+}
+
+/// Where a pull request starts changing a conflicting file, see [`CompareConfig::line_hints`].
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub struct LineHint {
+    pub path: String,
+    /// Number of the first added or removed line (in the new version of the file, if the line is added).
+    pub line: usize,
+    /// Contents of that line, without the leading `+` or `-`.
+    pub context: String,
+}
+
+/// Serialize conflicts into a JSON array for external tooling. See [`Conflict`] for the format.
+pub fn conflicts_to_json(conflicts: &[Conflict]) -> String {
+    serde_json::to_string(conflicts).unwrap()
+}
+
+/// Summarize conflicts of a pull for a check run (see [`crate::github::Client::create_check_run`]):
+/// a failure listing the conflicting pulls, or a success if there are no conflicts.
+pub fn check_run_report(conflicts: &[Conflict]) -> (structs::CheckConclusion, String) {
+    if conflicts.is_empty() {
+        return (
+            structs::CheckConclusion::Success,
+            "No conflicts with other pull requests.".to_string(),
+        );
+    }
+    let lines: Vec<_> = conflicts
+        .iter()
+        .map(|c| {
+            format!(
+                "- [#{} {}]({}): {:?}, {} file(s)",
+                c.original,
+                escape_markdown(&c.reference_title),
+                c.reference_url,
+                c.kind,
+                c.file_set.len()
+            )
+        })
+        .collect();
+    (structs::CheckConclusion::Failure, lines.join("\n"))
+}
+
+/// Lists with more files than this are rendered collapsed in comments by default, see [`RenderConfig`].
+pub const DEFAULT_MAX_INLINE_FILES: usize = 10;
+
+/// Overlaps of more files than this are too large to review at a glance, see [`Severity::Blocking`].
+pub const BLOCKING_FILE_COUNT: usize = 10;
+
+/// Default for [`CompareConfig::max_files`], well above what any normal pull changes.
+pub const DEFAULT_MAX_FILES: usize = 3000;
+
+/// Settings for rendering conflicts in comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderConfig {
+    /// Lists with more files than this are collapsed under a summary (`0` collapses every list).
+    pub max_inline_files: usize,
+
+    /// Language of the comment introduction (see [`ConflictType::to_markdown_localized`]), English if not set.
+    /// The rest of the comment, like the list of files, is the same in every language.
+    pub language: Option<String>,
+
+    /// Line added at the end of every comment, e.g. one made by [`comments::footer`].
+    pub footer: Option<String>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            max_inline_files: DEFAULT_MAX_INLINE_FILES,
+            language: None,
+            footer: None,
         }
-        .to_string()
     }
 }
 
 /// A structure containing information about a conflict between two pull requests.
-#[derive(Debug, Ord, Eq, PartialEq, PartialOrd, Clone)]
+///
+/// The JSON representation (see [`conflicts_to_json`]) uses field names as they are written here, and is meant to be
+/// consumed by external tools, so the fields should not be renamed. Example:
+/// ```json
+/// {"kind":"Overlap","trigger":2,"original":1,"reference_url":"https://github.com/ppy/osu-wiki/pull/1","reference_title":"Update article","file_set":["wiki/Article/en.md"]}
+/// ```
+#[derive(Debug, Ord, Eq, PartialEq, PartialOrd, Clone, Serialize, Deserialize)]
 pub struct Conflict {
     /// Type of conflict.
     pub kind: ConflictType,
@@ -48,8 +213,22 @@ pub struct Conflict {
     /// A GitHub URL to the "original" pull request.
     pub reference_url: String,
 
+    /// Title of the "original" pull request.
+    #[serde(default)]
+    pub reference_title: String,
+
     /// List of conflicting files. May contain both translations and originals, but articles (= directories) are guaranteed to be unique.
     pub file_set: Vec<String>,
+
+    /// Files from `file_set` which are added or deleted by either pull, along with what each pull does to them.
+    /// Files edited by both pulls (the usual case) aren't listed. Omitted from JSON when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_changes: Vec<FileChange>,
+
+    /// First changed line of files from `file_set`, in the pull which made the change (the trigger for overlaps,
+    /// the original for incomplete translations). Only filled if [`CompareConfig::line_hints`] is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_hints: Vec<LineHint>,
 }
 
 impl Conflict {
@@ -58,6 +237,7 @@ impl Conflict {
         trigger: i32,
         original: i32,
         reference_url: String,
+        reference_title: String,
         file_set: Vec<String>,
     ) -> Self {
         Self {
@@ -65,168 +245,942 @@ impl Conflict {
             trigger,
             original,
             reference_url,
+            reference_title,
             file_set,
+            file_changes: Vec::new(),
+            line_hints: Vec::new(),
         }
     }
     pub fn overlap(
         trigger: i32,
         original: i32,
         reference_url: String,
+        reference_title: String,
         file_set: Vec<String>,
     ) -> Self {
-        Self {
-            kind: ConflictType::Overlap,
+        Self::new(
+            ConflictType::Overlap,
             trigger,
             original,
             reference_url,
+            reference_title,
             file_set,
+        )
+    }
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            ConflictType::IncompleteTranslation | ConflictType::MixedLanguageChange => {
+                Severity::Info
+            }
+            // Assets are mostly binary files, which can't be merged: only one of the versions can be kept.
+            ConflictType::AssetChange => Severity::Blocking,
+            ConflictType::Overlap => {
+                let has_deletions = self.file_changes.iter().any(|change| {
+                    change.trigger == ChangeKind::Deleted || change.original == ChangeKind::Deleted
+                });
+                if has_deletions || self.file_set.len() > BLOCKING_FILE_COUNT {
+                    Severity::Blocking
+                } else {
+                    Severity::Warning
+                }
+            }
+        }
+    }
+    pub fn with_file_changes(mut self, file_changes: Vec<FileChange>) -> Self {
+        self.file_changes = file_changes;
+        self
+    }
+    pub fn with_line_hints(mut self, line_hints: Vec<LineHint>) -> Self {
+        self.line_hints = line_hints;
+        self
+    }
+    /// A file from `file_set`, followed by its line hint (if any).
+    fn file_with_line_hint(&self, file: &str) -> String {
+        match self.line_hints.iter().find(|hint| hint.path == file) {
+            Some(hint) => format!("{} (around line {})", file, hint.line),
+            None => file.to_string(),
         }
     }
     pub fn incomplete_translation(
         trigger: i32,
         original: i32,
         reference_url: String,
+        reference_title: String,
         file_set: Vec<String>,
     ) -> Self {
-        Self {
-            kind: ConflictType::IncompleteTranslation,
+        Self::new(
+            ConflictType::IncompleteTranslation,
             trigger,
             original,
             reference_url,
+            reference_title,
             file_set,
-        }
+        )
     }
 }
 
 impl ToMarkdown for Conflict {
     fn to_markdown(&self) -> String {
-        let header = comments::CommentHeader {
+        self.to_markdown_with(&RenderConfig::default())
+    }
+}
+
+impl Conflict {
+    fn header(&self) -> comments::CommentHeader {
+        comments::CommentHeader {
             pull_number: self.original,
             conflict_type: self.kind.clone(),
-        };
-        let mut lines = Vec::new();
-        lines.push(header.to_markdown());
-        lines.push(self.kind.to_markdown());
+        }
+    }
+
+    /// Marker which identifies comments about this conflict, see [`comments::TrackingMarker`].
+    pub fn tracking_marker(&self) -> comments::TrackingMarker {
+        self.header().tracking_marker(self.trigger)
+    }
+
+    /// Render the conflict as a comment, see [`RenderConfig`].
+    pub fn to_markdown_with(&self, config: &RenderConfig) -> String {
+        self.render(config, false)
+    }
+
+    /// Render the conflict as no longer relevant (e.g. the other pull was closed), with the reference to the other
+    /// pull crossed out. Meant to replace the original comment (see [`Conflict::to_markdown_with`]), so that
+    /// the history of the conflict is kept instead of deleting the comment.
+    pub fn to_markdown_resolved(&self, config: &RenderConfig) -> String {
+        self.render(config, true)
+    }
+
+    fn render(&self, config: &RenderConfig, resolved: bool) -> String {
+        let mut lines = vec![
+            self.header().to_markdown(),
+            self.tracking_marker().to_markdown(),
+            self.intro(config),
+        ];
+        lines.extend(self.reference_lines(config, resolved));
+        if let Some(footer) = &config.footer {
+            lines.push(String::new());
+            lines.push(footer.clone());
+        }
+        lines.join("\n")
+    }
+
+    /// Introduction of comments about this kind of conflict, see [`comments::Templates`].
+    fn intro(&self, config: &RenderConfig) -> String {
+        match &config.language {
+            Some(language) => self.kind.to_markdown_localized(language),
+            None => self.kind.to_markdown(),
+        }
+    }
 
-        if self.file_set.len() > 10 {
-            lines.push(format!("- {} (>10 files)", self.reference_url));
+    /// A list item with the other pull and conflicting files.
+    fn reference_lines(&self, config: &RenderConfig, resolved: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        let reference = format!(
+            "[#{} {}]({})",
+            self.original,
+            escape_markdown(&self.reference_title),
+            self.reference_url
+        );
+        if resolved {
+            lines.push(format!("- ~~{reference}~~ ✅ resolved, files:"));
         } else {
-            lines.push(format!("- {}, files:", self.reference_url));
-            let indent = "  ";
-            lines.push(format!("{indent}```"));
-            for file in &self.file_set {
-                lines.push(format!("{indent}{file}"));
+            lines.push(format!("- {reference}, files:"));
+        }
+        let indent = "  ";
+        // Long lists are collapsed to keep the comment readable.
+        let collapsed = self.file_set.len() > config.max_inline_files;
+        if collapsed {
+            lines.push(format!(
+                "{indent}<details><summary>{} files</summary>\n",
+                self.file_set.len()
+            ));
+        }
+        // Code blocks are shown verbatim, so file names don't need escaping there.
+        let files: Vec<_> = self
+            .file_set
+            .iter()
+            .map(|file| self.file_with_line_hint(file))
+            .collect();
+        let fence = code_fence(&files);
+        lines.push(format!("{indent}{fence}"));
+        for file in &files {
+            lines.push(format!("{indent}{file}"));
+        }
+        lines.push(format!("{indent}{fence}"));
+        if collapsed {
+            lines.push(format!("{indent}</details>"));
+        }
+        for change in &self.file_changes {
+            lines.push(format!(
+                "{indent}- {}",
+                change.describe(&escape_markdown(&change.path), self.trigger, self.original)
+            ));
+        }
+        lines
+    }
+}
+
+/// Render all conflicts of a pull as a single comment, instead of one comment per conflict (with the default
+/// settings, see [`render_grouped_with`]).
+pub fn render_grouped(conflicts: &[Conflict]) -> String {
+    render_grouped_with(conflicts, &RenderConfig::default())
+}
+
+/// Render all conflicts of a pull (all with the same trigger) as a single comment. Conflicts are listed in order
+/// (see [`Conflict`]), and those of the same kind share the introduction. Tracking markers of every conflict
+/// (see [`Conflict::tracking_marker`]) are at the end, so that each of them can still be found
+/// (see [`comments::TrackingMarker::all_from_comment`]).
+pub fn render_grouped_with(conflicts: &[Conflict], config: &RenderConfig) -> String {
+    let mut sorted = conflicts.to_vec();
+    sorted.sort();
+    if sorted.is_empty() {
+        return String::new();
+    }
+    let mut lines = Vec::new();
+    let mut previous_kind = None;
+    for conflict in &sorted {
+        if previous_kind != Some(&conflict.kind) {
+            if previous_kind.is_some() {
+                lines.push(String::new());
             }
-            lines.push(format!("{indent}```"));
+            lines.push(conflict.intro(config));
+            previous_kind = Some(&conflict.kind);
         }
+        lines.extend(conflict.reference_lines(config, false));
+    }
+    if let Some(footer) = &config.footer {
+        lines.push(String::new());
+        lines.push(footer.clone());
+    }
+    lines.extend(sorted.iter().map(|c| c.tracking_marker().to_markdown()));
+    lines.join("\n")
+}
 
+impl ToPlainText for Conflict {
+    fn to_plain_text(&self) -> String {
+        let mut lines = vec![
+            self.kind.to_plain_text(),
+            format!(
+                "#{} {} ({}), files:",
+                self.original, self.reference_title, self.reference_url
+            ),
+        ];
+        for file in &self.file_set {
+            lines.push(format!("  {}", self.file_with_line_hint(file)));
+        }
+        for change in &self.file_changes {
+            lines.push(change.describe(&change.path, self.trigger, self.original));
+        }
         lines.join("\n")
     }
 }
 
+/// Settings which affect how pull requests are compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompareConfig {
+    /// Languages of the source articles (file names without the extension), which translations follow.
+    pub original_languages: Vec<String>,
+
+    /// Whether draft pulls take part in conflict detection (they are work in progress, so they don't by default).
+    pub include_drafts: bool,
+
+    /// Whether pulls by the same author are exempt from conflict detection (e.g. an original change and its translation
+    /// opened by the same person, who is already aware of both).
+    pub skip_same_author: bool,
+
+    /// Languages which article files may have, e.g. `zh-tw` or `pt-br` (original languages are always allowed).
+    /// Other files, like `TEMPLATE.md` or `_index.md`, are ignored. If empty, any file name is accepted.
+    pub known_languages: Vec<String>,
+
+    /// Extensions of files which are checked for conflicts, including the leading dot (e.g. `.md`).
+    /// Files are only compared against files with the same extension.
+    pub extensions: Vec<String>,
+
+    /// Whether paths differing only in case (`wiki/Article/En.md` and `wiki/article/en.md`) refer to the same file.
+    pub case_insensitive_paths: bool,
+
+    /// Pulls with any of these labels (e.g. `no-conflict-check`) are left alone.
+    pub ignore_labels: Vec<String>,
+
+    /// Only report overlaps if both pulls edit the same lines of a file (according to hunk ranges),
+    /// rather than whenever they touch the same file. Added and deleted files always overlap.
+    pub line_level_overlaps: bool,
+
+    /// Mention where each pull starts changing a conflicting file (`wiki/Article/en.md (around line 42)`).
+    /// Off by default, since it makes comments longer and updates them whenever the lines move.
+    pub line_hints: bool,
+
+    /// Report pulls which change an original article along with its translations (see [`check_pull`]).
+    pub mixed_language_advisories: bool,
+
+    /// Glob patterns of paths which are checked for conflicts (see [`glob_match`]). If empty, any path is accepted.
+    pub include_paths: Vec<String>,
+
+    /// Glob patterns of paths which are never checked for conflicts, even if they match [`CompareConfig::include_paths`].
+    pub exclude_paths: Vec<String>,
+
+    /// Drop overlapping files which one of the pulls doesn't change compared to the default branch
+    /// (see [`filter_by_baseline`]). Requires fetching these files, which is done by the controller.
+    pub default_branch_baseline: bool,
+
+    /// Also report pulls changing the same files without one of [`CompareConfig::extensions`], such as images
+    /// (see [`ConflictType::AssetChange`]). Such files are compared by exact path. Binary files (e.g. PNG or JPG
+    /// images) are only listed as "differ" in `.diff` files, which is kept as a change without hunks
+    /// (see [`crate::github::parse_diff`]).
+    pub asset_conflicts: bool,
+
+    /// Pulls which haven't been updated for this long are left alone (see [`is_skipped`]). If not set, pulls
+    /// are compared regardless of age.
+    pub stale_after: Option<chrono::Duration>,
+
+    /// How article files are named, see [`ArticleLayout`].
+    pub article_layout: ArticleLayout,
+
+    /// Pulls changing more article files than this are not compared with others, since comparing them would take
+    /// too long (see [`DEFAULT_MAX_FILES`]).
+    pub max_files: usize,
+}
+
+impl Default for CompareConfig {
+    fn default() -> Self {
+        Self {
+            original_languages: vec!["en".to_string()],
+            include_drafts: false,
+            skip_same_author: false,
+            known_languages: Vec::new(),
+            extensions: vec![".md".to_string()],
+            case_insensitive_paths: false,
+            ignore_labels: Vec::new(),
+            line_level_overlaps: false,
+            line_hints: false,
+            mixed_language_advisories: false,
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            default_branch_baseline: false,
+            asset_conflicts: false,
+            stale_after: None,
+            article_layout: ArticleLayout::FileName,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+}
+
+/// Where the language of an article file is in its path, see [`Article::from_file_path_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArticleLayout {
+    /// Each language is a file in the article directory: `wiki/Article/en.md`.
+    #[default]
+    FileName,
+
+    /// Each language is a directory in the article directory, with files named the same way in each of them:
+    /// `wiki/Article/en/content.md`.
+    Folder,
+}
+
 /// A lightweight article wrapper, made for ease of file path comparison.
 #[derive(Debug)]
 pub struct Article {
     pub path: String,
     pub language: String,
+
+    /// File extension, including the leading dot.
+    pub extension: String,
+
+    /// Name of the file inside the language directory (without the extension) for [`ArticleLayout::Folder`].
+    pub file_name: Option<String>,
 }
 
 impl Article {
-    pub fn from_file_path(s: &str) -> Self {
+    /// Parse a path like `wiki/Article/en.md`. Files which can't be articles (e.g. `README.md` at the repository root,
+    /// a dotfile like `wiki/.md`, or a file without extension) are rejected.
+    pub fn from_file_path(s: &str) -> Option<Self> {
+        Self::from_file_path_with(s, ArticleLayout::FileName)
+    }
+
+    /// Same as [`Article::from_file_path`], but for articles laid out in the given way: for [`ArticleLayout::Folder`],
+    /// `wiki/Article/fr/content.md` is the `fr` version of `wiki/Article`.
+    pub fn from_file_path_with(s: &str, layout: ArticleLayout) -> Option<Self> {
         let fp = std::path::Path::new(s);
-        let language = fp.file_stem().unwrap().to_str().unwrap().to_owned();
-        let path = fp.parent().unwrap().to_str().unwrap().to_owned();
-        Self { path, language }
+        let stem = fp.file_stem()?.to_str()?.to_owned();
+        let extension = format!(".{}", fp.extension()?.to_str()?);
+        let parent = fp.parent()?;
+        let (path, language, file_name) = match layout {
+            ArticleLayout::FileName => (parent, stem, None),
+            ArticleLayout::Folder => {
+                let language = parent.file_name()?.to_str()?.to_owned();
+                (parent.parent()?, language, Some(stem))
+            }
+        };
+        let path = path.to_str()?.to_owned();
+        if path.is_empty()
+            || language.starts_with('.')
+            || file_name.as_ref().is_some_and(|f| f.starts_with('.'))
+        {
+            return None;
+        }
+        Some(Self {
+            path,
+            language,
+            extension,
+            file_name,
+        })
     }
 
-    pub fn original_file_path(&self) -> String {
-        format!("{}/en.md", self.path)
+    /// Paths to every possible original version of the article.
+    pub fn original_file_paths(&self, config: &CompareConfig) -> Vec<String> {
+        config
+            .original_languages
+            .iter()
+            .map(|language| self.file_path_in(language))
+            .collect()
     }
 
     pub fn file_path(&self) -> String {
-        format!("{}/{}.md", self.path, self.language)
+        self.file_path_in(&self.language)
+    }
+
+    fn file_path_in(&self, language: &str) -> String {
+        match &self.file_name {
+            Some(file_name) => {
+                format!("{}/{}/{}{}", self.path, language, file_name, self.extension)
+            }
+            None => format!("{}/{}{}", self.path, language, self.extension),
+        }
+    }
+
+    /// Check if the file name is a language code from [`CompareConfig::known_languages`] (case-insensitive).
+    pub fn has_known_language(&self, config: &CompareConfig) -> bool {
+        config.known_languages.is_empty()
+            || self.is_original(config)
+            || config
+                .known_languages
+                .iter()
+                .any(|language| language.eq_ignore_ascii_case(&self.language))
     }
 
-    pub fn is_original(&self) -> bool {
-        self.language == "en"
+    pub fn is_original(&self, config: &CompareConfig) -> bool {
+        config.original_languages.contains(&self.language)
     }
 
-    pub fn is_translation(&self) -> bool {
-        !self.is_original()
+    pub fn is_translation(&self, config: &CompareConfig) -> bool {
+        !self.is_original(config)
     }
 }
 
 impl std::cmp::PartialEq for Article {
     fn eq(&self, other: &Self) -> bool {
-        self.path == other.path && self.language == other.language
+        self.path == other.path
+            && self.language == other.language
+            && self.extension == other.extension
+            && self.file_name == other.file_name
+    }
+}
+
+/// Paths affected by a file change without `a/` and `b/` prefixes, along with the kind of change: one path for edits,
+/// additions and deletions, and two for renames (so that a renamed article is matched against edits on either the old
+/// or the new path).
+fn patched_file_paths(patched: &unidiff::PatchedFile) -> Vec<(String, ChangeKind)> {
+    const NO_FILE: &str = "/dev/null";
+    let source = patched
+        .source_file
+        .strip_prefix("a/")
+        .unwrap_or(&patched.source_file);
+    let target = patched
+        .target_file
+        .strip_prefix("b/")
+        .unwrap_or(&patched.target_file);
+    if patched.target_file == NO_FILE {
+        return vec![(source.to_string(), ChangeKind::Deleted)];
+    }
+    if patched.source_file == NO_FILE {
+        return vec![(target.to_string(), ChangeKind::Added)];
+    }
+    let mut paths = Vec::new();
+    if source != target {
+        paths.push((source.to_string(), ChangeKind::Modified));
+    }
+    paths.push((target.to_string(), ChangeKind::Modified));
+    paths
+}
+
+/// Check if a file change has no line information: binary files come without hunks (see
+/// [`crate::github::StreamingDiffParser`]). They are still compared as whole files, but never line by line.
+fn is_binary(patched: &unidiff::PatchedFile) -> bool {
+    patched.hunks().is_empty()
+}
+
+/// Form of a path used for comparisons: lowercase if [`CompareConfig::case_insensitive_paths`] is set.
+fn path_key(path: &str, config: &CompareConfig) -> String {
+    if config.case_insensitive_paths {
+        path.to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Check if a path passes [`CompareConfig::include_paths`] and [`CompareConfig::exclude_paths`].
+fn is_path_allowed(path: &str, config: &CompareConfig) -> bool {
+    (config.include_paths.is_empty() || config.include_paths.iter().any(|p| glob_match(p, path)))
+        && !config.exclude_paths.iter().any(|p| glob_match(p, path))
+}
+
+/// Check if a diff changes more than [`CompareConfig::max_files`] article files. Counting stops at the limit,
+/// so this is cheap even for huge diffs.
+fn has_too_many_files(diff: &unidiff::PatchSet, config: &CompareConfig) -> bool {
+    article_files_ordered(diff, config)
+        .take(config.max_files.saturating_add(1))
+        .count()
+        > config.max_files
+}
+
+/// Paths (see [`path_key`]) of files with one of [`CompareConfig::extensions`] touched by a diff, in order of appearance.
+/// Paths filtered out by [`is_path_allowed`] are skipped.
+fn article_files_ordered<'a>(
+    diff: &'a unidiff::PatchSet,
+    config: &'a CompareConfig,
+) -> impl Iterator<Item = (String, ChangeKind)> + 'a {
+    diff.files()
+        .iter()
+        .flat_map(patched_file_paths)
+        .filter(|(path, _)| is_path_allowed(path, config))
+        .map(|(path, kind)| (path_key(&path, config), kind))
+        .filter(|(path, _)| config.extensions.iter().any(|ext| path.ends_with(ext)))
+}
+
+/// Line ranges of the original file (inclusive, 1-based) covered by each hunk, keyed by path (see [`path_key`]).
+fn changed_line_ranges(
+    diff: &unidiff::PatchSet,
+    config: &CompareConfig,
+) -> HashMap<String, Vec<(usize, usize)>> {
+    let mut out: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    for patched in diff.files().iter().filter(|patched| !is_binary(patched)) {
+        let ranges: Vec<_> = patched
+            .hunks()
+            .iter()
+            // Pure insertions don't cover any lines, but still conflict with edits around the insertion point.
+            .map(|h| (h.source_start, h.source_start + h.source_length.max(1) - 1))
+            .collect();
+        for (path, _) in patched_file_paths(patched) {
+            out.entry(path_key(&path, config))
+                .or_default()
+                .extend(ranges.iter().copied());
+        }
+    }
+    out
+}
+
+/// The first added or removed line of each file, keyed by path (see [`path_key`]).
+fn first_changed_lines(
+    diff: &unidiff::PatchSet,
+    config: &CompareConfig,
+) -> HashMap<String, LineHint> {
+    let mut out = HashMap::new();
+    for patched in diff.files().iter().filter(|patched| !is_binary(patched)) {
+        let first = patched
+            .hunks()
+            .iter()
+            .flat_map(|h| h.lines())
+            .find(|l| l.is_added() || l.is_removed());
+        let first = match first {
+            Some(line) => line,
+            None => continue,
+        };
+        for (path, _) in patched_file_paths(patched) {
+            out.entry(path_key(&path, config))
+                .or_insert_with(|| LineHint {
+                    path,
+                    line: first.target_line_no.or(first.source_line_no).unwrap_or(0),
+                    context: first.value.clone(),
+                });
+        }
+    }
+    out
+}
+
+/// Check if any two ranges (inclusive) from different sets intersect.
+fn ranges_overlap(a: &[(usize, usize)], b: &[(usize, usize)]) -> bool {
+    a.iter().any(|(a_start, a_end)| {
+        b.iter()
+            .any(|(b_start, b_end)| a_start <= b_end && b_start <= a_end)
+    })
+}
+
+/// Map path keys (see [`path_key`]) back to paths as they are written in the diffs. Later diffs take precedence.
+fn path_spellings(diffs: &[&unidiff::PatchSet], config: &CompareConfig) -> HashMap<String, String> {
+    diffs
+        .iter()
+        .flat_map(|diff| diff.files().iter().flat_map(patched_file_paths))
+        .map(|(path, _)| (path_key(&path, config), path))
+        .collect()
+}
+
+/// Unique paths of files without any of [`CompareConfig::extensions`] touched by a diff, and how they are changed.
+/// Unlike articles, these are keyed by paths as they are written in the diff.
+fn asset_files(diff: &unidiff::PatchSet, config: &CompareConfig) -> HashMap<String, ChangeKind> {
+    diff.files()
+        .iter()
+        .flat_map(patched_file_paths)
+        .filter(|(path, _)| is_path_allowed(path, config))
+        .filter(|(path, _)| !config.extensions.iter().any(|ext| path.ends_with(ext)))
+        .collect()
+}
+
+/// Unique paths of files with one of [`CompareConfig::extensions`] touched by a diff, and how they are changed.
+fn article_files(diff: &unidiff::PatchSet, config: &CompareConfig) -> HashMap<String, ChangeKind> {
+    article_files_ordered(diff, config).collect()
+}
+
+/// Compare strings the way humans do, treating runs of digits as numbers (`section2` < `section10`).
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    fn chunks(s: &str) -> Vec<&str> {
+        let mut out = Vec::new();
+        let mut start = 0;
+        for (i, c) in s.char_indices().skip(1) {
+            let prev = s[..i].chars().next_back().unwrap();
+            if prev.is_ascii_digit() != c.is_ascii_digit() {
+                out.push(&s[start..i]);
+                start = i;
+            }
+        }
+        if start < s.len() {
+            out.push(&s[start..]);
+        }
+        out
+    }
+
+    for (x, y) in chunks(a).into_iter().zip(chunks(b)) {
+        let ordering = if x.starts_with(|c: char| c.is_ascii_digit())
+            && y.starts_with(|c: char| c.is_ascii_digit())
+        {
+            // Compare numbers by magnitude first, so that arbitrarily long ones don't overflow.
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            x.cmp(y)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Group articles by their directory, skipping files which aren't articles.
+fn index_articles(
+    paths: impl Iterator<Item = String>,
+    config: &CompareConfig,
+) -> HashMap<String, Vec<Article>> {
+    let mut index: HashMap<String, Vec<Article>> = HashMap::new();
+    for article in paths
+        .filter_map(|p| Article::from_file_path_with(&p, config.article_layout))
+        .filter(|a| a.has_known_language(config))
+    {
+        index.entry(article.path.clone()).or_default().push(article);
+    }
+    index
+}
+
+/// Check if a pull is left out of conflict detection: it's no longer open, it's a draft
+/// (unless [`CompareConfig::include_drafts`] is set), it has one of [`CompareConfig::ignore_labels`],
+/// or it hasn't been updated within [`CompareConfig::stale_after`].
+fn is_skipped(pull: &structs::PullRequest, config: &CompareConfig) -> bool {
+    !pull.is_open()
+        || config
+            .stale_after
+            .is_some_and(|age| pull.updated_at < chrono::Utc::now() - age)
+        || (!config.include_drafts && pull.is_draft())
+        || config
+            .ignore_labels
+            .iter()
+            .any(|label| pull.has_label(label))
+}
+
+/// Check a single pull for translations changed along with their originals, which is worth a reminder
+/// ([`ConflictType::MixedLanguageChange`]) if [`CompareConfig::mixed_language_advisories`] is set.
+/// The conflict lists the translations.
+pub fn check_pull(pull: &structs::PullRequest, config: &CompareConfig) -> Result<Option<Conflict>> {
+    if !config.mixed_language_advisories || is_skipped(pull, config) {
+        return Ok(None);
+    }
+    let diff = pull
+        .diff
+        .as_ref()
+        .ok_or_else(|| eyre::eyre!("Pull #{} has no diff loaded", pull.number))?;
+    let index = index_articles(
+        article_files_ordered(diff, config).map(|(path, _)| path),
+        config,
+    );
+    let spellings = path_spellings(&[diff], config);
+
+    let mut translations = Vec::new();
+    for articles in index.values() {
+        for translation in articles.iter().filter(|a| a.is_translation(config)) {
+            if articles
+                .iter()
+                .any(|a| a.is_original(config) && a.extension == translation.extension)
+            {
+                let path = translation.file_path();
+                translations.push(spellings.get(&path).cloned().unwrap_or(path));
+            }
+        }
+    }
+    if translations.is_empty() {
+        return Ok(None);
+    }
+    translations.sort_by(|a, b| natural_cmp(a, b));
+    translations.dedup();
+    Ok(Some(Conflict::new(
+        ConflictType::MixedLanguageChange,
+        pull.number,
+        pull.number,
+        pull.html_url.clone(),
+        pull.title.clone(),
+        translations,
+    )))
+}
+
+/// Language of translations changed by a pull, e.g. for addressing its author in that language.
+/// Returns `None` if the pull doesn't change translations, or changes translations into several languages.
+pub fn translation_language(pull: &structs::PullRequest, config: &CompareConfig) -> Option<String> {
+    let diff = pull.diff.as_ref()?;
+    let mut languages: Vec<_> = article_files_ordered(diff, config)
+        .filter_map(|(path, _)| Article::from_file_path_with(&path, config.article_layout))
+        .filter(|a| a.is_translation(config) && a.has_known_language(config))
+        .map(|a| a.language.to_lowercase())
+        .collect();
+    languages.sort();
+    languages.dedup();
+    match languages.as_slice() {
+        [language] => Some(language.clone()),
+        _ => None,
     }
 }
 
 /// Compare two pulls and pinpoint different types of conflicts between them on article level.
+///
+/// Both pulls are expected to have their diffs loaded; otherwise, an error is returned.
+/// Closed and merged pulls never conflict (see [`structs::PullRequest::is_open`]).
+/// Draft pulls never conflict unless [`CompareConfig::include_drafts`] is set.
+/// Pulls targeting different branches never conflict either, and neither do pulls by the same author
+/// if [`CompareConfig::skip_same_author`] is set, or pulls with one of [`CompareConfig::ignore_labels`],
+/// or stale pulls (see [`CompareConfig::stale_after`]).
+/// A pull never conflicts with itself (same number in the same repository).
+/// Non-article files are only compared if [`CompareConfig::asset_conflicts`] is set.
+/// Pulls with more than [`CompareConfig::max_files`] article files are not compared at all.
 pub fn compare_pulls(
     new_pull: &structs::PullRequest,
     other_pull: &structs::PullRequest,
-) -> Vec<Conflict> {
-    let new_diff = new_pull.diff.as_ref().unwrap();
-    let other_diff = other_pull.diff.as_ref().unwrap();
+    config: &CompareConfig,
+) -> Result<Vec<Conflict>> {
+    if new_pull.number == other_pull.number && new_pull.repo_name() == other_pull.repo_name() {
+        return Ok(Vec::new());
+    }
+    if is_skipped(new_pull, config) || is_skipped(other_pull, config) {
+        return Ok(Vec::new());
+    }
+    if new_pull.base.ref_name != other_pull.base.ref_name {
+        return Ok(Vec::new());
+    }
+    if config.skip_same_author && new_pull.user.id == other_pull.user.id {
+        return Ok(Vec::new());
+    }
+    let new_diff = new_pull
+        .diff
+        .as_ref()
+        .ok_or_else(|| eyre::eyre!("Pull #{} has no diff loaded", new_pull.number))?;
+    let other_diff = other_pull
+        .diff
+        .as_ref()
+        .ok_or_else(|| eyre::eyre!("Pull #{} has no diff loaded", other_pull.number))?;
+    for (pull, diff, counterpart) in [
+        (new_pull, new_diff, other_pull),
+        (other_pull, other_diff, new_pull),
+    ] {
+        if has_too_many_files(diff, config) {
+            log::warn!(
+                "Pull #{} changes more than {} files, skipping its comparison with #{}",
+                pull.number,
+                config.max_files,
+                counterpart.number
+            );
+            return Ok(Vec::new());
+        }
+    }
 
     let mut overlaps = Vec::new();
     let mut originals = Vec::new();
 
     let mut is_new_translation = false;
 
-    for incoming in new_diff
-        .files()
-        .iter()
-        .filter(|fp| fp.target_file.ends_with(".md"))
-    {
-        let other_files: HashSet<_> = other_diff
-            .files()
-            .iter()
-            .filter(|patched| patched.target_file.ends_with(".md"))
-            .map(|patched| patched.path())
-            .collect();
-        for other in other_files.iter() {
-            let new_article = Article::from_file_path(&incoming.path());
-            let other_article = Article::from_file_path(other);
-
-            // Different folders.
-            if new_article.path != other_article.path {
-                continue;
-            }
+    let other_files = article_files(other_diff, config);
+    let other_index = index_articles(other_files.keys().cloned(), config);
+    let new_files = article_files(new_diff, config);
+    let new_index = index_articles(
+        article_files_ordered(new_diff, config).map(|(path, _)| path),
+        config,
+    );
+    let mut file_changes = Vec::new();
+    let spellings = path_spellings(&[other_diff, new_diff], config);
+    let spelling = |path: String| spellings.get(&path).cloned().unwrap_or(path);
+    let line_ranges = if config.line_level_overlaps {
+        Some((
+            changed_line_ranges(new_diff, config),
+            changed_line_ranges(other_diff, config),
+        ))
+    } else {
+        None
+    };
+    let (new_hints, other_hints) = if config.line_hints {
+        (
+            first_changed_lines(new_diff, config),
+            first_changed_lines(other_diff, config),
+        )
+    } else {
+        Default::default()
+    };
+    let hint = |hints: &HashMap<String, LineHint>, path: &str| {
+        hints.get(path).map(|hint| LineHint {
+            path: spelling(path.to_string()),
+            ..hint.clone()
+        })
+    };
+    let mut overlap_hints = Vec::new();
+    let mut original_hints = Vec::new();
 
+    for (directory, new_articles) in new_index.iter() {
+        // Articles from different folders never conflict.
+        let other_articles = match other_index.get(directory) {
+            Some(articles) => articles,
+            None => continue,
+        };
+        for new_article in new_articles {
             // Protect against duplicate conflicts when an original change also marks translations as outdated:
             // [EN (meaningful update), RU (outdate translation)] vs [RU (translation update)] produces only one conflict (IncompleteTranslation).
-            let translation_only_change = new_article.is_translation()
-                && !other_files.contains(&new_article.original_file_path());
+            let translation_only_change = new_article.is_translation(config)
+                && !new_article
+                    .original_file_paths(config)
+                    .iter()
+                    .any(|original| other_files.contains_key(original));
 
-            if new_article == other_article
-                && (new_article.is_original() || translation_only_change)
-            {
-                overlaps.push(new_article.file_path());
-                continue;
-            }
+            for other_article in other_articles {
+                // With `ArticleLayout::Folder`, files with different names are different articles in the same directory.
+                if new_article.extension != other_article.extension
+                    || new_article.file_name != other_article.file_name
+                {
+                    continue;
+                }
+                if new_article == other_article
+                    && (new_article.is_original(config) || translation_only_change)
+                {
+                    let path = new_article.file_path();
+                    let change = FileChange {
+                        path: spelling(path.clone()),
+                        trigger: new_files
+                            .get(&path)
+                            .copied()
+                            .unwrap_or(ChangeKind::Modified),
+                        original: other_files
+                            .get(&path)
+                            .copied()
+                            .unwrap_or(ChangeKind::Modified),
+                    };
+                    let edited_by_both = change.trigger == ChangeKind::Modified
+                        && change.original == ChangeKind::Modified;
+                    if let Some((new_ranges, other_ranges)) = &line_ranges {
+                        let (new_ranges, other_ranges) = (
+                            new_ranges.get(&path).map(Vec::as_slice).unwrap_or_default(),
+                            other_ranges
+                                .get(&path)
+                                .map(Vec::as_slice)
+                                .unwrap_or_default(),
+                        );
+                        // Without hunks (e.g. binary files), there's nothing to compare.
+                        if edited_by_both
+                            && !new_ranges.is_empty()
+                            && !other_ranges.is_empty()
+                            && !ranges_overlap(new_ranges, other_ranges)
+                        {
+                            continue;
+                        }
+                    }
+                    if !edited_by_both {
+                        file_changes.push(change);
+                    }
+                    overlap_hints.extend(hint(&new_hints, &path));
+                    overlaps.push(spelling(path));
+                    continue;
+                }
 
-            if new_article.is_original() && other_article.is_translation() {
-                originals.push(new_article.file_path());
-            } else if other_article.is_original() && new_article.is_translation() {
-                originals.push(other_article.file_path());
-                is_new_translation = true;
+                if new_article.is_original(config) && other_article.is_translation(config) {
+                    original_hints.extend(hint(&new_hints, &new_article.file_path()));
+                    originals.push(spelling(new_article.file_path()));
+                } else if other_article.is_original(config) && new_article.is_translation(config) {
+                    original_hints.extend(hint(&other_hints, &other_article.file_path()));
+                    originals.push(spelling(other_article.file_path()));
+                    is_new_translation = true;
+                }
             }
         }
     }
 
-    overlaps.sort();
-    originals.sort();
+    overlaps.sort_by(|a, b| natural_cmp(a, b));
+    // An original is listed once per translation in the other pull.
+    originals.sort_by(|a, b| natural_cmp(a, b));
+    originals.dedup();
+    file_changes.sort();
+    for hints in [&mut overlap_hints, &mut original_hints] {
+        hints.sort_by(|a, b| natural_cmp(&a.path, &b.path));
+        hints.dedup();
+    }
 
     let mut out = Vec::new();
     if !overlaps.is_empty() {
-        out.push(Conflict::overlap(
-            new_pull.number,
-            other_pull.number,
-            other_pull.html_url.clone(),
-            overlaps,
-        ));
+        out.push(
+            Conflict::overlap(
+                new_pull.number,
+                other_pull.number,
+                other_pull.html_url.clone(),
+                other_pull.title.clone(),
+                overlaps,
+            )
+            .with_file_changes(file_changes)
+            .with_line_hints(overlap_hints),
+        );
+    }
+
+    if config.asset_conflicts {
+        let new_assets = asset_files(new_diff, config);
+        let other_assets = asset_files(other_diff, config);
+        let mut assets: Vec<_> = new_assets
+            .keys()
+            .filter(|path| other_assets.contains_key(*path))
+            .cloned()
+            .collect();
+        assets.sort_by(|a, b| natural_cmp(a, b));
+        let mut asset_changes: Vec<_> = assets
+            .iter()
+            .map(|path| FileChange {
+                path: path.clone(),
+                trigger: new_assets[path],
+                original: other_assets[path],
+            })
+            .filter(|c| c.trigger != ChangeKind::Modified || c.original != ChangeKind::Modified)
+            .collect();
+        asset_changes.sort();
+        if !assets.is_empty() {
+            out.push(
+                Conflict::new(
+                    ConflictType::AssetChange,
+                    new_pull.number,
+                    other_pull.number,
+                    other_pull.html_url.clone(),
+                    other_pull.title.clone(),
+                    assets,
+                )
+                .with_file_changes(asset_changes),
+            );
+        }
     }
 
     if !originals.is_empty() {
@@ -235,17 +1189,205 @@ pub fn compare_pulls(
         } else {
             (&other_pull, &new_pull)
         };
-        out.push(Conflict::incomplete_translation(
-            trigger.number,
-            original.number,
-            original.html_url.clone(),
-            originals,
-        ));
+        out.push(
+            Conflict::incomplete_translation(
+                trigger.number,
+                original.number,
+                original.html_url.clone(),
+                original.title.clone(),
+                originals,
+            )
+            .with_line_hints(original_hints),
+        );
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Drop files from an overlap if one of the pulls doesn't actually change them compared to the default branch:
+/// for example, it only reverts a file to its state on the default branch, or repeats a change merged in the meantime.
+///
+/// `base_files` maps paths from [`Conflict::file_set`] to their contents on the default branch (see
+/// [`CompareConfig::default_branch_baseline`]). Files with unknown contents (e.g. new ones) are kept as is.
+/// A hunk doesn't diverge from the base if the default branch already has the lines it produces, and no longer has
+/// the lines it replaces. A file is kept only if diverging hunks of both pulls touch the same lines.
+///
+/// Other kinds of conflicts are returned unchanged. If no files are left, the conflict is dropped altogether.
+pub fn filter_by_baseline(
+    conflict: Conflict,
+    new_pull: &structs::PullRequest,
+    other_pull: &structs::PullRequest,
+    base_files: &HashMap<String, String>,
+    config: &CompareConfig,
+) -> Option<Conflict> {
+    if conflict.kind != ConflictType::Overlap {
+        return Some(conflict);
+    }
+    let (new_diff, other_diff) = match (&new_pull.diff, &other_pull.diff) {
+        (Some(new_diff), Some(other_diff)) => (new_diff, other_diff),
+        _ => return Some(conflict),
+    };
+    let diverges = |path: &String| {
+        let base = match base_files.get(path) {
+            Some(base) => base,
+            None => return true,
+        };
+        match (
+            diverging_line_ranges(new_diff, path, base, config),
+            diverging_line_ranges(other_diff, path, base, config),
+        ) {
+            (Some(new_ranges), Some(other_ranges)) => ranges_overlap(&new_ranges, &other_ranges),
+            // Without hunks (e.g. binary files), there's nothing to compare.
+            _ => true,
+        }
+    };
+    let file_set: Vec<_> = conflict
+        .file_set
+        .iter()
+        .filter(|f| diverges(f))
+        .cloned()
+        .collect();
+    if file_set.is_empty() {
+        return None;
+    }
+    let mut conflict = conflict;
+    conflict.file_changes.retain(|c| file_set.contains(&c.path));
+    conflict.line_hints.retain(|h| file_set.contains(&h.path));
+    conflict.file_set = file_set;
+    Some(conflict)
+}
+
+/// Line ranges (see [`changed_line_ranges`]) of hunks which change `path` in a way its `base` version doesn't reflect.
+/// Returns `None` if the diff has no hunks for the file, or the file is binary (see [`is_binary`]).
+fn diverging_line_ranges(
+    diff: &unidiff::PatchSet,
+    path: &str,
+    base: &str,
+    config: &CompareConfig,
+) -> Option<Vec<(usize, usize)>> {
+    let key = path_key(path, config);
+    let base_lines: Vec<_> = base.lines().collect();
+    let contains = |lines: Vec<unidiff::Line>| {
+        let block: Vec<_> = lines
+            .iter()
+            .map(|l| l.value.trim_end_matches(['\r', '\n']))
+            .collect();
+        block.is_empty()
+            || base_lines
+                .windows(block.len())
+                .any(|w| w == block.as_slice())
+    };
+    let files: Vec<_> = diff
+        .files()
+        .iter()
+        .filter(|patched| {
+            patched_file_paths(patched)
+                .iter()
+                .any(|(p, _)| path_key(p, config) == key)
+        })
+        .collect();
+    if files.is_empty() || files.iter().any(|patched| is_binary(patched)) {
+        return None;
     }
+    let hunks: Vec<_> = files.iter().flat_map(|patched| patched.hunks()).collect();
+    Some(
+        hunks
+            .into_iter()
+            // The base has the lines the hunk produces, and not the lines it replaces: nothing changes.
+            .filter(|h| !contains(h.target_lines()) || contains(h.source_lines()))
+            .map(|h| (h.source_start, h.source_start + h.source_length.max(1) - 1))
+            .collect(),
+    )
+}
+
+/// Compare every pull with every other one, treating the more recently created pull of each pair as the new one,
+/// and check every pull on its own (see [`check_pull`]).
+///
+/// Pulls which can't be compared (e.g. ones without a diff) are skipped with a warning, and pulls which were closed
+/// or merged since they were listed are skipped silently.
+/// Duplicate conflicts (same trigger, original, and kind) are collapsed into one.
+pub fn find_all_conflicts(pulls: &[structs::PullRequest], config: &CompareConfig) -> Vec<Conflict> {
+    let mut sorted_pulls: Vec<_> = pulls.iter().collect();
+    sorted_pulls.sort_by_key(|p| (p.created_at, p.number));
+
+    let mut found: HashMap<(i32, i32, ConflictType), Conflict> = HashMap::new();
+    for (i, new_pull) in sorted_pulls.iter().enumerate() {
+        match check_pull(new_pull, config) {
+            Ok(Some(c)) => {
+                found.insert((c.trigger, c.original, c.kind.clone()), c);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!(
+                "Failed to check pull #{}, skipping: {:?}",
+                new_pull.number,
+                e
+            ),
+        }
+        for other_pull in sorted_pulls[..i].iter() {
+            if new_pull.number == other_pull.number {
+                continue;
+            }
+            match compare_pulls(new_pull, other_pull, config) {
+                Ok(conflicts) => {
+                    for c in conflicts {
+                        found
+                            .entry((c.trigger, c.original, c.kind.clone()))
+                            .or_insert(c);
+                    }
+                }
+                Err(e) => log::warn!(
+                    "Failed to compare pulls #{} and #{}, skipping: {:?}",
+                    new_pull.number,
+                    other_pull.number,
+                    e
+                ),
+            }
+        }
+    }
+    let mut out: Vec<_> = found.into_values().collect();
     out.sort();
     out
 }
 
+/// Difference between conflicts found by two consecutive scans, see [`diff_conflicts`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConflictDelta {
+    /// Conflicts which weren't there during the previous scan.
+    pub added: Vec<Conflict>,
+
+    /// Conflicts found by both scans, in their current form (the file set may have changed in between).
+    pub persisting: Vec<Conflict>,
+
+    /// Conflicts from the previous scan which are gone now.
+    pub resolved: Vec<Conflict>,
+}
+
+/// Compare conflicts from the previous scan with the current ones (e.g. results of [`find_all_conflicts`]).
+/// Conflicts are matched by their pulls and kind (see [`Conflict::key`]), so a conflict whose file set changed
+/// still counts as persisting. All lists are sorted.
+pub fn diff_conflicts(previous: &[Conflict], current: &[Conflict]) -> ConflictDelta {
+    let previous_keys: HashMap<_, _> = previous.iter().map(|c| (c.key(), c)).collect();
+    let current_keys: HashMap<_, _> = current.iter().map(|c| (c.key(), c)).collect();
+
+    let mut delta = ConflictDelta::default();
+    for (key, c) in current_keys.iter() {
+        if previous_keys.contains_key(key) {
+            delta.persisting.push((*c).clone());
+        } else {
+            delta.added.push((*c).clone());
+        }
+    }
+    delta.resolved = previous_keys
+        .iter()
+        .filter(|(key, _)| !current_keys.contains_key(key))
+        .map(|(_, c)| (*c).clone())
+        .collect();
+    delta.added.sort();
+    delta.persisting.sort();
+    delta.resolved.sort();
+    delta
+}
+
 type ConflictKey = (i32, i32, ConflictType);
 impl Conflict {
     pub fn key(&self) -> ConflictKey {
@@ -280,7 +1422,10 @@ impl Storage {
                 if existing_conflict == c {
                     None
                 } else {
+                    existing_conflict.reference_title = c.reference_title.clone();
                     existing_conflict.file_set = c.file_set.clone();
+                    existing_conflict.file_changes = c.file_changes.clone();
+                    existing_conflict.line_hints = c.line_hints.clone();
                     Some(existing_conflict.clone())
                 }
             }
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::test::{self, pull_link};
+use crate::test::{self, pull_link, PULL_TITLE};
 
 #[test]
 fn conflict_to_markdown() {
@@ -8,6 +8,7 @@ fn conflict_to_markdown() {
         1,
         2,
         pull_link("test/repo", 2),
+        PULL_TITLE.to_string(),
         vec!["wiki/Ranking_criteria/en.md".to_string()],
     );
     assert_eq!(
@@ -17,8 +18,9 @@ fn conflict_to_markdown() {
 pull_number: 2
 conflict_type: Overlap
 -->
+<!-- observatory:pull:2:Overlap:1 -->
 {}
-- https://github.com/test/repo/pull/2, files:
+- [#2 Update \`Ranking criteria\`](https://github.com/test/repo/pull/2), files:
   ```
   wiki/Ranking_criteria/en.md
   ```"#,
@@ -30,8 +32,10 @@ conflict_type: Overlap
         2,
         3,
         pull_link("test/repo", 3),
+        PULL_TITLE.to_string(),
         vec!["wiki/Ranking_criteria/en.md".to_string(); 11],
     );
+    let files = ["  wiki/Ranking_criteria/en.md"; 11].join("\n");
     assert_eq!(
         c2.to_markdown(),
         format!(
@@ -39,25 +43,209 @@ conflict_type: Overlap
 pull_number: 3
 conflict_type: IncompleteTranslation
 -->
+<!-- observatory:pull:3:IncompleteTranslation:2 -->
 {}
-- https://github.com/test/repo/pull/3 (>10 files)"#,
-            comments::INCOMPLETE_TRANSLATION_TEMPLATE
+- [#3 Update \`Ranking criteria\`](https://github.com/test/repo/pull/3), files:
+  <details><summary>11 files</summary>
+
+  ```
+{}
+  ```
+  </details>"#,
+            comments::INCOMPLETE_TRANSLATION_TEMPLATE,
+            files
+        )
+    );
+}
+
+#[test]
+fn conflict_tracking_marker() {
+    let c = Conflict::overlap(
+        1,
+        2,
+        pull_link("test/repo", 2),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Ranking_criteria/en.md".to_string()],
+    );
+    let body = c.to_markdown_resolved(&RenderConfig::default());
+    assert_eq!(
+        comments::TrackingMarker::from_comment(&body),
+        Some(comments::TrackingMarker {
+            trigger: 1,
+            original: 2,
+            kind: ConflictType::Overlap,
+        })
+    );
+}
+
+#[test]
+fn conflict_to_markdown_resolved() {
+    let c = Conflict::overlap(
+        1,
+        2,
+        pull_link("test/repo", 2),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Ranking_criteria/en.md".to_string()],
+    );
+    let config = RenderConfig::default();
+    let active = c.to_markdown_with(&config);
+    let resolved = c.to_markdown_resolved(&config);
+    assert_eq!(
+        resolved,
+        active.replace(
+            "- [#2 Update \\`Ranking criteria\\`](https://github.com/test/repo/pull/2), files:",
+            "- ~~[#2 Update \\`Ranking criteria\\`](https://github.com/test/repo/pull/2)~~ ✅ resolved, files:"
+        )
+    );
+    assert_ne!(resolved, active);
+    // The comment is still recognized as the one about the conflict.
+    assert_eq!(
+        comments::CommentHeader::from_comment(&resolved),
+        comments::CommentHeader::from_comment(&active)
+    );
+}
+
+#[test]
+fn conflict_footer() {
+    let c = Conflict::overlap(
+        1,
+        2,
+        pull_link("test/repo", 2),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Ranking_criteria/en.md".to_string(); 20],
+    );
+    let footer = comments::footer("https://example.com/issues");
+    assert_eq!(
+        footer,
+        format!(
+            "<sub>observatory v{} · [feedback](https://example.com/issues)</sub>",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+    let config = RenderConfig {
+        footer: Some(footer.clone()),
+        ..Default::default()
+    };
+
+    assert!(!c.to_markdown().contains(&footer));
+    let with_footer = c.to_markdown_with(&config);
+    assert_eq!(with_footer.matches(&footer).count(), 1);
+    assert!(with_footer.ends_with(&format!("\n\n{footer}")));
+    assert_eq!(c.to_markdown_resolved(&config).matches(&footer).count(), 1);
+}
+
+#[test]
+fn render_grouped_conflicts() {
+    assert_eq!(render_grouped(&[]), "");
+
+    let file = "wiki/Ranking_criteria/en.md".to_string();
+    let conflicts = vec![
+        Conflict::incomplete_translation(
+            4,
+            3,
+            pull_link("test/repo", 3),
+            PULL_TITLE.to_string(),
+            vec![file.clone()],
+        ),
+        Conflict::overlap(
+            4,
+            2,
+            pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
+            vec![file.clone()],
+        ),
+        Conflict::overlap(
+            4,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec![file.clone()],
+        ),
+    ];
+    let footer = comments::footer("");
+    let config = RenderConfig {
+        footer: Some(footer.clone()),
+        ..Default::default()
+    };
+
+    let rendered = render_grouped_with(&conflicts, &config);
+    assert_eq!(
+        rendered,
+        format!(
+            r#"{}
+- [#1 Update \`Ranking criteria\`](https://github.com/test/repo/pull/1), files:
+  ```
+  wiki/Ranking_criteria/en.md
+  ```
+- [#2 Update \`Ranking criteria\`](https://github.com/test/repo/pull/2), files:
+  ```
+  wiki/Ranking_criteria/en.md
+  ```
+
+{}
+- [#3 Update \`Ranking criteria\`](https://github.com/test/repo/pull/3), files:
+  ```
+  wiki/Ranking_criteria/en.md
+  ```
+
+{}
+<!-- observatory:pull:1:Overlap:4 -->
+<!-- observatory:pull:2:Overlap:4 -->
+<!-- observatory:pull:3:IncompleteTranslation:4 -->"#,
+            comments::OVERLAP_TEMPLATE,
+            comments::INCOMPLETE_TRANSLATION_TEMPLATE,
+            footer
         )
     );
+    for c in &conflicts {
+        assert!(rendered.contains(&c.tracking_marker().to_markdown()));
+    }
+
+    let mut reversed = conflicts.clone();
+    reversed.reverse();
+    assert_eq!(render_grouped_with(&reversed, &config), rendered);
+}
+
+#[test]
+fn conflict_to_plain_text() {
+    let c = Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec![
+            "wiki/Article/fr.md".to_string(),
+            "wiki/Ranking_criteria/en.md".to_string(),
+        ],
+    )
+    .with_file_changes(vec![FileChange {
+        path: "wiki/Article/fr.md".to_string(),
+        trigger: ChangeKind::Modified,
+        original: ChangeKind::Deleted,
+    }]);
+    assert_eq!(
+        c.to_plain_text(),
+        r#"Someone else has edited same files as you did. Please check their changes in case they conflict with yours:
+#1 Update `Ranking criteria` (https://github.com/test/repo/pull/1), files:
+  wiki/Article/fr.md
+  wiki/Ranking_criteria/en.md
+wiki/Article/fr.md is edited in #2 but deleted in #1"#
+    );
 }
 
 #[test]
 fn article_basic() {
-    let original = Article::from_file_path("wiki/Article/en.md");
-    assert!(original.is_original());
-    assert!(!original.is_translation());
+    let config = CompareConfig::default();
+    let original = Article::from_file_path("wiki/Article/en.md").unwrap();
+    assert!(original.is_original(&config));
+    assert!(!original.is_translation(&config));
     assert_eq!(original.language, "en");
     assert_eq!(original.path, "wiki/Article");
     assert_eq!(original.file_path(), "wiki/Article/en.md");
 
-    let translation = Article::from_file_path("wiki/Article/ko.md");
-    assert!(!translation.is_original());
-    assert!(translation.is_translation());
+    let translation = Article::from_file_path("wiki/Article/ko.md").unwrap();
+    assert!(!translation.is_original(&config));
+    assert!(translation.is_translation(&config));
     assert_eq!(translation.language, "ko");
     assert_eq!(translation.path, "wiki/Article");
     assert_eq!(translation.file_path(), "wiki/Article/ko.md");
@@ -65,18 +253,173 @@ fn article_basic() {
     assert_ne!(original, translation);
 }
 
+#[test]
+fn article_custom_original_language() {
+    let config = CompareConfig {
+        original_languages: vec!["ja".to_string()],
+        ..Default::default()
+    };
+    let original = Article::from_file_path("wiki/Article/ja.md").unwrap();
+    assert!(original.is_original(&config));
+    assert!(!original.is_translation(&config));
+    assert_eq!(
+        original.original_file_paths(&config),
+        vec!["wiki/Article/ja.md"]
+    );
+
+    let translation = Article::from_file_path("wiki/Article/en.md").unwrap();
+    assert!(!translation.is_original(&config));
+    assert!(translation.is_translation(&config));
+}
+
+#[test]
+fn custom_original_language_incomplete_translation() {
+    let config = CompareConfig {
+        original_languages: vec!["ja".to_string()],
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/ja.md"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            1,
+            2,
+            pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/ja.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn article_layouts() {
+    let config = CompareConfig::default();
+    let by_file_name =
+        Article::from_file_path_with("wiki/Article/fr.md", ArticleLayout::FileName).unwrap();
+    assert_eq!(by_file_name.path, "wiki/Article");
+    assert_eq!(by_file_name.language, "fr");
+    assert_eq!(by_file_name.file_path(), "wiki/Article/fr.md");
+    assert_eq!(
+        by_file_name.original_file_paths(&config),
+        vec!["wiki/Article/en.md"]
+    );
+
+    let by_folder =
+        Article::from_file_path_with("wiki/Article/fr/content.md", ArticleLayout::Folder).unwrap();
+    assert_eq!(by_folder.path, "wiki/Article");
+    assert_eq!(by_folder.language, "fr");
+    assert_eq!(by_folder.file_name.as_deref(), Some("content"));
+    assert_eq!(by_folder.file_path(), "wiki/Article/fr/content.md");
+    assert_eq!(
+        by_folder.original_file_paths(&config),
+        vec!["wiki/Article/en/content.md"]
+    );
+
+    // The same path means different things in different layouts.
+    let misread =
+        Article::from_file_path_with("wiki/Article/fr/content.md", ArticleLayout::FileName)
+            .unwrap();
+    assert_eq!(misread.path, "wiki/Article/fr");
+    assert_eq!(misread.language, "content");
+
+    assert_eq!(
+        Article::from_file_path_with("wiki/en.md", ArticleLayout::Folder),
+        None
+    );
+    assert_eq!(
+        Article::from_file_path_with("wiki/Article/.en/content.md", ArticleLayout::Folder),
+        None
+    );
+    assert_eq!(
+        Article::from_file_path_with("wiki/Article/en/.md", ArticleLayout::Folder),
+        None
+    );
+}
+
+#[test]
+fn folder_layout_incomplete_translation() {
+    let config = CompareConfig {
+        article_layout: ArticleLayout::Folder,
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(1, &["wiki/Article/en/content.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/fr/content.md"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en/content.md".to_string()],
+        )]
+    );
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn folder_layout_different_file_names_no_conflict() {
+    let config = CompareConfig {
+        article_layout: ArticleLayout::Folder,
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(1, &["wiki/Article/en/content.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/fr/other.md"]);
+    assert!(compare_pulls(&new_pull, &existing_pull, &config)
+        .unwrap()
+        .is_empty());
+    assert!(compare_pulls(&existing_pull, &new_pull, &config)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn article_bad_paths() {
+    assert_eq!(Article::from_file_path("README.md"), None);
+    assert_eq!(Article::from_file_path("wiki/.md"), None);
+    assert_eq!(Article::from_file_path(".md"), None);
+    assert_eq!(Article::from_file_path(""), None);
+    assert_eq!(Article::from_file_path("wiki/Article/LICENSE"), None);
+}
+
+#[test]
+fn root_files_no_conflict() {
+    let existing_pull = test::make_pull(1, &["README.md", "wiki/.md"]);
+    let new_pull = test::make_pull(2, &["README.md", "wiki/.md"]);
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+}
+
 #[test]
 fn different_paths_no_conflict() {
     let existing_pull = test::make_pull(1, &["wiki/First_article/en.md"]);
     let new_pull = test::make_pull(2, &["wiki/Second_article/en.md"]);
-    assert!(compare_pulls(&new_pull, &existing_pull).is_empty());
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
 }
 
 #[test]
 fn no_markdown_no_conflict() {
     let existing_pull = test::make_pull(1, &["wiki/First_article/img/test.png"]);
     let new_pull = test::make_pull(2, &["wiki/First_article/img/test.png"]);
-    assert!(compare_pulls(&new_pull, &existing_pull).is_empty());
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
 }
 
 #[test]
@@ -85,11 +428,12 @@ fn single_file_overlap() {
     let new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
 
     assert_eq!(
-        compare_pulls(&new_pull, &existing_pull),
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
         vec![Conflict::overlap(
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string()],
         )]
     );
@@ -117,11 +461,12 @@ fn multiple_files_overlap() {
     );
 
     assert_eq!(
-        compare_pulls(&new_pull, &existing_pull),
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
         vec![Conflict::overlap(
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/en.md".to_string(),
                 "wiki/Ranking_criteria/en.md".to_string(),
@@ -129,11 +474,12 @@ fn multiple_files_overlap() {
         )]
     );
     assert_eq!(
-        compare_pulls(&existing_pull, &new_pull),
+        compare_pulls(&existing_pull, &new_pull, &CompareConfig::default()).unwrap(),
         vec![Conflict::overlap(
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec![
                 "wiki/Article/en.md".to_string(),
                 "wiki/Ranking_criteria/en.md".to_string(),
@@ -148,11 +494,12 @@ fn existing_translation_becomes_incomplete() {
     let new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
 
     assert_eq!(
-        compare_pulls(&new_pull, &existing_pull),
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
         vec![Conflict::incomplete_translation(
             1,
             2,
             pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string(),],
         )]
     );
@@ -164,12 +511,1274 @@ fn new_translation_marked_as_incomplete() {
     let new_pull = test::make_pull(2, &["wiki/Article/ru.md"]);
 
     assert_eq!(
-        compare_pulls(&new_pull, &existing_pull),
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
         vec![Conflict::incomplete_translation(
             2,
             1,
             pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
             vec!["wiki/Article/en.md".to_string(),],
         )]
     );
 }
+
+#[test]
+fn missing_diff_is_an_error() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let mut new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
+    new_pull.diff = None;
+
+    assert!(compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).is_err());
+    assert!(compare_pulls(&existing_pull, &new_pull, &CompareConfig::default()).is_err());
+}
+
+#[test]
+fn large_pulls_overlap() {
+    let files: Vec<String> = (0..500)
+        .map(|i| format!("wiki/Article_{i}/en.md"))
+        .collect();
+    let file_refs: Vec<&str> = files.iter().map(|f| f.as_str()).collect();
+    let existing_pull = test::make_pull(1, &file_refs);
+    let new_pull = test::make_pull(2, &file_refs);
+
+    // Already in natural order.
+    let expected_files = files.clone();
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            expected_files,
+        )]
+    );
+}
+
+#[test]
+fn all_conflicts_empty() {
+    assert!(find_all_conflicts(&[], &CompareConfig::default()).is_empty());
+
+    let single_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    assert!(find_all_conflicts(&[single_pull], &CompareConfig::default()).is_empty());
+}
+
+#[test]
+fn all_conflicts_multiple_pulls() {
+    let pulls = [
+        test::make_pull(1, &["wiki/Article/en.md"]),
+        test::make_pull(2, &["wiki/Article/ru.md"]),
+        test::make_pull(3, &["wiki/Article/en.md"]),
+        test::make_pull(4, &["wiki/Other_article/en.md"]),
+    ];
+
+    assert_eq!(
+        find_all_conflicts(&pulls, &CompareConfig::default()),
+        vec![
+            Conflict::overlap(
+                3,
+                1,
+                pull_link("test/repo", 1),
+                PULL_TITLE.to_string(),
+                vec!["wiki/Article/en.md".to_string()],
+            ),
+            Conflict::incomplete_translation(
+                2,
+                1,
+                pull_link("test/repo", 1),
+                PULL_TITLE.to_string(),
+                vec!["wiki/Article/en.md".to_string()],
+            ),
+            Conflict::incomplete_translation(
+                2,
+                3,
+                pull_link("test/repo", 3),
+                PULL_TITLE.to_string(),
+                vec!["wiki/Article/en.md".to_string()],
+            ),
+        ]
+    );
+}
+
+#[test]
+fn all_conflicts_deduplicated() {
+    // The same pull listed twice must neither conflict with itself, nor produce duplicates.
+    let pulls = [
+        test::make_pull(1, &["wiki/Article/en.md"]),
+        test::make_pull(2, &["wiki/Article/en.md"]),
+        test::make_pull(2, &["wiki/Article/en.md"]),
+    ];
+
+    assert_eq!(
+        find_all_conflicts(&pulls, &CompareConfig::default()),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+fn rename_diff(old_path: &str, new_path: &str) -> String {
+    format!(
+        r#"diff --git a/{0} b/{1}
+similarity index 90%
+rename from {0}
+rename to {1}
+index 5483f282a0a..2c8c1482b97 100644
+--- a/{0}
++++ b/{1}
+@@ -1,3 +1,3 @@
+ # Article
+ 
+-Old text.
++New text."#,
+        old_path, new_path
+    )
+}
+
+#[test]
+fn renamed_article_overlaps_with_old_path() {
+    let existing_pull = test::make_pull(1, &["wiki/Old_article/en.md"]);
+    let new_pull = test::make_pull_with_diff(
+        2,
+        &rename_diff("wiki/Old_article/en.md", "wiki/New_article/en.md"),
+    );
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Old_article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn renamed_article_overlaps_with_new_path() {
+    let existing_pull = test::make_pull_with_diff(
+        1,
+        &rename_diff("wiki/Old_article/en.md", "wiki/New_article/en.md"),
+    );
+    let new_pull = test::make_pull(2, &["wiki/New_article/en.md"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/New_article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn drafts_ignored_by_default() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let mut new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
+    new_pull.draft = Some(true);
+
+    let config = CompareConfig::default();
+    assert!(compare_pulls(&new_pull, &existing_pull, &config)
+        .unwrap()
+        .is_empty());
+    assert!(compare_pulls(&existing_pull, &new_pull, &config)
+        .unwrap()
+        .is_empty());
+    assert!(find_all_conflicts(&[existing_pull, new_pull], &config).is_empty());
+}
+
+#[test]
+fn drafts_included_on_request() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let mut new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
+    new_pull.draft = Some(true);
+
+    let config = CompareConfig {
+        include_drafts: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn different_base_branches_no_conflict() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let mut new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
+    new_pull.base = test::make_ref("stable", "test/repo");
+
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn conflicts_json() {
+    let conflicts = vec![
+        Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        ),
+        Conflict::incomplete_translation(
+            3,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            Vec::new(),
+        ),
+    ];
+    let json = conflicts_to_json(&conflicts);
+    assert_eq!(
+        json,
+        r#"[{"kind":"Overlap","trigger":2,"original":1,"reference_url":"https://github.com/test/repo/pull/1","reference_title":"Update `Ranking criteria`","file_set":["wiki/Article/en.md"]},{"kind":"IncompleteTranslation","trigger":3,"original":1,"reference_url":"https://github.com/test/repo/pull/1","reference_title":"Update `Ranking criteria`","file_set":[]}]"#
+    );
+    assert_eq!(
+        serde_json::from_str::<Vec<Conflict>>(&json).unwrap(),
+        conflicts
+    );
+}
+
+#[test]
+fn same_author_skipped_on_request() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/ru.md"]);
+    let mut other_author_pull = test::make_pull(3, &["wiki/Article/en.md"]);
+    other_author_pull.user = structs::Actor {
+        id: 2,
+        login: "peppy".to_string(),
+    };
+    let pulls = [existing_pull, new_pull, other_author_pull];
+
+    // Same author conflicts are reported by default.
+    assert_eq!(
+        find_all_conflicts(&pulls, &CompareConfig::default()).len(),
+        3
+    );
+
+    let config = CompareConfig {
+        skip_same_author: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        find_all_conflicts(&pulls, &config),
+        vec![
+            Conflict::overlap(
+                3,
+                1,
+                pull_link("test/repo", 1),
+                PULL_TITLE.to_string(),
+                vec!["wiki/Article/en.md".to_string()],
+            ),
+            Conflict::incomplete_translation(
+                2,
+                3,
+                pull_link("test/repo", 3),
+                PULL_TITLE.to_string(),
+                vec!["wiki/Article/en.md".to_string()],
+            ),
+        ]
+    );
+}
+
+#[test]
+fn known_languages_validation() {
+    let config = CompareConfig {
+        known_languages: vec!["zh-tw".to_string(), "pt-br".to_string()],
+        ..Default::default()
+    };
+    for path in [
+        "wiki/Article/en.md",
+        "wiki/Article/zh-tw.md",
+        "wiki/Article/pt-br.md",
+        "wiki/Article/ZH-TW.md",
+    ] {
+        assert!(Article::from_file_path(path)
+            .unwrap()
+            .has_known_language(&config));
+    }
+    for path in ["wiki/Article/TEMPLATE.md", "wiki/Article/_index.md"] {
+        assert!(!Article::from_file_path(path)
+            .unwrap()
+            .has_known_language(&config));
+        assert!(Article::from_file_path(path)
+            .unwrap()
+            .has_known_language(&CompareConfig::default()));
+    }
+}
+
+#[test]
+fn unknown_languages_no_conflict() {
+    let config = CompareConfig {
+        known_languages: vec!["zh-tw".to_string()],
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/TEMPLATE.md"]);
+    assert!(compare_pulls(&new_pull, &existing_pull, &config)
+        .unwrap()
+        .is_empty());
+
+    let new_pull = test::make_pull(3, &["wiki/Article/zh-tw.md"]);
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            3,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn custom_extensions() {
+    let config = CompareConfig {
+        extensions: vec![".md".to_string(), ".yaml".to_string()],
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.yaml", "wiki/Other/ru.yaml"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/en.yaml", "wiki/Other/en.md"]);
+
+    // Only the .yaml pair conflicts: files with different extensions are never compared.
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.yaml".to_string()],
+        )]
+    );
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+fn deletion_diff(path: &str) -> String {
+    format!(
+        r#"diff --git a/{0} b/{0}
+deleted file mode 100644
+index 5483f282a0a..0000000000
+--- a/{0}
++++ /dev/null
+@@ -1,2 +0,0 @@
+-# Article
+-Text."#,
+        path
+    )
+}
+
+#[test]
+fn deleted_file_overlaps_with_edit() {
+    let existing_pull = test::make_pull_with_diff(1, &deletion_diff("wiki/Article/fr.md"));
+    let new_pull = test::make_pull(2, &["wiki/Article/fr.md"]);
+
+    let conflicts = compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap();
+    assert_eq!(
+        conflicts,
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/fr.md".to_string()],
+        )
+        .with_file_changes(vec![FileChange {
+            path: "wiki/Article/fr.md".to_string(),
+            trigger: ChangeKind::Modified,
+            original: ChangeKind::Deleted,
+        }])]
+    );
+    assert!(conflicts[0]
+        .to_markdown()
+        .ends_with("  - wiki/Article/fr.md is edited in #2 but deleted in #1"));
+}
+
+fn addition_diff(path: &str) -> String {
+    format!(
+        r#"diff --git a/{0} b/{0}
+new file mode 100644
+index 0000000000..2c8c1482b97
+--- /dev/null
++++ b/{0}
+@@ -0,0 +1,2 @@
++# Article
++Text."#,
+        path
+    )
+}
+
+#[test]
+fn same_file_added_by_both() {
+    let path = "wiki/New_article/en.md";
+    let existing_pull = test::make_pull_with_diff(1, &addition_diff(path));
+    let new_pull = test::make_pull_with_diff(2, &addition_diff(path));
+    let config = CompareConfig {
+        line_level_overlaps: true,
+        ..Default::default()
+    };
+
+    let conflicts = compare_pulls(&new_pull, &existing_pull, &config).unwrap();
+    let change = FileChange {
+        path: path.to_string(),
+        trigger: ChangeKind::Added,
+        original: ChangeKind::Added,
+    };
+    assert!(change.is_added_by_both());
+    assert_eq!(
+        conflicts,
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec![path.to_string()],
+        )
+        .with_file_changes(vec![change])]
+    );
+    assert!(conflicts[0]
+        .to_markdown()
+        .ends_with(r"  - wiki/New\_article/en.md is created by both #2 and #1"));
+    assert!(conflicts[0]
+        .to_plain_text()
+        .ends_with("\nwiki/New_article/en.md is created by both #2 and #1"));
+
+    // A new file at the path of an edited one is still described as two different changes.
+    let conflicts = compare_pulls(&new_pull, &test::make_pull(1, &[path]), &config).unwrap();
+    assert!(!conflicts[0].file_changes[0].is_added_by_both());
+    assert!(conflicts[0]
+        .to_markdown()
+        .ends_with("is added in #2 but edited in #1"));
+}
+
+#[test]
+fn deleted_original_affects_translation() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/fr.md"]);
+    let new_pull = test::make_pull_with_diff(2, &deletion_diff("wiki/Article/en.md"));
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap(),
+        vec![Conflict::incomplete_translation(
+            1,
+            2,
+            pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn conflict_severity() {
+    let files = vec!["wiki/Article/en.md".to_string()];
+    let translation = Conflict::incomplete_translation(
+        1,
+        2,
+        pull_link("test/repo", 2),
+        PULL_TITLE.to_string(),
+        files.clone(),
+    );
+    let overlap = Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        files.clone(),
+    );
+    let deletion = overlap.clone().with_file_changes(vec![FileChange {
+        path: files[0].clone(),
+        trigger: ChangeKind::Deleted,
+        original: ChangeKind::Modified,
+    }]);
+    let large_overlap = Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Article/en.md".to_string(); BLOCKING_FILE_COUNT + 1],
+    );
+
+    assert_eq!(translation.severity(), Severity::Info);
+    assert_eq!(overlap.severity(), Severity::Warning);
+    assert_eq!(deletion.severity(), Severity::Blocking);
+    assert_eq!(large_overlap.severity(), Severity::Blocking);
+    assert!(Severity::Info < Severity::Warning && Severity::Warning < Severity::Blocking);
+}
+
+fn two_originals_config() -> CompareConfig {
+    CompareConfig {
+        original_languages: vec!["en".to_string(), "en-gb".to_string()],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn two_originals_new_original_change() {
+    let config = two_originals_config();
+    let existing_pull = test::make_pull(1, &["wiki/Article/fr.md", "wiki/Article/de.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/en.md", "wiki/Article/en-gb.md"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            1,
+            2,
+            pull_link("test/repo", 2),
+            PULL_TITLE.to_string(),
+            vec![
+                "wiki/Article/en-gb.md".to_string(),
+                "wiki/Article/en.md".to_string()
+            ],
+        )]
+    );
+}
+
+#[test]
+fn two_originals_new_translation() {
+    let config = two_originals_config();
+    let existing_pull = test::make_pull(1, &["wiki/Article/en-gb.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/fr.md"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en-gb.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn two_originals_edited_separately() {
+    let config = two_originals_config();
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let new_pull = test::make_pull(2, &["wiki/Article/en-gb.md", "wiki/Article/fr.md"]);
+
+    // Originals don't depend on each other, but the new translation may miss changes to either of them.
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::incomplete_translation(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn natural_sort() {
+    let mut paths = vec![
+        "wiki/Article/section2/en.md",
+        "wiki/Article/section10/en.md",
+        "wiki/Article/section1/en.md",
+        "wiki/Article/section01/fr.md",
+    ];
+    paths.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(
+        paths,
+        vec![
+            "wiki/Article/section1/en.md",
+            "wiki/Article/section01/fr.md",
+            "wiki/Article/section2/en.md",
+            "wiki/Article/section10/en.md",
+        ]
+    );
+}
+
+#[test]
+fn natural_sort_in_conflicts() {
+    let files = [
+        "wiki/Article/section2/en.md",
+        "wiki/Article/section10/en.md",
+        "wiki/Article/section1/en.md",
+    ];
+    let existing_pull = test::make_pull(1, &files);
+    let new_pull = test::make_pull(2, &files);
+
+    let conflicts = compare_pulls(&new_pull, &existing_pull, &CompareConfig::default()).unwrap();
+    assert_eq!(
+        conflicts[0].file_set,
+        vec![
+            "wiki/Article/section1/en.md",
+            "wiki/Article/section2/en.md",
+            "wiki/Article/section10/en.md",
+        ]
+    );
+}
+
+#[test]
+fn check_run_summary() {
+    assert_eq!(check_run_report(&[]).0, structs::CheckConclusion::Success);
+
+    let conflicts = [Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Article/en.md".to_string()],
+    )];
+    assert_eq!(
+        check_run_report(&conflicts),
+        (
+            structs::CheckConclusion::Failure,
+            r"- [#1 Update \`Ranking criteria\`](https://github.com/test/repo/pull/1): Overlap, 1 file(s)"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn conflict_to_markdown_escaping() {
+    let c = Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        "Fix `code` in [Article]".to_string(),
+        vec!["wiki/weird_*name*/en.md".to_string()],
+    )
+    .with_file_changes(vec![FileChange {
+        path: "wiki/weird_*name*/en.md".to_string(),
+        trigger: ChangeKind::Added,
+        original: ChangeKind::Added,
+    }]);
+    let markdown = c.to_markdown();
+    assert!(markdown.contains(
+        r"- [#1 Fix \`code\` in \[Article\]](https://github.com/test/repo/pull/1), files:"
+    ));
+    // Paths are verbatim inside the code block, but escaped elsewhere.
+    assert!(markdown.contains("\n  wiki/weird_*name*/en.md\n"));
+    assert!(markdown.ends_with(r"  - wiki/weird\_\*name\*/en.md is created by both #2 and #1"));
+}
+
+#[test]
+fn case_insensitive_paths() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md", "wiki/Other/fr.md"]);
+    let new_pull = test::make_pull(2, &["wiki/article/En.md", "wiki/OTHER/EN.md"]);
+
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+
+    let config = CompareConfig {
+        case_insensitive_paths: true,
+        ..Default::default()
+    };
+    // Paths are shown as written in the pull which made the change.
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![
+            Conflict::overlap(
+                2,
+                1,
+                pull_link("test/repo", 1),
+                PULL_TITLE.to_string(),
+                vec!["wiki/article/En.md".to_string()],
+            ),
+            Conflict::incomplete_translation(
+                1,
+                2,
+                pull_link("test/repo", 2),
+                PULL_TITLE.to_string(),
+                vec!["wiki/OTHER/EN.md".to_string()],
+            ),
+        ]
+    );
+}
+
+#[test]
+fn ignored_labels() {
+    let existing_pull = test::make_pull(1, &["wiki/Article/en.md"]);
+    let mut new_pull = test::make_pull(2, &["wiki/Article/en.md"]);
+    new_pull.labels.push(structs::Label {
+        id: 1,
+        name: "No-Conflict-Check".to_string(),
+        color: "ffffff".to_string(),
+    });
+
+    let pulls = [existing_pull, new_pull];
+    assert_eq!(
+        find_all_conflicts(&pulls, &CompareConfig::default()).len(),
+        1
+    );
+    let config = CompareConfig {
+        ignore_labels: vec!["no-conflict-check".to_string()],
+        ..Default::default()
+    };
+    assert!(find_all_conflicts(&pulls, &config).is_empty());
+}
+
+fn hunk_diff(path: &str, start: usize) -> String {
+    format!(
+        r#"diff --git a/{0} b/{0}
+index 5483f282a0a..2c8c1482b97 100644
+--- a/{0}
++++ b/{0}
+@@ -{1},3 +{1},3 @@
+ context
+-old
++new
+ context"#,
+        path, start
+    )
+}
+
+fn binary_diff(path: &str) -> String {
+    format!(
+        r#"diff --git a/{0} b/{0}
+index 5483f282a0a..2c8c1482b97 100644
+Binary files a/{0} and b/{0} differ"#,
+        path
+    )
+}
+
+#[test]
+fn binary_files_compared_as_whole_files() {
+    let config = CompareConfig {
+        line_level_overlaps: true,
+        line_hints: true,
+        asset_conflicts: true,
+        ..Default::default()
+    };
+    let path = "wiki/Article/en.md";
+    let binary_pull = test::make_pull_with_diff(1, &binary_diff(path));
+    assert!(is_binary(&binary_pull.diff.as_ref().unwrap()[0]));
+
+    // No lines to compare, so any edit of the same file is an overlap. Only the text side has a line hint.
+    let text_pull = test::make_pull_with_diff(2, &hunk_diff(path, 10));
+    let hint = LineHint {
+        path: path.to_string(),
+        line: 11,
+        context: "old".to_string(),
+    };
+    assert_eq!(
+        compare_pulls(&text_pull, &binary_pull, &config).unwrap(),
+        vec![Conflict::overlap(
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec![path.to_string()],
+        )
+        .with_line_hints(vec![hint])]
+    );
+    let conflicts = compare_pulls(
+        &test::make_pull_with_diff(2, &binary_diff(path)),
+        &binary_pull,
+        &config,
+    )
+    .unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert!(conflicts[0].line_hints.is_empty());
+
+    let image = "wiki/Article/img/banner.png";
+    let conflicts = compare_pulls(
+        &test::make_pull_with_diff(2, &binary_diff(image)),
+        &test::make_pull_with_diff(1, &binary_diff(image)),
+        &config,
+    )
+    .unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].kind, ConflictType::AssetChange);
+}
+
+#[test]
+fn line_level_overlaps() {
+    let config = CompareConfig {
+        line_level_overlaps: true,
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull_with_diff(1, &hunk_diff("wiki/Article/en.md", 10));
+    let distant_pull = test::make_pull_with_diff(2, &hunk_diff("wiki/Article/en.md", 100));
+    let close_pull = test::make_pull_with_diff(3, &hunk_diff("wiki/Article/en.md", 12));
+
+    assert!(compare_pulls(&distant_pull, &existing_pull, &config)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        compare_pulls(&distant_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        compare_pulls(&close_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::overlap(
+            3,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/en.md".to_string()],
+        )]
+    );
+}
+
+#[test]
+fn line_ranges_intersection() {
+    assert!(ranges_overlap(&[(1, 5)], &[(5, 10)]));
+    assert!(ranges_overlap(&[(1, 2), (20, 30)], &[(25, 25)]));
+    assert!(!ranges_overlap(&[(1, 4)], &[(5, 10)]));
+    assert!(!ranges_overlap(&[], &[(5, 10)]));
+}
+
+#[test]
+fn excluded_paths_never_conflict() {
+    let config = CompareConfig {
+        include_paths: vec!["wiki/**".to_string(), "meta/**".to_string()],
+        exclude_paths: vec!["meta/**".to_string()],
+        ..Default::default()
+    };
+    let pulls = [
+        test::make_pull(
+            1,
+            &[
+                "wiki/Article/en.md",
+                "meta/Template/en.md",
+                "news/2023/en.md",
+            ],
+        ),
+        test::make_pull(
+            2,
+            &[
+                "wiki/Article/en.md",
+                "meta/Template/fr.md",
+                "news/2023/en.md",
+            ],
+        ),
+        test::make_pull(3, &["meta/Template/en.md", "wiki/Article/fr.md"]),
+    ];
+
+    let conflicts = find_all_conflicts(&pulls, &config);
+    assert!(!conflicts.is_empty());
+    for c in conflicts {
+        assert!(
+            c.file_set.iter().all(|f| f.starts_with("wiki/")),
+            "{:?}",
+            c.file_set
+        );
+    }
+    assert!(find_all_conflicts(&pulls, &CompareConfig::default())
+        .iter()
+        .any(|c| c.file_set.iter().any(|f| f.starts_with("meta/"))));
+}
+
+#[test]
+fn same_pull_no_conflict() {
+    let pull = test::make_pull(1, &["wiki/Article/en.md", "wiki/Other/ru.md"]);
+    assert!(compare_pulls(&pull, &pull, &CompareConfig::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn line_hints() {
+    let config = CompareConfig {
+        line_hints: true,
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull_with_diff(1, &hunk_diff("wiki/Article/ru.md", 10));
+    let new_pull = test::make_pull_with_diff(2, &hunk_diff("wiki/Article/en.md", 40));
+    let overlapping_pull = test::make_pull_with_diff(3, &hunk_diff("wiki/Article/ru.md", 20));
+
+    let conflicts = compare_pulls(&new_pull, &existing_pull, &config).unwrap();
+    assert_eq!(
+        conflicts[0].line_hints,
+        vec![LineHint {
+            path: "wiki/Article/en.md".to_string(),
+            line: 41,
+            context: "old".to_string(),
+        }]
+    );
+    assert!(conflicts[0]
+        .to_markdown()
+        .contains("\n  wiki/Article/en.md (around line 41)\n"));
+
+    let conflicts = compare_pulls(&overlapping_pull, &existing_pull, &config).unwrap();
+    assert_eq!(conflicts[0].line_hints[0].line, 21);
+    assert!(conflicts[0]
+        .to_plain_text()
+        .contains("wiki/Article/ru.md (around line 21)"));
+
+    assert!(
+        compare_pulls(&overlapping_pull, &existing_pull, &CompareConfig::default()).unwrap()[0]
+            .line_hints
+            .is_empty()
+    );
+}
+
+#[test]
+fn max_inline_files() {
+    let c = Conflict::overlap(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Article/en.md".to_string(); 20],
+    );
+    assert!(c
+        .to_markdown()
+        .contains("<details><summary>20 files</summary>"));
+
+    let collapse_all = RenderConfig {
+        max_inline_files: 0,
+        ..Default::default()
+    };
+    let one_file = Conflict {
+        file_set: vec!["wiki/Article/en.md".to_string()],
+        ..c.clone()
+    };
+    assert!(one_file
+        .to_markdown_with(&collapse_all)
+        .contains("<details><summary>1 files</summary>"));
+
+    let inline_all = RenderConfig {
+        max_inline_files: 30,
+        ..Default::default()
+    };
+    assert!(!c.to_markdown_with(&inline_all).contains("<details>"));
+}
+
+#[test]
+fn mixed_language_change() {
+    let config = CompareConfig {
+        mixed_language_advisories: true,
+        ..Default::default()
+    };
+    let pull = test::make_pull(
+        1,
+        &[
+            "wiki/Article/fr.md",
+            "wiki/Article/en.md",
+            "wiki/Article/ru.md",
+            "wiki/Other/fr.md",
+            "wiki/Third/en.md",
+        ],
+    );
+
+    assert_eq!(
+        check_pull(&pull, &config).unwrap(),
+        Some(Conflict::new(
+            ConflictType::MixedLanguageChange,
+            1,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec![
+                "wiki/Article/fr.md".to_string(),
+                "wiki/Article/ru.md".to_string()
+            ],
+        ))
+    );
+    assert_eq!(check_pull(&pull, &CompareConfig::default()).unwrap(), None);
+    let translations_only = test::make_pull(2, &["wiki/Article/fr.md", "wiki/Other/fr.md"]);
+    assert_eq!(check_pull(&translations_only, &config).unwrap(), None);
+
+    // Also reported along with conflicts between pulls.
+    let other_pull = test::make_pull(3, &["wiki/Article/fr.md"]);
+    let all = find_all_conflicts(&[pull, other_pull], &config);
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[1].kind, ConflictType::MixedLanguageChange);
+}
+
+#[test]
+fn conflicts_between_scans() {
+    let config = CompareConfig::default();
+    let previous = find_all_conflicts(
+        &[
+            test::make_pull(1, &["wiki/Article/en.md"]),
+            test::make_pull(2, &["wiki/Article/en.md"]),
+            test::make_pull(3, &["wiki/Other/en.md"]),
+            test::make_pull(4, &["wiki/Other/en.md"]),
+        ],
+        &config,
+    );
+    let current = find_all_conflicts(
+        &[
+            test::make_pull(1, &["wiki/Article/en.md", "wiki/Third/en.md"]),
+            test::make_pull(2, &["wiki/Article/en.md", "wiki/Third/en.md"]),
+            test::make_pull(3, &["wiki/Other/en.md"]),
+            test::make_pull(5, &["wiki/Other/ru.md"]),
+        ],
+        &config,
+    );
+
+    let delta = diff_conflicts(&previous, &current);
+    assert_eq!(
+        delta.added,
+        vec![Conflict::incomplete_translation(
+            5,
+            3,
+            pull_link("test/repo", 3),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Other/en.md".to_string()],
+        )]
+    );
+    // The changed file set is picked up from the current scan.
+    assert_eq!(delta.persisting.len(), 1);
+    assert_eq!(
+        delta.persisting[0].file_set,
+        vec!["wiki/Article/en.md", "wiki/Third/en.md"]
+    );
+    assert_eq!(delta.resolved.len(), 1);
+    assert_eq!(
+        (delta.resolved[0].trigger, delta.resolved[0].original),
+        (4, 3)
+    );
+
+    assert_eq!(diff_conflicts(&current, &current).persisting, current);
+    assert_eq!(diff_conflicts(&[], &[]), ConflictDelta::default());
+}
+
+#[test]
+fn default_branch_baseline() {
+    let config = CompareConfig::default();
+    let existing_pull = test::make_pull_with_diff(1, &hunk_diff("wiki/Article/en.md", 10));
+    let new_pull = test::make_pull_with_diff(2, &hunk_diff("wiki/Article/en.md", 10));
+    let conflict = compare_pulls(&new_pull, &existing_pull, &config)
+        .unwrap()
+        .remove(0);
+    let with_base =
+        |base: &str| HashMap::from([("wiki/Article/en.md".to_string(), base.to_string())]);
+
+    // Both pulls change the line which is still on the default branch.
+    let diverged = with_base("# Article\n\ncontext\nold\ncontext\n");
+    assert_eq!(
+        filter_by_baseline(
+            conflict.clone(),
+            &new_pull,
+            &existing_pull,
+            &diverged,
+            &config
+        ),
+        Some(conflict.clone())
+    );
+
+    // The default branch already has the change, so neither pull changes anything.
+    let merged = with_base("# Article\n\ncontext\nnew\ncontext\n");
+    assert_eq!(
+        filter_by_baseline(
+            conflict.clone(),
+            &new_pull,
+            &existing_pull,
+            &merged,
+            &config
+        ),
+        None
+    );
+
+    // Unknown contents and other kinds of conflicts are left alone.
+    assert_eq!(
+        filter_by_baseline(
+            conflict.clone(),
+            &new_pull,
+            &existing_pull,
+            &HashMap::new(),
+            &config
+        ),
+        Some(conflict)
+    );
+    let translation = Conflict::incomplete_translation(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Article/en.md".to_string()],
+    );
+    assert_eq!(
+        filter_by_baseline(
+            translation.clone(),
+            &new_pull,
+            &existing_pull,
+            &merged,
+            &config
+        ),
+        Some(translation)
+    );
+}
+
+#[test]
+fn asset_conflicts() {
+    let config = CompareConfig {
+        asset_conflicts: true,
+        ..Default::default()
+    };
+    let existing_pull = test::make_pull(
+        1,
+        &[
+            "wiki/Article/en.md",
+            "wiki/Article/img/banner.svg",
+            "wiki/Article/img/map.svg",
+        ],
+    );
+    let new_pull = test::make_pull(2, &["wiki/Other/en.md", "wiki/Article/img/banner.svg"]);
+
+    assert_eq!(
+        compare_pulls(&new_pull, &existing_pull, &config).unwrap(),
+        vec![Conflict::new(
+            ConflictType::AssetChange,
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec!["wiki/Article/img/banner.svg".to_string()],
+        )]
+    );
+    assert!(
+        compare_pulls(&new_pull, &existing_pull, &CompareConfig::default())
+            .unwrap()
+            .is_empty()
+    );
+
+    let c = &compare_pulls(&new_pull, &existing_pull, &config).unwrap()[0];
+    assert_eq!(c.severity(), Severity::Blocking);
+    assert!(c.to_markdown().contains(comments::ASSET_CHANGE_TEMPLATE));
+
+    // Replacing the same image, as seen in `.diff` files.
+    let image = "wiki/Article/img/banner.png";
+    assert_eq!(
+        compare_pulls(
+            &test::make_pull_with_diff(2, &binary_diff(image)),
+            &test::make_pull_with_diff(1, &binary_diff(image)),
+            &config
+        )
+        .unwrap(),
+        vec![Conflict::new(
+            ConflictType::AssetChange,
+            2,
+            1,
+            pull_link("test/repo", 1),
+            PULL_TITLE.to_string(),
+            vec![image.to_string()],
+        )]
+    );
+}
+
+#[test]
+fn localized_comments() {
+    let c = Conflict::incomplete_translation(
+        2,
+        1,
+        pull_link("test/repo", 1),
+        PULL_TITLE.to_string(),
+        vec!["wiki/Article/en.md".to_string()],
+    );
+    let in_language = |language: &str| RenderConfig {
+        language: Some(language.to_string()),
+        ..Default::default()
+    };
+    let french = c.to_markdown_with(&in_language("FR"));
+    assert!(french.contains(comments::FRENCH_TEMPLATES.incomplete_translation));
+    assert!(!french.contains(comments::INCOMPLETE_TRANSLATION_TEMPLATE));
+    assert_eq!(c.to_markdown_with(&in_language("xx")), c.to_markdown());
+
+    let config = CompareConfig::default();
+    let translation = test::make_pull(1, &["wiki/Article/fr.md", "wiki/Other/fr.md"]);
+    assert_eq!(
+        translation_language(&translation, &config),
+        Some("fr".to_string())
+    );
+    let several = test::make_pull(2, &["wiki/Article/fr.md", "wiki/Article/ru.md"]);
+    assert_eq!(translation_language(&several, &config), None);
+    let original = test::make_pull(3, &["wiki/Article/en.md"]);
+    assert_eq!(translation_language(&original, &config), None);
+}
+
+#[test]
+fn closed_pulls_skipped() {
+    let config = CompareConfig::default();
+    let mut merged = test::make_pull(1, &["wiki/Article/en.md"]);
+    merged.state = "closed".to_string();
+    merged.merged_at = Some(chrono::Utc::now());
+    let open = test::make_pull(2, &["wiki/Article/en.md"]);
+    let third = test::make_pull(3, &["wiki/Article/en.md"]);
+
+    assert!(!merged.is_open());
+    assert!(compare_pulls(&open, &merged, &config).unwrap().is_empty());
+    let all = find_all_conflicts(&[merged.clone(), open.clone(), third.clone()], &config);
+    assert_eq!(all.len(), 1);
+    assert_eq!((all[0].trigger, all[0].original), (3, 2));
+
+    // A merged pull may still be listed as open for a moment.
+    let mut just_merged = open.clone();
+    just_merged.merged_at = Some(chrono::Utc::now());
+    assert!(compare_pulls(&third, &just_merged, &config)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn stale_pulls_skipped() {
+    let mut config = CompareConfig::default();
+    let mut stale = test::make_pull(1, &["wiki/Article/en.md"]);
+    stale.updated_at = chrono::Utc::now() - chrono::Duration::days(100);
+    let fresh = test::make_pull(2, &["wiki/Article/en.md"]);
+    let third = test::make_pull(3, &["wiki/Article/en.md"]);
+    let pulls = [stale.clone(), fresh.clone(), third.clone()];
+
+    assert_eq!(compare_pulls(&fresh, &stale, &config).unwrap().len(), 1);
+    assert_eq!(find_all_conflicts(&pulls, &config).len(), 3);
+
+    config.stale_after = Some(chrono::Duration::days(90));
+    assert!(compare_pulls(&fresh, &stale, &config).unwrap().is_empty());
+    let all = find_all_conflicts(&pulls, &config);
+    assert_eq!(all.len(), 1);
+    assert_eq!((all[0].trigger, all[0].original), (3, 2));
+}
+
+#[test]
+fn too_many_files_not_compared() {
+    let files: Vec<String> = (0..5).map(|i| format!("wiki/Article_{i}/en.md")).collect();
+    let files: Vec<&str> = files.iter().map(String::as_str).collect();
+    let huge_pull = test::make_pull(1, &files);
+    let new_pull = test::make_pull(2, &["wiki/Article_0/en.md"]);
+
+    let mut config = CompareConfig::default();
+    assert_eq!(
+        compare_pulls(&new_pull, &huge_pull, &config).unwrap().len(),
+        1
+    );
+
+    config.max_files = 5;
+    assert_eq!(
+        compare_pulls(&new_pull, &huge_pull, &config).unwrap().len(),
+        1
+    );
+    config.max_files = 4;
+    assert!(compare_pulls(&new_pull, &huge_pull, &config)
+        .unwrap()
+        .is_empty());
+    assert!(compare_pulls(&huge_pull, &new_pull, &config)
+        .unwrap()
+        .is_empty());
+}
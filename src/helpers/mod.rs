@@ -5,3 +5,85 @@ pub mod conflicts;
 pub trait ToMarkdown {
     fn to_markdown(&self) -> String;
 }
+
+/// Render without any markup, for destinations which don't support Markdown (chat notifications, terminal output).
+pub trait ToPlainText {
+    fn to_plain_text(&self) -> String;
+}
+
+/// Escape characters which have special meaning in Markdown, so that arbitrary text (e.g. a pull title) is shown as is.
+pub fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '`'
+                | '*'
+                | '_'
+                | '{'
+                | '}'
+                | '['
+                | ']'
+                | '<'
+                | '>'
+                | '('
+                | ')'
+                | '#'
+                | '|'
+                | '!'
+                | '~'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A fence for a code block containing `lines`: three backticks, or more if any line contains a run of backticks
+/// that would otherwise close the block early.
+pub fn code_fence<S: AsRef<str>>(lines: &[S]) -> String {
+    let longest_run = lines
+        .iter()
+        .flat_map(|line| line.as_ref().split(|c| c != '`'))
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    "`".repeat(std::cmp::max(3, longest_run + 1))
+}
+
+/// Check if a `/`-separated path matches a glob pattern: `*` and `?` match within one path segment,
+/// and a `**` segment matches any number of segments (including none), so `wiki/**` matches everything under `wiki/`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn segment_match(pattern: &[char], s: &[char]) -> bool {
+        match pattern.split_first() {
+            None => s.is_empty(),
+            Some(('*', rest)) => (0..=s.len()).any(|i| segment_match(rest, &s[i..])),
+            Some(('?', rest)) => !s.is_empty() && segment_match(rest, &s[1..]),
+            Some((c, rest)) => s.first() == Some(c) && segment_match(rest, &s[1..]),
+        }
+    }
+
+    fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|i| segments_match(rest, &path[i..])),
+            Some((segment, rest)) => {
+                !path.is_empty()
+                    && segment_match(
+                        &segment.chars().collect::<Vec<_>>(),
+                        &path[0].chars().collect::<Vec<_>>(),
+                    )
+                    && segments_match(rest, &path[1..])
+            }
+        }
+    }
+
+    let pattern: Vec<_> = pattern.split('/').collect();
+    let path: Vec<_> = path.split('/').collect();
+    segments_match(&pattern, &path)
+}
+
+#[cfg(test)]
+#[path = "mod_test.rs"]
+pub(crate) mod tests;
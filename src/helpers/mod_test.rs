@@ -0,0 +1,38 @@
+use super::*;
+
+#[test]
+fn escape_markdown_path() {
+    assert_eq!(
+        escape_markdown("wiki/weird_*name*/en.md"),
+        r"wiki/weird\_\*name\*/en.md"
+    );
+}
+
+#[test]
+fn escape_markdown_title() {
+    assert_eq!(
+        escape_markdown("Fix `code` in [Article]"),
+        r"Fix \`code\` in \[Article\]"
+    );
+}
+
+#[test]
+fn code_fence_length() {
+    assert_eq!(code_fence(&["wiki/Article/en.md"]), "```");
+    assert_eq!(code_fence::<&str>(&[]), "```");
+    assert_eq!(code_fence(&["wiki/```/en.md", "wiki/`/en.md"]), "````");
+}
+
+#[test]
+fn glob_match_patterns() {
+    assert!(glob_match("wiki/**", "wiki/Article/en.md"));
+    assert!(glob_match("wiki/**", "wiki/Article/Sub/en.md"));
+    assert!(!glob_match("wiki/**", "meta/wiki/en.md"));
+    assert!(glob_match("**/TEMPLATE.md", "TEMPLATE.md"));
+    assert!(glob_match("**/TEMPLATE.md", "wiki/Article/TEMPLATE.md"));
+    assert!(glob_match("wiki/*/en.md", "wiki/Article/en.md"));
+    assert!(!glob_match("wiki/*/en.md", "wiki/Article/Sub/en.md"));
+    assert!(glob_match("wiki/*/??.md", "wiki/Article/ru.md"));
+    assert!(!glob_match("wiki/*/??.md", "wiki/Article/zh-tw.md"));
+    assert!(glob_match("news/2023-*", "news/2023-01-01-title.md"));
+}
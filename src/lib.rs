@@ -1,5 +1,6 @@
 pub mod config;
 pub mod controller;
+pub mod error;
 pub mod github;
 pub mod handler;
 pub mod helpers;
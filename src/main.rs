@@ -42,27 +42,9 @@ impl RequestValidator {
         Self { token }
     }
 
-    pub fn validate(&self, data: &str, signature: &str) -> Result<bool> {
-        // The signature is a string where every two letters describe a byte (high 4 bits | low 4 bits).
-        // Example: fbf26f84aa96ef919cb4b2d81e86cb9236204e48d16b6e34145d36acfd0b8d5d
-        // To convert it to bytes, read every letter as a hex digit, then combine every pair of them
-        // into a proper byte.
-        let from_hex: Vec<u8> = signature
-            .chars()
-            .map(|ch| match ch {
-                '0'..='9' => ch as u8 - b'0',
-                'a'..='f' => (ch as u8 - b'a') + 10,
-                _ => b'_', // yeah this isn't supposed to happen
-            })
-            .collect();
-        let mut bytes = Vec::new();
-        for i in 0..from_hex.len() / 2 {
-            bytes.push(from_hex[2 * i] << 4 | from_hex[2 * i + 1]);
-        }
-
-        let key = &ring::hmac::Key::new(ring::hmac::HMAC_SHA256, self.token.as_bytes());
-        let local_signature = ring::hmac::sign(key, data.as_bytes());
-        Ok(bytes == local_signature.as_ref())
+    /// Check the request body against the full `X-Hub-Signature-256` header value (`sha256=...`).
+    pub fn validate(&self, data: &str, signature_header: &str) -> bool {
+        github::verify_signature(&self.token, data.as_bytes(), signature_header)
     }
 }
 
@@ -78,23 +60,40 @@ pub async fn github_events(mut req: Request) -> viz::Result<()> {
             log::warn!("GitHub event is missing the signature header, rejecting");
             StatusCode::FORBIDDEN.into_error()
         })?;
-    let signature = &signature_header.strip_prefix("sha256=").unwrap();
 
     let body = req.text().await?;
     let validator = req
         .state::<RequestValidator>()
         .ok_or_else(|| StatusCode::INTERNAL_SERVER_ERROR.into_error())?;
-    if !validator.validate(&body, signature).unwrap() {
+    if !validator.validate(&body, &signature_header) {
+        log::warn!("GitHub event has an invalid signature, rejecting");
         return Err(StatusCode::FORBIDDEN.into_error());
     }
 
     // TODO: instead of processing requests right here, use std::sync::mspc channels -- this way we won't even need to
     // access the controller from the web server.
-    match event_type.as_str() {
-        "pull_request" => handler::pull_request_event(req, body).await,
-        "installation" => handler::installation_event(req, body).await,
-        "installation_repositories" => handler::installation_repositories_event(req, body).await,
-        _ => Ok(()),
+    let event = github::WebhookEvent::parse(&event_type, &body).map_err(|e| {
+        log::error!(
+            "Failed to deserialize a \"{}\" event coming from GitHub: {:?}. JSON: {:?}",
+            event_type,
+            e,
+            body
+        );
+        StatusCode::INTERNAL_SERVER_ERROR.into_error()
+    })?;
+    match event {
+        github::WebhookEvent::Ping(evt) => {
+            log::info!("Webhook {} is set up: {}", evt.hook_id, evt.zen);
+            Ok(())
+        }
+        github::WebhookEvent::PullRequest(evt) => handler::pull_request_event(req, *evt).await,
+        github::WebhookEvent::Installation(evt) => handler::installation_event(req, evt).await,
+        github::WebhookEvent::InstallationRepositories(evt) => {
+            handler::installation_repositories_event(req, evt).await
+        }
+        github::WebhookEvent::IssueComment(_)
+        | github::WebhookEvent::PullRequestReview(_)
+        | github::WebhookEvent::Other(_) => Ok(()),
     }
 }
 
@@ -134,6 +133,7 @@ async fn main() -> Result<()> {
 
     let private_key = std::fs::read_to_string(std::path::Path::new(&settings.github.app_key_path))
         .expect("Failed to read GitHub App private key");
+    github::validate_app_credentials(&settings.github.app_id, &private_key)?;
     let webhook_secret = settings.github.webhook_secret;
 
     let validator = RequestValidator::new(webhook_secret);
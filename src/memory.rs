@@ -46,6 +46,21 @@ impl Memory {
             .cloned()
     }
 
+    /// Diff of a pull which was stored earlier, if the pull still points to the same head commit and base branch.
+    /// Such pulls don't need their diffs downloaded again.
+    pub fn unchanged_diff(
+        &self,
+        full_repo_name: &str,
+        p: &PullRequest,
+    ) -> Option<unidiff::PatchSet> {
+        let g = self.pulls.lock().unwrap();
+        let known = g.get(full_repo_name)?.get(&p.number)?;
+        if known.head.sha != p.head.sha || known.base.ref_name != p.base.ref_name {
+            return None;
+        }
+        known.diff.clone()
+    }
+
     pub fn drop_repository(&self, full_repo_name: &str) {
         self.pulls
             .lock()
@@ -18,6 +18,29 @@ pub struct Repository {
     pub full_name: String,
     pub fork: Option<bool>,   // missing in installation events
     pub owner: Option<Actor>, // missing in installation events
+    #[serde(default)]
+    pub default_branch: Option<String>, // missing in installation events
+}
+
+// https://docs.github.com/en/rest/pulls/pulls#list-pull-requests (`state` query parameter)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PullState {
+    #[default]
+    Open,
+    Closed,
+    All,
+}
+
+impl PullState {
+    /// Value of the `state` query parameter, same as the serialized form.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PullState::Open => "open",
+            PullState::Closed => "closed",
+            PullState::All => "all",
+        }
+    }
 }
 
 // https://docs.github.com/en/rest/pulls/pulls
@@ -31,11 +54,87 @@ pub struct PullRequest {
     pub html_url: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub draft: Option<bool>,
+    /// Set once the pull is merged (its `state` is `closed` then).
+    #[serde(default)]
+    pub merged_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub base: PullRequestRef,
+    pub head: PullRequestRef,
+    #[serde(default)]
+    pub labels: Vec<Label>,
 
     #[serde(skip)]
     pub diff: Option<unidiff::PatchSet>,
 }
 
+// https://docs.github.com/en/rest/issues/labels
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Label {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+// Branch information for either end of a pull request (`base` or `head`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PullRequestRef {
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub sha: String,
+    pub repo: Option<Repository>, // missing if the head repository (fork) was deleted
+}
+
+impl PullRequest {
+    pub fn is_draft(&self) -> bool {
+        self.draft == Some(true)
+    }
+
+    /// Check if the pull is still open. Pulls are listed as open, but may be merged or closed by the time
+    /// they are compared.
+    pub fn is_open(&self) -> bool {
+        self.state == "open" && self.merged_at.is_none()
+    }
+
+    /// Full name of the repository the pull is made to, which is where it lives (its diff, comments, etc.).
+    /// Missing if the payload didn't include the repository.
+    pub fn repo_name(&self) -> Option<&str> {
+        self.base.repo.as_ref().map(|r| r.full_name.as_str())
+    }
+
+    /// Full name of the repository the changes come from, which is a fork for most contributors.
+    /// Only useful for logging: everything about the pull is requested from [`PullRequest::repo_name`].
+    pub fn head_repo_name(&self) -> Option<&str> {
+        self.head.repo.as_ref().map(|r| r.full_name.as_str())
+    }
+
+    /// Check if the changes come from another repository. Pulls whose fork was deleted count as well.
+    pub fn is_from_fork(&self) -> bool {
+        self.head_repo_name() != self.repo_name()
+    }
+
+    /// Check if the pull has a diff with at least one file. Diffs of pulls with only merge commits or binary changes
+    /// are empty, and never produce conflicts, which callers may want to handle explicitly.
+    pub fn has_loadable_diff(&self) -> bool {
+        self.diff.as_ref().is_some_and(|d| !d.is_empty())
+    }
+
+    /// Check if the pull has a label with the given name (case-insensitive, like on GitHub).
+    pub fn has_label(&self, name: &str) -> bool {
+        self.labels
+            .iter()
+            .any(|l| l.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// https://docs.github.com/en/rest/pulls/pulls#list-pull-requests-files
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PullRequestFile {
+    pub filename: String,
+    pub status: String, // added, removed, modified, renamed, copied, changed, unchanged
+    pub previous_filename: Option<String>, // only present for renamed files
+    pub patch: Option<String>, // missing for binary files and very large changes
+}
+
 // https://docs.github.com/en/developers/webhooks-and-events/webhooks/webhook-events-and-payloads#pull_request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PullRequestEvent {
@@ -47,6 +146,69 @@ pub struct PullRequestEvent {
     pub sender: Actor,
 }
 
+// https://docs.github.com/en/webhooks-and-events/webhooks/webhook-events-and-payloads#pull_request_review
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PullRequestReviewEvent {
+    pub action: String, // submitted, edited, dismissed
+    pub review: Review,
+    pub pull_request: PullRequest,
+    pub repository: Repository,
+    pub installation: InstallationIdWrapper,
+    pub sender: Actor,
+}
+
+// https://docs.github.com/en/rest/pulls/reviews
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Review {
+    pub id: i64,
+    pub user: Actor,
+    pub body: Option<String>,
+    pub state: String, // approved, changes_requested, commented, dismissed (lowercase in webhooks, uppercase in the API)
+    pub submitted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// https://docs.github.com/en/webhooks-and-events/webhooks/webhook-events-and-payloads#issue_comment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssueCommentEvent {
+    pub action: String, // created, edited, deleted
+    pub comment: IssueComment,
+    pub issue: Issue,
+    pub repository: Repository,
+    pub installation: InstallationIdWrapper,
+    pub sender: Actor,
+}
+
+// Issues and pull requests share numbering and comments; pull requests have `pull_request` set
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Issue {
+    pub number: i32,
+    pub title: String,
+    pub state: String,
+    pub user: Actor,
+    pub pull_request: Option<IssuePullRequestLinks>,
+}
+
+impl Issue {
+    pub fn is_pull_request(&self) -> bool {
+        self.pull_request.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssuePullRequestLinks {
+    pub url: String,
+    pub html_url: String,
+}
+
+// https://docs.github.com/en/webhooks/webhook-events-and-payloads#ping
+// Sent once when a webhook is created, to check that it's reachable.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PingEvent {
+    pub zen: String,
+    pub hook_id: i64,
+    pub repository: Option<Repository>, // missing for app and organization webhooks
+}
+
 // https://docs.github.com/webhooks-and-events/webhooks/webhook-events-and-payloads#installation
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InstallationEvent {
@@ -59,7 +221,7 @@ pub struct InstallationEvent {
 }
 
 // https://docs.github.com/webhooks-and-events/webhooks/webhook-events-and-payloads#installation_repositories
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct InstallationRepositoriesEvent {
     pub action: String,
     pub installation: Installation,
@@ -83,6 +245,27 @@ pub struct Installation {
 
     #[serde(default)]
     pub repositories: Vec<Repository>,
+
+    /// Set if the installation is suspended, in which case its tokens can't be used.
+    #[serde(default)]
+    pub suspended_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Installation {
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+
+    /// Update the list of repositories available to the installation (see [`InstallationRepositoriesEvent`]).
+    pub fn apply_repository_changes(&mut self, added: &[Repository], removed: &[Repository]) {
+        self.repositories
+            .retain(|r| !removed.iter().any(|removed_repo| removed_repo.id == r.id));
+        for repo in added {
+            if !self.repositories.iter().any(|r| r.id == repo.id) {
+                self.repositories.push(repo.clone());
+            }
+        }
+    }
 }
 
 // https://docs.github.com/en/rest/reference/apps#create-an-installation-access-token-for-an-app
@@ -117,6 +300,81 @@ pub struct PostIssueComment {
     pub body: String,
 }
 
+// https://docs.github.com/en/rest/issues/comments#create-an-issue-comment (response)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssueCommentResponse {
+    pub id: i64,
+    pub body: String,
+    pub user: Actor,
+    pub html_url: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+// https://docs.github.com/en/rest/reactions/reactions#about-reactions
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionContent {
+    #[serde(rename = "+1")]
+    ThumbsUp,
+    #[serde(rename = "-1")]
+    ThumbsDown,
+    #[serde(rename = "laugh")]
+    Laugh,
+    #[serde(rename = "confused")]
+    Confused,
+    #[serde(rename = "heart")]
+    Heart,
+    #[serde(rename = "hooray")]
+    Hooray,
+    #[serde(rename = "rocket")]
+    Rocket,
+    #[serde(rename = "eyes")]
+    Eyes,
+}
+
+// https://docs.github.com/en/rest/reactions/reactions#create-reaction-for-an-issue
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostReaction {
+    pub content: ReactionContent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reaction {
+    pub id: i64,
+    pub content: ReactionContent,
+}
+
+// https://docs.github.com/en/rest/checks/runs#create-a-check-run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Neutral,
+    Failure,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostCheckRun {
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    pub conclusion: CheckConclusion,
+    pub output: CheckRunOutput,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckRunOutput {
+    pub title: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckRun {
+    pub id: i64,
+    pub head_sha: String,
+    pub conclusion: Option<CheckConclusion>, // missing until the run is completed
+    pub html_url: Option<String>,
+}
+
 // https://docs.github.com/en/rest/apps/apps#get-the-authenticated-app
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct App {
@@ -125,3 +383,125 @@ pub struct App {
     pub owner: Actor,
     pub name: String,
 }
+
+// https://docs.github.com/en/rest/rate-limit/rate-limit#get-rate-limit-status-for-the-authenticated-user
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: i64,
+    pub remaining: i64,
+    pub used: i64,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub reset: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RateLimitResponse {
+    pub rate: RateLimit,
+}
+
+// https://docs.github.com/en/graphql/guides/forming-calls-with-graphql#communicating-with-graphql
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphQLRequest {
+    pub query: String,
+    pub variables: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphQLResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphQLError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphQLError {
+    pub message: String,
+}
+
+// https://docs.github.com/en/graphql/guides/using-pagination-in-the-graphql-api
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLPageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLConnection<T> {
+    pub nodes: Vec<T>,
+    pub page_info: Option<GraphQLPageInfo>, // only present if requested
+}
+
+impl<T> GraphQLConnection<T> {
+    /// Check if the nodes are all there is, i.e. there are no more pages (or paging info wasn't requested).
+    pub fn is_complete(&self) -> bool {
+        !self.page_info.as_ref().is_some_and(|p| p.has_next_page)
+    }
+}
+
+// Data of `PULLS_WITH_FILES_QUERY`, see `Client::pulls_with_files_graphql`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLPullsWithFiles {
+    pub repository: GraphQLRepositoryPulls,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLRepositoryPulls {
+    pub pull_requests: GraphQLConnection<GraphQLPullRequest>,
+}
+
+// https://docs.github.com/en/graphql/reference/objects#pullrequest
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLPullRequest {
+    pub database_id: i64,
+    pub number: i32,
+    pub state: String, // OPEN, CLOSED, MERGED
+    pub title: String,
+    pub url: String,
+    pub author: Option<GraphQLActor>, // missing if the account was deleted
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub is_draft: bool,
+    pub merged_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub base_ref_name: String,
+    pub base_ref_oid: String,
+    pub head_ref_name: String,
+    pub head_ref_oid: String,
+    pub base_repository: Option<GraphQLRepository>,
+    pub head_repository: Option<GraphQLRepository>, // missing if the fork was deleted
+    pub labels: Option<GraphQLConnection<GraphQLLabel>>,
+    pub files: Option<GraphQLConnection<GraphQLChangedFile>>, // missing if there are too many changes
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLActor {
+    pub login: String,
+    pub database_id: Option<i64>, // only for users and bots
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLRepository {
+    pub database_id: i64,
+    pub name: String,
+    pub name_with_owner: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphQLLabel {
+    pub name: String,
+    pub color: String,
+}
+
+// https://docs.github.com/en/graphql/reference/objects#pullrequestchangedfile
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQLChangedFile {
+    pub path: String,
+    pub change_type: String, // ADDED, CHANGED, COPIED, DELETED, MODIFIED, RENAMED
+}